@@ -0,0 +1,307 @@
+use crate::convert::{detect_format, PuzzleFile};
+use crate::parser::parse_puzzle;
+use crate::solver::{solve, Outcome, SolverOptions, Stats};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// How solving one of the puzzles went
+#[derive(Debug, Clone)]
+pub enum BatchOutcome {
+    Solved,
+    Unsolvable,
+    TimedOut,
+    // The file couldn't be read or parsed
+    Error(String),
+}
+
+impl BatchOutcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BatchOutcome::Solved => "solved",
+            BatchOutcome::Unsolvable => "unsolvable",
+            BatchOutcome::TimedOut => "timeout",
+            BatchOutcome::Error(_) => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchResult {
+    pub path: PathBuf,
+    pub outcome: BatchOutcome,
+    // Only there if the puzzle could be parsed
+    pub stats: Option<Stats>,
+}
+
+// Find the puzzle files to solve
+// Directories are searched (all the way down), and the file names in the paths can contain the wildcards
+// * (anything) and ? (any single character). Only files that look like puzzles are kept, so anything else lying
+// around (pictures, solutions...) is skipped
+pub fn find_puzzles(paths: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut puzzles = vec![];
+    for path in paths.iter() {
+        let matches = expand_wildcards(path);
+        if matches.is_empty() {
+            return Err(format!("Nothing found at {}", path));
+        }
+        for found in matches {
+            if found.is_dir() {
+                search_directory(&found, &mut puzzles)?;
+            } else if looks_like_puzzle(&found) {
+                puzzles.push(found);
+            }
+        }
+    }
+    if puzzles.is_empty() {
+        return Err(format!("No puzzles found at {}", paths.join(", ")));
+    }
+    Ok(puzzles)
+}
+
+fn looks_like_puzzle(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|input| detect_format(&input).is_some())
+}
+
+// Every path matching the given one, which may contain wildcards
+fn expand_wildcards(path: &str) -> Vec<PathBuf> {
+    let mut matches = vec![PathBuf::new()];
+    for component in Path::new(path).components() {
+        let name = component.as_os_str().to_string_lossy();
+        if !name.contains(['*', '?']) {
+            matches = matches
+                .into_iter()
+                .map(|found| found.join(component))
+                .collect();
+            continue;
+        }
+
+        let mut expanded = vec![];
+        for found in matches {
+            let directory = if found.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                found.as_path()
+            };
+            if let Ok(entries) = fs::read_dir(directory) {
+                let mut names: Vec<String> = entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .filter(|entry_name| wildcard_match(&name, entry_name))
+                    .collect();
+                names.sort();
+                expanded.extend(names.iter().map(|entry_name| found.join(entry_name)));
+            }
+        }
+        matches = expanded;
+    }
+    matches.retain(|found| found.exists());
+    matches
+}
+
+// Does the name match the pattern, where * matches anything and ? matches any single character?
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // matched[j] is whether the pattern so far matches the first j characters of the name
+    let mut matched = vec![false; name.len() + 1];
+    matched[0] = true;
+    for p in pattern.iter() {
+        if *p == '*' {
+            for j in 1..=name.len() {
+                matched[j] = matched[j] || matched[j - 1];
+            }
+        } else {
+            for j in (1..=name.len()).rev() {
+                matched[j] = matched[j - 1] && (*p == '?' || *p == name[j - 1]);
+            }
+            matched[0] = false;
+        }
+    }
+    matched[name.len()]
+}
+
+// Add every puzzle file in the directory (and the directories in it) to the list, in order
+fn search_directory(directory: &Path, puzzles: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(directory)
+        .map_err(|why| format!("couldn't read {}: {}", directory.display(), why))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            search_directory(&path, puzzles)?;
+        } else if looks_like_puzzle(&path) {
+            puzzles.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Solve a single puzzle file, without printing anything
+pub fn solve_file(path: &Path, options: &SolverOptions) -> BatchResult {
+    let puzzle = fs::read_to_string(path)
+        .map_err(|why| format!("couldn't read {}: {}", path.display(), why))
+        .and_then(|input| PuzzleFile::read(&input))
+        .and_then(|puzzle_file| parse_puzzle(&puzzle_file.to_text()));
+    let puzzle = match puzzle {
+        Err(why) => {
+            return BatchResult {
+                path: path.to_path_buf(),
+                outcome: BatchOutcome::Error(why),
+                stats: None,
+            }
+        }
+        Ok(puzzle) => puzzle,
+    };
+
+    let result = solve(puzzle, options, &mut ());
+    BatchResult {
+        path: path.to_path_buf(),
+        outcome: match result.outcome {
+            Outcome::Solved(_) => BatchOutcome::Solved,
+            Outcome::Unsolvable => BatchOutcome::Unsolvable,
+            // Either way, it gave up before finding an answer
+            Outcome::TimedOut | Outcome::Cancelled(_) | Outcome::Abandoned(_) => {
+                BatchOutcome::TimedOut
+            }
+        },
+        stats: Some(result.stats),
+    }
+}
+
+// Solve all the puzzles, with up to the given number at once
+// Each result is passed to on_result as soon as it's known, but the results come back in the same order as the paths
+pub fn solve_batch<F>(
+    paths: &[PathBuf],
+    options: &SolverOptions,
+    jobs: usize,
+    on_result: F,
+) -> Vec<BatchResult>
+where
+    F: Fn(&BatchResult) + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<BatchResult>>> = Mutex::new(vec![None; paths.len()]);
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, paths.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                if index >= paths.len() {
+                    break;
+                }
+                let result = solve_file(&paths[index], options);
+                on_result(&result);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+// A table of how each puzzle went, with a total at the bottom
+pub fn summary_table(results: &[BatchResult]) -> String {
+    let width = results
+        .iter()
+        .map(|result| result.path.display().to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max("Puzzle".len());
+    let mut table = format!(
+        "{:<width$}  {:<10}  {:>10}  {:>12}\n",
+        "Puzzle",
+        "Outcome",
+        "Seconds",
+        "States",
+        width = width
+    );
+    for result in results.iter() {
+        let (seconds, states) = match &result.stats {
+            Some(stats) => (
+                format!("{:.3}", stats.elapsed.as_secs_f64()),
+                stats.states_visited.to_string(),
+            ),
+            None => (String::from("-"), String::from("-")),
+        };
+        table.push_str(&format!(
+            "{:<width$}  {:<10}  {:>10}  {:>12}\n",
+            result.path.display(),
+            result.outcome.name(),
+            seconds,
+            states,
+            width = width
+        ));
+    }
+
+    let count = |name: &str| {
+        results
+            .iter()
+            .filter(|result| result.outcome.name() == name)
+            .count()
+    };
+    let total: f64 = results
+        .iter()
+        .filter_map(|result| result.stats.as_ref())
+        .map(|stats| stats.elapsed.as_secs_f64())
+        .sum();
+    table.push_str(&format!(
+        "\nSolved {} of {} ({} unsolvable, {} timed out, {} errors) in {:.3} seconds of solving\n",
+        count("solved"),
+        results.len(),
+        count("unsolvable"),
+        count("timeout"),
+        count("error"),
+        total
+    ));
+    for result in results.iter() {
+        if let BatchOutcome::Error(why) = &result.outcome {
+            table.push_str(&format!("{}: {}\n", result.path.display(), why));
+        }
+    }
+    table
+}
+
+// The results as CSV, one row per puzzle
+pub fn to_csv(results: &[BatchResult]) -> String {
+    let mut csv = String::from(
+        "puzzle,outcome,seconds,states_visited,states_created,max_frontier,children_discarded\n",
+    );
+    for result in results.iter() {
+        let stats = match &result.stats {
+            Some(stats) => format!(
+                "{:.6},{},{},{},{}",
+                stats.elapsed.as_secs_f64(),
+                stats.states_visited,
+                stats.states_created,
+                stats.max_frontier,
+                stats.children_discarded
+            ),
+            None => String::from(",,,,"),
+        };
+        csv.push_str(&format!(
+            "{},{},{}\n",
+            csv_field(&result.path.display().to_string()),
+            result.outcome.name(),
+            stats
+        ));
+    }
+    csv
+}
+
+// Quote the field if it has anything in it that would break up the row
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
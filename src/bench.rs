@@ -0,0 +1,263 @@
+use crate::batch::{solve_file, BatchOutcome};
+use crate::solver::{Backend, Heuristic, Moves, SolverOptions, Stats};
+use std::path::PathBuf;
+
+// A way of solving puzzles to compare against the others
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchConfig {
+    pub backend: Backend,
+    pub heuristic: Heuristic,
+    pub moves: Moves,
+}
+
+// How one puzzle went with one config, over all the runs
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub path: PathBuf,
+    pub config: BenchConfig,
+    pub outcome: BatchOutcome,
+    // The time taken by each run, in seconds
+    pub times: Vec<f64>,
+    // The stats from the last run (the search is the same every time, so only the time changes)
+    pub stats: Option<Stats>,
+}
+
+impl BenchResult {
+    pub fn median_time(&self) -> f64 {
+        median(&self.times)
+    }
+
+    pub fn discard_ratio(&self) -> f64 {
+        self.stats
+            .as_ref()
+            .map_or(0.0, |stats| stats.discard_ratio())
+    }
+}
+
+// An earlier result to compare against, read from a baseline file
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaselineEntry {
+    pub puzzle: String,
+    pub solver: String,
+    pub heuristic: String,
+    pub moves: String,
+    pub outcome: String,
+    pub median_time: f64,
+    pub states_visited: u64,
+    pub max_frontier: usize,
+    pub discard_ratio: f64,
+}
+
+// The columns of a baseline file, followed by the fraction of the discarded states thrown away for each reason
+const BASELINE_HEADER: &str = "puzzle,solver,heuristic,moves,outcome,median_seconds,states_visited,max_frontier,discard_ratio,no_children,dead_end,pools,blocked,region";
+
+fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let middle = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
+// Solve every puzzle with every config the given number of times
+// on_result is called with each result as soon as it's known
+pub fn run_bench<F>(
+    paths: &[PathBuf],
+    configs: &[BenchConfig],
+    runs: usize,
+    options: &SolverOptions,
+    on_result: F,
+) -> Vec<BenchResult>
+where
+    F: Fn(&BenchResult),
+{
+    let mut results = vec![];
+    for path in paths.iter() {
+        for config in configs.iter() {
+            let options = SolverOptions {
+                backend: config.backend,
+                heuristic: config.heuristic,
+                moves: config.moves,
+                ..options.clone()
+            };
+            let mut result = BenchResult {
+                path: path.clone(),
+                config: *config,
+                outcome: BatchOutcome::Solved,
+                times: vec![],
+                stats: None,
+            };
+            for _ in 0..runs.max(1) {
+                let run = solve_file(path, &options);
+                result.outcome = run.outcome;
+                result.stats = run.stats;
+                match &result.stats {
+                    Some(stats) => result.times.push(stats.elapsed.as_secs_f64()),
+                    // No point running it again if it can't even be read
+                    None => break,
+                }
+            }
+            on_result(&result);
+            results.push(result);
+        }
+    }
+    results
+}
+
+// The results side by side, along with how they changed from the baseline (if there is one)
+pub fn bench_table(results: &[BenchResult], baseline: Option<&[BaselineEntry]>) -> String {
+    let width = results
+        .iter()
+        .map(|result| result.path.display().to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max("Puzzle".len());
+    let mut table = format!(
+        "{:<width$}  {:<8}  {:<12}  {:<11}  {:<10}  {:>10}  {:>10}  {:>8}  {:>9}",
+        "Puzzle",
+        "Solver",
+        "Heuristic",
+        "Moves",
+        "Outcome",
+        "Median s",
+        "States",
+        "Frontier",
+        "Discarded",
+        width = width
+    );
+    if baseline.is_some() {
+        table.push_str(&format!("  {:>12}  {:>12}", "Time change", "State change"));
+    }
+    table.push('\n');
+
+    for result in results.iter() {
+        let (states, frontier) = match &result.stats {
+            Some(stats) => (
+                stats.states_visited.to_string(),
+                stats.max_frontier.to_string(),
+            ),
+            None => (String::from("-"), String::from("-")),
+        };
+        table.push_str(&format!(
+            "{:<width$}  {:<8}  {:<12}  {:<11}  {:<10}  {:>10.4}  {:>10}  {:>8}  {:>8.1}%",
+            result.path.display(),
+            result.config.backend.name(),
+            result.config.heuristic.name(),
+            result.config.moves.name(),
+            result.outcome.name(),
+            result.median_time(),
+            states,
+            frontier,
+            result.discard_ratio() * 100.0,
+            width = width
+        ));
+
+        if let Some(baseline) = baseline {
+            let puzzle = result.path.display().to_string();
+            let entry = baseline.iter().find(|entry| {
+                entry.puzzle == puzzle
+                    && entry.solver == result.config.backend.name()
+                    && entry.heuristic == result.config.heuristic.name()
+                    && entry.moves == result.config.moves.name()
+            });
+            let (time_change, state_change) = match (entry, &result.stats) {
+                (Some(entry), Some(stats)) => (
+                    change(entry.median_time, result.median_time()),
+                    change(entry.states_visited as f64, stats.states_visited as f64),
+                ),
+                _ => (String::from("new"), String::from("new")),
+            };
+            table.push_str(&format!("  {:>12}  {:>12}", time_change, state_change));
+        }
+        table.push('\n');
+    }
+    table
+}
+
+// The change from one value to the other, as a percentage
+fn change(before: f64, after: f64) -> String {
+    if before == 0.0 {
+        return if after == 0.0 {
+            String::from("+0.0%")
+        } else {
+            String::from("-")
+        };
+    }
+    format!("{:+.1}%", (after - before) / before * 100.0)
+}
+
+// The results as a baseline file, to compare later runs against
+pub fn to_baseline(results: &[BenchResult]) -> String {
+    let mut baseline = format!("{}\n", BASELINE_HEADER);
+    for result in results.iter() {
+        let stats = result.stats.clone().unwrap_or_default();
+        baseline.push_str(&format!(
+            "{},{},{},{},{},{:.6},{},{},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6}\n",
+            result.path.display(),
+            result.config.backend.name(),
+            result.config.heuristic.name(),
+            result.config.moves.name(),
+            result.outcome.name(),
+            result.median_time(),
+            stats.states_visited,
+            stats.max_frontier,
+            result.discard_ratio(),
+            stats.discarded_for(stats.discarded_no_children),
+            stats.discarded_for(stats.discarded_dead_end),
+            stats.discarded_for(stats.discarded_pools),
+            stats.discarded_for(stats.discarded_blocked),
+            stats.discarded_for(stats.discarded_cc)
+        ));
+    }
+    baseline
+}
+
+// Read a baseline file written by to_baseline
+// Baselines from before there was a choice of moves don't have a moves column, and always moved an endpoint
+pub fn read_baseline(input: &str) -> Result<Vec<BaselineEntry>, String> {
+    let has_moves = input
+        .lines()
+        .next()
+        .is_some_and(|header| header.split(',').any(|column| column == "moves"));
+    let columns = BASELINE_HEADER.split(',').count() - usize::from(!has_moves);
+
+    let mut entries = vec![];
+    for (number, line) in input.lines().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let bad_line = || {
+            format!(
+                "line {} of the baseline isn't a benchmark result",
+                number + 1
+            )
+        };
+        // The puzzle's path comes first, and may have commas in it, so count the other columns from the end
+        let mut fields: Vec<&str> = line.rsplitn(columns, ',').collect();
+        if fields.len() != columns {
+            return Err(bad_line());
+        }
+        fields.reverse();
+        if !has_moves {
+            fields.insert(3, Moves::Endpoint.name());
+        }
+        entries.push(BaselineEntry {
+            puzzle: fields[0].to_string(),
+            solver: fields[1].to_string(),
+            heuristic: fields[2].to_string(),
+            moves: fields[3].to_string(),
+            outcome: fields[4].to_string(),
+            median_time: fields[5].parse().map_err(|_| bad_line())?,
+            states_visited: fields[6].parse().map_err(|_| bad_line())?,
+            max_frontier: fields[7].parse().map_err(|_| bad_line())?,
+            discard_ratio: fields[8].parse().map_err(|_| bad_line())?,
+        });
+    }
+    Ok(entries)
+}
@@ -0,0 +1,486 @@
+use crate::parser::{flow_label, parse_puzzle, PATHS_SECTION, PUZZLE_TYPES};
+
+// The formats a puzzle can be read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    // The regular puzzle file format, starting with the type of puzzle
+    Text,
+    // The JSON written by convert
+    Json,
+    // One of the game's level packs: a level per line, each with the size of the board and the path of every flow
+    LevelPack,
+}
+
+// Work out which format the input is in, from what's in it
+pub fn detect_format(input: &str) -> Option<InputFormat> {
+    let first_line = input
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("//"))?;
+    if PUZZLE_TYPES.contains(&first_line.to_uppercase().as_str()) {
+        Some(InputFormat::Text)
+    } else if first_line.starts_with('{') {
+        Some(InputFormat::Json)
+    } else if first_line
+        .split_once(';')
+        .is_some_and(|(header, _)| parse_level_header(header).is_some())
+    {
+        Some(InputFormat::LevelPack)
+    } else {
+        None
+    }
+}
+
+// A puzzle file, split into its parts, with comments, trailing whitespace and trailing blank lines taken out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PuzzleFile {
+    pub puzzle_type: String,
+    pub board: Vec<String>,
+    // The pre-drawn paths, if there are any
+    pub paths: Option<Vec<String>>,
+}
+
+impl PuzzleFile {
+    // Read a puzzle in any of the input formats (the first level, for a level pack), checking that it's a valid
+    // puzzle along the way
+    pub fn read(input: &str) -> Result<PuzzleFile, String> {
+        PuzzleFile::read_level(input, 1)
+    }
+
+    // The same as read, but for a level pack, read the level at the given position in the pack (counting from 1)
+    pub fn read_level(input: &str, level: usize) -> Result<PuzzleFile, String> {
+        let input = input.trim_start_matches('\u{feff}');
+        let puzzle_file = match detect_format(input) {
+            Some(InputFormat::Text) => PuzzleFile::from_text(input),
+            Some(InputFormat::Json) => PuzzleFile::from_json(input)?,
+            Some(InputFormat::LevelPack) => PuzzleFile::from_level_pack(input, level)?,
+            None => {
+                return Err(format!(
+                    "the format isn't recognized (expected a puzzle starting with one of {}, JSON or a level pack)",
+                    PUZZLE_TYPES.join(", ")
+                ))
+            }
+        };
+        parse_puzzle(&puzzle_file.to_text())?;
+        Ok(puzzle_file)
+    }
+
+    // Split up a puzzle in the regular text format
+    fn from_text(input: &str) -> PuzzleFile {
+        let mut lines = input
+            .split('\n')
+            .map(str::trim_end)
+            .filter(|line| !line.starts_with("//"));
+        let puzzle_type = lines
+            .by_ref()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("")
+            .trim()
+            .to_uppercase();
+        let mut board = vec![];
+        let mut paths: Option<Vec<String>> = None;
+        for line in lines {
+            match paths.as_mut() {
+                Some(paths) => paths.push(line.to_string()),
+                None if line == PATHS_SECTION => paths = Some(vec![]),
+                None => board.push(line.to_string()),
+            }
+        }
+
+        drop_trailing_blank_lines(&mut board);
+        if let Some(paths) = paths.as_mut() {
+            drop_trailing_blank_lines(paths);
+        }
+        PuzzleFile {
+            puzzle_type,
+            board,
+            paths,
+        }
+    }
+
+    // Read the JSON written by to_json
+    fn from_json(input: &str) -> Result<PuzzleFile, String> {
+        let json = JsonParser::new(input).parse_document()?;
+        let lines = |key: &str| -> Result<Option<Vec<String>>, String> {
+            match json.get(key) {
+                None => Ok(None),
+                Some(Json::Array(items)) => items
+                    .iter()
+                    .map(|item| match item {
+                        Json::String(line) => Ok(line.trim_end().to_string()),
+                        _ => Err(format!("\"{}\" must be a list of lines", key)),
+                    })
+                    .collect::<Result<Vec<String>, String>>()
+                    .map(Some),
+                Some(_) => Err(format!("\"{}\" must be a list of lines", key)),
+            }
+        };
+
+        let puzzle_type = match json.get("type") {
+            Some(Json::String(puzzle_type)) => puzzle_type.trim().to_uppercase(),
+            _ => return Err(String::from("\"type\" is missing")),
+        };
+        if !PUZZLE_TYPES.contains(&puzzle_type.as_str()) {
+            return Err(format!("unknown type of puzzle {}", puzzle_type));
+        }
+        let board = lines("board")?.ok_or_else(|| String::from("\"board\" is missing"))?;
+        Ok(PuzzleFile {
+            puzzle_type,
+            board,
+            paths: lines("paths")?,
+        })
+    }
+
+    // Read a level from one of the game's level packs, e.g. "5,0,1,2;0,1,2;10,5,6,7"
+    // The first part of each line is the size of the board (a number, or width:height), two numbers the solver
+    // doesn't need (the second being the level number) and the number of flows
+    // Each part after that is a flow, as the list of the cells along its path (counted across each row in turn),
+    // the first and last of which are its dots. The paths themselves are the solution, so are left out
+    fn from_level_pack(input: &str, level: usize) -> Result<PuzzleFile, String> {
+        let levels: Vec<&str> = input
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("//"))
+            .collect();
+        let line = match level.checked_sub(1).and_then(|i| levels.get(i)) {
+            Some(line) => line,
+            None => {
+                return Err(format!(
+                    "there's no level {} (the pack has {} levels)",
+                    level,
+                    levels.len()
+                ))
+            }
+        };
+
+        let mut parts = line.trim_end_matches(';').split(';');
+        let (width, height, num_flows) = parse_level_header(parts.next().unwrap())
+            .ok_or_else(|| format!("level {} doesn't start with the size of the board", level))?;
+        let mut grid = vec![vec![String::from("."); width]; height];
+        let flows: Vec<&str> = parts.collect();
+        if flows.len() != num_flows {
+            return Err(format!(
+                "level {} should have {} flows, but has {}",
+                level,
+                num_flows,
+                flows.len()
+            ));
+        }
+        for (index, flow) in flows.iter().enumerate() {
+            let cells = flow
+                .split(',')
+                .map(|cell| cell.trim().parse::<usize>())
+                .collect::<Result<Vec<usize>, _>>()
+                .map_err(|_| format!("level {} has a flow that isn't a list of cells", level))?;
+            let label = flow_label(index);
+            for dot in [cells.first(), cells.last()].into_iter().flatten() {
+                if *dot >= width * height {
+                    return Err(format!("level {} has a cell off the board", level));
+                }
+                grid[dot / width][dot % width] = label.clone();
+            }
+        }
+
+        // Lay the board out with every cell joined to its neighbors
+        let mut board = vec![];
+        for (row, cells) in grid.iter().enumerate() {
+            if row > 0 {
+                board.push(vec!["|"; width].join(" "));
+            }
+            board.push(cells.join("-"));
+        }
+        Ok(PuzzleFile {
+            puzzle_type: String::from("STANDARD"),
+            board,
+            paths: None,
+        })
+    }
+
+    // The puzzle in the regular text format
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", self.puzzle_type);
+        for line in self.board.iter() {
+            text.push_str(line);
+            text.push('\n');
+        }
+        if let Some(paths) = &self.paths {
+            text.push_str(PATHS_SECTION);
+            text.push('\n');
+            for line in paths.iter() {
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    // The puzzle as JSON, with the board (and paths) as lists of lines, e.g. for other tools to read
+    pub fn to_json(&self) -> String {
+        let mut json = format!("{{\n  \"type\": {},\n", json_string(&self.puzzle_type));
+        json.push_str(&format!("  \"board\": {}", json_lines(&self.board)));
+        if let Some(paths) = &self.paths {
+            json.push_str(&format!(",\n  \"paths\": {}", json_lines(paths)));
+        }
+        json.push_str("\n}\n");
+        json
+    }
+}
+
+// Read the first part of a level pack line (up to the first ';'), returning the width and height of the board and
+// the number of flows
+fn parse_level_header(header: &str) -> Option<(usize, usize, usize)> {
+    let fields: Vec<&str> = header.split(',').map(str::trim).collect();
+    if fields.len() != 4
+        || fields[1..]
+            .iter()
+            .any(|field| field.parse::<usize>().is_err())
+    {
+        return None;
+    }
+    let (width, height) = match fields[0].split_once(':') {
+        Some((width, height)) => (width.parse().ok()?, height.parse().ok()?),
+        None => (fields[0].parse().ok()?, fields[0].parse().ok()?),
+    };
+    if width == 0 || height == 0 {
+        return None;
+    }
+    Some((width, height, fields[3].parse().ok()?))
+}
+
+fn drop_trailing_blank_lines(lines: &mut Vec<String>) {
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+}
+
+// A list of lines, one per line
+fn json_lines(lines: &[String]) -> String {
+    let items: Vec<String> = lines
+        .iter()
+        .map(|line| format!("    {}", json_string(line)))
+        .collect();
+    format!("[\n{}\n  ]", items.join(",\n"))
+}
+
+// Quote the text, escaping anything that can't go in a JSON string as is
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Just enough JSON to read puzzles back in
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    // The value of the given key, if this is an object with it
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl JsonParser {
+    fn new(input: &str) -> JsonParser {
+        JsonParser {
+            chars: input.chars().collect(),
+            position: 0,
+        }
+    }
+
+    // A single value, with nothing but whitespace after it
+    fn parse_document(&mut self) -> Result<Json, String> {
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if self.position < self.chars.len() {
+            return Err(self.error("the end of the JSON"));
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.get(self.position) {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => Ok(Json::String(self.parse_string()?)),
+            Some('t') => self.parse_word("true", Json::Bool(true)),
+            Some('f') => self.parse_word("false", Json::Bool(false)),
+            Some('n') => self.parse_word("null", Json::Null),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.parse_number(),
+            _ => Err(self.error("a value")),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, String> {
+        self.position += 1;
+        let mut entries = vec![];
+        self.skip_whitespace();
+        if self.next_is('}') {
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.chars.get(self.position) != Some(&'"') {
+                return Err(self.error("a key"));
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            if !self.next_is(':') {
+                return Err(self.error("':'"));
+            }
+            entries.push((key, self.parse_value()?));
+            self.skip_whitespace();
+            if self.next_is('}') {
+                return Ok(Json::Object(entries));
+            }
+            if !self.next_is(',') {
+                return Err(self.error("',' or '}'"));
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.position += 1;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.next_is(']') {
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            if self.next_is(']') {
+                return Ok(Json::Array(items));
+            }
+            if !self.next_is(',') {
+                return Err(self.error("',' or ']'"));
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.position += 1;
+        let mut text = String::new();
+        loop {
+            let c = match self.chars.get(self.position) {
+                Some(c) => *c,
+                None => return Err(self.error("the end of the string")),
+            };
+            self.position += 1;
+            match c {
+                '"' => return Ok(text),
+                '\\' => {
+                    let escaped = self.chars.get(self.position).copied();
+                    self.position += 1;
+                    match escaped {
+                        Some('"') => text.push('"'),
+                        Some('\\') => text.push('\\'),
+                        Some('/') => text.push('/'),
+                        Some('b') => text.push('\u{8}'),
+                        Some('f') => text.push('\u{c}'),
+                        Some('n') => text.push('\n'),
+                        Some('r') => text.push('\r'),
+                        Some('t') => text.push('\t'),
+                        Some('u') => {
+                            let digits: String =
+                                self.chars.iter().skip(self.position).take(4).collect();
+                            let code = u32::from_str_radix(&digits, 16)
+                                .map_err(|_| self.error("four hex digits"))?;
+                            self.position += 4;
+                            text.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return Err(self.error("an escape character")),
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(*c))
+        {
+            self.position += 1;
+        }
+        let number: String = self.chars[start..self.position].iter().collect();
+        number
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| format!("{} isn't a number", number))
+    }
+
+    fn parse_word(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        let found: String = self
+            .chars
+            .iter()
+            .skip(self.position)
+            .take(word.len())
+            .collect();
+        if found != word {
+            return Err(self.error(word));
+        }
+        self.position += word.len();
+        Ok(value)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(|c| c.is_whitespace())
+        {
+            self.position += 1;
+        }
+    }
+
+    // Move past the next character if it's the given one
+    fn next_is(&mut self, c: char) -> bool {
+        if self.chars.get(self.position) == Some(&c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, expected: &str) -> String {
+        format!(
+            "expected {} at character {} of the JSON",
+            expected,
+            self.position + 1
+        )
+    }
+}
@@ -0,0 +1,419 @@
+use crate::observer::{Control, Observer};
+use crate::puzzle::{CellId, FlowId, Puzzle, MAX_NEIGHBORS};
+use crate::solver::{Outcome, SolverOptions, Stats};
+use std::cmp::max;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+// The most routes to list, over all the flows, before giving up on listing them
+pub const MAX_ROUTES: usize = 100_000;
+
+// One way an unfinished flow could go: the empty cells it fills, in order from its first endpoint until it's next to
+// the second
+struct Route {
+    flow_id: FlowId,
+    cells: Vec<CellId>,
+}
+
+// Solve the puzzle as an exact cover problem: list every route each unfinished flow could take, then pick one route
+// per flow so that every empty cell is filled exactly once (Knuth's Algorithm X, with dancing links)
+// Routes never run alongside themselves (well made puzzles never need them to), and are no longer than the empty
+// cells left over once every other flow has taken its shortest route. If there are more than MAX_ROUTES, it gives up
+// rather than guess
+// Returns how the search ended, along with the last state looked at
+pub fn exact_cover(
+    puzzle: Puzzle,
+    options: &SolverOptions,
+    observer: &mut dyn Observer,
+    stats: &mut Stats,
+    start: Instant,
+) -> (Outcome, Option<Puzzle>) {
+    if puzzle.is_complete() {
+        stats.max_flows_completed = puzzle.num_complete();
+        return (Outcome::Solved(puzzle), None);
+    }
+
+    let flows: Vec<FlowId> = (0..puzzle.num_flows() as usize)
+        .map(|index| FlowId { index })
+        .filter(|flow_id| !puzzle.get_flow(*flow_id).unwrap().is_complete(&puzzle))
+        .collect();
+
+    // The fewest cells each flow could fill, and so the most any one flow can fill while leaving room for the rest
+    let mut shortest = vec![];
+    for flow_id in flows.iter() {
+        let flow = puzzle.get_flow(*flow_id).unwrap();
+        match puzzle.cells_between(*flow_id, flow.get_endpoint(0), flow.get_endpoint(1)) {
+            Some(cells) => shortest.push(cells),
+            None => return (Outcome::Unsolvable, None),
+        }
+    }
+    let total_shortest: u64 = shortest.iter().sum();
+    let open = puzzle.num_open_cells();
+    if total_shortest > open {
+        return (Outcome::Unsolvable, None);
+    }
+
+    let mut lister = RouteLister {
+        puzzle: &puzzle,
+        routes: vec![],
+        budget: MAX_ROUTES * MAX_NEIGHBORS * MAX_NEIGHBORS,
+    };
+    for (flow_id, cells) in flows.iter().zip(shortest.iter()) {
+        if options.out_of_time_or_states(start, 0) {
+            return (Outcome::TimedOut, None);
+        }
+        if options.is_cancelled() {
+            return (Outcome::Cancelled(puzzle.clone()), None);
+        }
+        if !lister.list(*flow_id, (open - total_shortest + cells) as usize) {
+            stats.states_created = lister.routes.len() as u64;
+            return (Outcome::Abandoned(puzzle.clone()), None);
+        }
+    }
+    let routes = lister.routes;
+    stats.states_created = routes.len() as u64;
+
+    // A column for each unfinished flow (so each gets exactly one route), then one for each empty cell
+    let mut columns = vec![None; puzzle.num_cells()];
+    let mut num_columns = flows.len();
+    for (index, cell) in puzzle.get_cells().iter().enumerate() {
+        if !cell.is_occupied() {
+            columns[index] = Some(num_columns);
+            num_columns += 1;
+        }
+    }
+    let mut links = Links::new(num_columns);
+    for (index, route) in routes.iter().enumerate() {
+        let flow_column = flows.iter().position(|id| *id == route.flow_id).unwrap();
+        let mut row = vec![flow_column];
+        row.extend(route.cells.iter().map(|id| columns[id.index].unwrap()));
+        links.add_row(index, &row);
+    }
+
+    let mut search = Search {
+        links,
+        routes: &routes,
+        board: puzzle.clone(),
+        best: puzzle,
+        best_depth: 0,
+        options,
+        observer,
+        stats,
+        start,
+    };
+    match search.search() {
+        Ok(true) => (Outcome::Solved(search.board.clone()), Some(search.board)),
+        Ok(false) => (Outcome::Unsolvable, Some(search.board)),
+        Err(outcome) => (outcome, Some(search.board)),
+    }
+}
+
+// Lists the routes for each flow, depth first
+struct RouteLister<'a> {
+    puzzle: &'a Puzzle,
+    routes: Vec<Route>,
+    // How many more partial routes to look at before giving up
+    budget: usize,
+}
+
+impl RouteLister<'_> {
+    // Add every route for the flow that fills no more than the given number of cells
+    // Returns false if there were too many
+    fn list(&mut self, flow_id: FlowId, max_cells: usize) -> bool {
+        let flow = self.puzzle.get_flow(flow_id).unwrap();
+        let (from, to) = (flow.get_endpoint(0), flow.get_endpoint(1));
+
+        // The fewest cells the flow would still have to fill after each cell to reach the other end
+        let mut needed: Vec<Option<usize>> = vec![None; self.puzzle.num_cells()];
+        let mut queue = VecDeque::new();
+        for n in self.puzzle.get_cell(to).unwrap().neighbors.iter() {
+            if self.puzzle.get_cell(*n).unwrap().is_open_to(flow_id) && needed[n.index].is_none() {
+                needed[n.index] = Some(0);
+                queue.push_back(*n);
+            }
+        }
+        while let Some(id) = queue.pop_front() {
+            for n in self.puzzle.get_cell(id).unwrap().neighbors.iter() {
+                if self.puzzle.get_cell(*n).unwrap().is_open_to(flow_id)
+                    && needed[n.index].is_none()
+                {
+                    needed[n.index] = Some(needed[id.index].unwrap() + 1);
+                    queue.push_back(*n);
+                }
+            }
+        }
+
+        // The flow's own cells, which the route mustn't run alongside
+        let mut own: Vec<bool> = self
+            .puzzle
+            .get_cells()
+            .iter()
+            .map(|cell| cell.flow_id == Some(flow_id))
+            .collect();
+        let mut path = vec![];
+        self.extend(flow_id, from, to, max_cells, &needed, &mut own, &mut path)
+    }
+
+    // Carry on the route from curr, through every neighbor that could still make it to the other end in time
+    #[allow(clippy::too_many_arguments)]
+    fn extend(
+        &mut self,
+        flow_id: FlowId,
+        curr: CellId,
+        to: CellId,
+        max_cells: usize,
+        needed: &[Option<usize>],
+        own: &mut Vec<bool>,
+        path: &mut Vec<CellId>,
+    ) -> bool {
+        if self.budget == 0 {
+            return false;
+        }
+        self.budget -= 1;
+
+        if !path.is_empty() && self.puzzle.get_cell(curr).unwrap().is_neighbor(&to) {
+            self.routes.push(Route {
+                flow_id,
+                cells: path.clone(),
+            });
+            return self.routes.len() <= MAX_ROUTES;
+        }
+
+        for n in self.puzzle.get_cell(curr).unwrap().neighbors.iter() {
+            let cell = self.puzzle.get_cell(*n).unwrap();
+            if own[n.index] || !cell.is_open_to(flow_id) {
+                continue;
+            }
+            match needed[n.index] {
+                Some(needed) if path.len() + 1 + needed <= max_cells => {}
+                _ => continue,
+            }
+            let alongside = cell
+                .neighbors
+                .iter()
+                .any(|m| own[m.index] && *m != curr && *m != to);
+            if alongside {
+                continue;
+            }
+            own[n.index] = true;
+            path.push(*n);
+            let within = self.extend(flow_id, *n, to, max_cells, needed, own, path);
+            path.pop();
+            own[n.index] = false;
+            if !within {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// The exact cover matrix, as dancing links: node 0 is the root, then a header for each column, then a node for each
+// cell (route, column) of the matrix that's filled in, linked to the nodes either side of it in its row and column
+struct Links {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    column: Vec<usize>,
+    // The route each node belongs to
+    row: Vec<usize>,
+    // How many nodes are still in each column
+    size: Vec<usize>,
+}
+
+impl Links {
+    fn new(num_columns: usize) -> Links {
+        let headers = num_columns + 1;
+        Links {
+            left: (0..headers).map(|i| (i + headers - 1) % headers).collect(),
+            right: (0..headers).map(|i| (i + 1) % headers).collect(),
+            up: (0..headers).collect(),
+            down: (0..headers).collect(),
+            column: (0..headers).collect(),
+            row: vec![0; headers],
+            size: vec![0; headers],
+        }
+    }
+
+    // Add a row with a node in each of the given columns (counting from 0, not including the root)
+    fn add_row(&mut self, row: usize, columns: &[usize]) {
+        let first = self.left.len();
+        for (i, column) in columns.iter().enumerate() {
+            let node = first + i;
+            let header = column + 1;
+            self.left.push(if i == 0 {
+                first + columns.len() - 1
+            } else {
+                node - 1
+            });
+            self.right.push(if i == columns.len() - 1 {
+                first
+            } else {
+                node + 1
+            });
+            self.up.push(self.up[header]);
+            self.down.push(header);
+            let above = self.up[header];
+            self.down[above] = node;
+            self.up[header] = node;
+            self.column.push(header);
+            self.row.push(row);
+            self.size[header] += 1;
+        }
+    }
+
+    // Take the column out, along with every row that has a node in it
+    fn cover(&mut self, header: usize) {
+        self.right[self.left[header]] = self.right[header];
+        self.left[self.right[header]] = self.left[header];
+        let mut i = self.down[header];
+        while i != header {
+            let mut j = self.right[i];
+            while j != i {
+                self.down[self.up[j]] = self.down[j];
+                self.up[self.down[j]] = self.up[j];
+                self.size[self.column[j]] -= 1;
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+
+    // Put back a column taken out by cover, in the opposite order
+    fn uncover(&mut self, header: usize) {
+        let mut i = self.up[header];
+        while i != header {
+            let mut j = self.left[i];
+            while j != i {
+                self.size[self.column[j]] += 1;
+                self.down[self.up[j]] = j;
+                self.up[self.down[j]] = j;
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+        self.right[self.left[header]] = header;
+        self.left[self.right[header]] = header;
+    }
+
+    // The column with the fewest rows left, which is the quickest to rule in or out. None if every column is covered
+    fn smallest_column(&self) -> Option<usize> {
+        let mut smallest = None;
+        let mut header = self.right[0];
+        while header != 0 {
+            if smallest.is_none_or(|best| self.size[header] < self.size[best]) {
+                smallest = Some(header);
+            }
+            header = self.right[header];
+        }
+        smallest
+    }
+}
+
+// Algorithm X, drawing each route on the board as it's picked so the observer can watch
+struct Search<'a> {
+    links: Links,
+    routes: &'a [Route],
+    board: Puzzle,
+    // The board with the most cells filled in, to hand back if the search is cancelled
+    best: Puzzle,
+    best_depth: usize,
+    options: &'a SolverOptions,
+    observer: &'a mut dyn Observer,
+    stats: &'a mut Stats,
+    start: Instant,
+}
+
+impl Search<'_> {
+    // Returns whether a solution was found (and left drawn on the board), or how the search ended if it stopped early
+    fn search(&mut self) -> Result<bool, Outcome> {
+        let header = match self.links.smallest_column() {
+            Some(header) => header,
+            None => return Ok(true),
+        };
+
+        self.links.cover(header);
+        let mut node = self.links.down[header];
+        while node != header {
+            if self
+                .options
+                .out_of_time_or_states(self.start, self.stats.states_visited)
+            {
+                return Err(Outcome::TimedOut);
+            }
+            if self.options.is_cancelled() {
+                return Err(Outcome::Cancelled(self.best.clone()));
+            }
+            self.stats.states_visited += 1;
+
+            let mut other = self.links.right[node];
+            while other != node {
+                self.links.cover(self.links.column[other]);
+                other = self.links.right[other];
+            }
+            let route = &self.routes[self.links.row[node]];
+            draw(&mut self.board, route);
+
+            self.stats.max_flows_completed =
+                max(self.stats.max_flows_completed, self.board.num_complete());
+            if self.observer.state_expanded(&self.board, self.stats) == Control::Stop {
+                return Err(Outcome::Cancelled(self.best.clone()));
+            }
+            let depth = self.board.num_cells() - self.board.num_open_cells() as usize;
+            if depth > self.best_depth {
+                self.best_depth = depth;
+                self.best = self.board.clone();
+                self.observer.new_best_depth(&self.board, depth, self.stats);
+            }
+
+            if self.search()? {
+                return Ok(true);
+            }
+
+            erase(&mut self.board, route);
+            let mut other = self.links.left[node];
+            while other != node {
+                self.links.uncover(self.links.column[other]);
+                other = self.links.left[other];
+            }
+            node = self.links.down[node];
+        }
+        self.links.uncover(header);
+        Ok(false)
+    }
+}
+
+// Draw the route in, moving the flow's first endpoint along it until it's next to the second
+fn draw(board: &mut Puzzle, route: &Route) {
+    let from = board.get_flow(route.flow_id).unwrap().get_endpoint(0);
+    board.get_cell_mut(from).unwrap().is_endpoint = false;
+    let mut previous = from;
+    for id in route.cells.iter() {
+        let cell = board.get_cell_mut(*id).unwrap();
+        cell.flow_id = Some(route.flow_id);
+        cell.previous = Some(previous);
+        previous = *id;
+    }
+    board.get_cell_mut(previous).unwrap().is_endpoint = true;
+    board
+        .get_flow_mut(route.flow_id)
+        .unwrap()
+        .update_endpoint(0, previous);
+}
+
+// Take a route drawn by draw back out again
+fn erase(board: &mut Puzzle, route: &Route) {
+    let from = board.get_cell(route.cells[0]).unwrap().previous.unwrap();
+    for id in route.cells.iter() {
+        let cell = board.get_cell_mut(*id).unwrap();
+        cell.flow_id = None;
+        cell.previous = None;
+        cell.is_endpoint = false;
+    }
+    board.get_cell_mut(from).unwrap().is_endpoint = true;
+    board
+        .get_flow_mut(route.flow_id)
+        .unwrap()
+        .update_endpoint(0, from);
+}
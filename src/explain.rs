@@ -0,0 +1,265 @@
+use crate::puzzle::{CellId, FlowId, Puzzle, Unsolvable};
+use crate::solver::{solve, Outcome, SolverOptions};
+
+// How many states the search on each smaller version of the board may visit before giving up on it
+pub const CORE_STATE_LIMIT: u64 = 100_000;
+
+// The explanation of why a puzzle can't be solved
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    // The problem with the board as it was given, if one of the solver's checks catches it straight away
+    pub immediate: Option<Unsolvable>,
+    // A part of the board that is impossible on its own. Either:
+    // - Some flows that can't all be connected, even with the rest of the flows removed and no need to fill any cells
+    // - Or, if every flow can be connected, some cells that the flows can't fill while being connected (cells is empty otherwise)
+    pub flows: Vec<FlowId>,
+    pub cells: Vec<CellId>,
+    // False if some of the smaller boards took too long to check, so the part might not be as small as it could be.
+    // Otherwise, removing any one flow or cell from it makes it possible
+    pub minimal: bool,
+}
+
+impl Explanation {
+    // Describe the explanation in plain terms
+    pub fn describe(&self, puzzle: &Puzzle) -> String {
+        let mut description = String::new();
+        if let Some(reason) = &self.immediate {
+            description.push_str(&format!(
+                "The board can't be solved as it is: {}\n\n",
+                reason.describe(puzzle)
+            ));
+        }
+
+        if self.cells.is_empty() {
+            let labels: Vec<&str> = self
+                .flows
+                .iter()
+                .map(|id| puzzle.get_flow(*id).unwrap().get_label())
+                .collect();
+            description.push_str(&format!(
+                "Flow(s) {} can't all be connected, even without filling any other cells\n",
+                labels.join(", ")
+            ));
+        } else {
+            let locations: Vec<String> = self
+                .cells
+                .iter()
+                .map(|id| puzzle.get_cell(*id).unwrap().location())
+                .collect();
+            description.push_str(&format!(
+                "The flows can all be connected, but not while filling all of these cells:\n\t{}\n",
+                locations.join("\n\t")
+            ));
+        }
+
+        description.push_str(if self.minimal {
+            "Taking away any one of these makes it possible, so this is where to look for the problem"
+        } else {
+            "Some smaller boards took too long to check, so this might not be the smallest impossible part"
+        });
+        description
+    }
+}
+
+// Explain why a puzzle can't be solved, after checking that with a quiet solve using the given options
+// Returns None if it can be, or how the search ended if it gave up before finding out
+pub fn explain(puzzle: &Puzzle, options: &SolverOptions) -> Result<Option<Explanation>, Outcome> {
+    match solve(puzzle.clone(), options, &mut ()).outcome {
+        Outcome::Solved(_) => return Ok(None),
+        Outcome::Unsolvable => {}
+        outcome => return Err(outcome),
+    }
+
+    let mut minimal = true;
+    let all_flows: Vec<FlowId> = (0..puzzle.flows.len())
+        .map(|index| FlowId { index })
+        .collect();
+    let empty_cells: Vec<CellId> = (0..puzzle.num_cells())
+        .map(|index| CellId { index })
+        .filter(|id| !puzzle.get_cell(*id).unwrap().is_occupied())
+        .collect();
+
+    // Start with everything, then take away as much as possible while keeping the board impossible
+    // If the flows get in each others' way, find the fewest flows that do. Otherwise, they can only be
+    // getting stuck on filling the board, so keep all of them and find the fewest cells they can't fill
+    let (flows, cells) = match is_impossible(puzzle, &all_flows, &[]) {
+        Some(true) => (
+            shrink(
+                all_flows,
+                |flows| is_impossible(puzzle, flows, &[]),
+                &mut minimal,
+            ),
+            vec![],
+        ),
+        result => {
+            if result.is_none() {
+                minimal = false;
+            }
+            let cells = shrink(
+                empty_cells,
+                |cells| is_impossible(puzzle, &all_flows, cells),
+                &mut minimal,
+            );
+            (all_flows, cells)
+        }
+    };
+
+    Ok(Some(Explanation {
+        immediate: puzzle.find_unsolvable(),
+        flows,
+        cells,
+        minimal,
+    }))
+}
+
+// Remove as many items as possible while the test still says the board is impossible
+// Tries to remove big chunks at a time first, then smaller and smaller ones, down to single items
+// If the test can't decide (returns None), the chunk is kept and minimal is set to false
+fn shrink<T: Copy, F: FnMut(&[T]) -> Option<bool>>(
+    mut items: Vec<T>,
+    mut test: F,
+    minimal: &mut bool,
+) -> Vec<T> {
+    let mut chunk = items.len().div_ceil(2);
+    while chunk > 0 {
+        let mut i = 0;
+        while i < items.len() {
+            let end = (i + chunk).min(items.len());
+            let candidate: Vec<T> = items[..i]
+                .iter()
+                .chain(items[end..].iter())
+                .copied()
+                .collect();
+            match test(&candidate) {
+                Some(true) => items = candidate,
+                Some(false) => i += chunk,
+                None => {
+                    *minimal = false;
+                    i += chunk;
+                }
+            }
+        }
+        chunk /= 2;
+    }
+    items
+}
+
+// Is it impossible to connect the given flows, while filling the given cells?
+// Every other flow is removed from the board, and every other cell may be left empty
+// Some(true) if it's impossible, Some(false) if it's possible, None if the search gave up
+fn is_impossible(puzzle: &Puzzle, flows: &[FlowId], cells: &[CellId]) -> Option<bool> {
+    let board = keep_flows(puzzle, flows);
+    let mut required = vec![false; board.num_cells()];
+    for id in cells {
+        required[id.index] = true;
+    }
+
+    // Depth first, as the board will usually be impossible and the whole thing has to be searched anyway
+    let mut stack = vec![board];
+    let mut states = 0;
+    while let Some(state) = stack.pop() {
+        states += 1;
+        if states > CORE_STATE_LIMIT {
+            return None;
+        }
+
+        if state.flows.iter().all(|flow| flow.is_complete(&state)) {
+            if (0..state.num_cells())
+                .all(|i| !required[i] || state.get_cell(CellId { index: i }).unwrap().is_occupied())
+            {
+                return Some(false);
+            }
+            continue;
+        }
+
+        if !is_dead(&state, &required) {
+            stack.extend(state.create_children());
+        }
+    }
+    Some(true)
+}
+
+// A copy of the puzzle with only the given flows. The cells of every other flow are emptied
+fn keep_flows(puzzle: &Puzzle, flows: &[FlowId]) -> Puzzle {
+    let mut board = puzzle.clone();
+    let new_id = |id: FlowId| {
+        flows
+            .iter()
+            .position(|kept| *kept == id)
+            .map(|index| FlowId { index })
+    };
+
+    for i in 0..board.num_cells() {
+        let cell = board.get_cell_mut(CellId { index: i }).unwrap();
+        cell.restricted_to = cell.restricted_to.and_then(new_id);
+        cell.flow_id = cell.flow_id.and_then(new_id);
+        if cell.flow_id.is_none() {
+            cell.is_endpoint = false;
+        }
+    }
+
+    board.flows = flows
+        .iter()
+        .enumerate()
+        .map(|(index, id)| {
+            let mut flow = puzzle.get_flow(*id).unwrap().clone();
+            flow.id = index;
+            flow
+        })
+        .collect();
+    board
+}
+
+// The checks from is_solvable that still hold when only some cells need to be filled
+fn is_dead(state: &Puzzle, required: &[bool]) -> bool {
+    if state.num_possible_children() == 0 {
+        return true;
+    }
+
+    // A required cell needs a path through it, so it needs two ways in
+    for (i, cell) in state.get_cells().iter().enumerate() {
+        if required[i]
+            && !cell.is_occupied()
+            && cell.num_open_neighbors(state) == 1
+            && !cell
+                .neighbors
+                .iter()
+                .any(|n| state.get_cell(*n).unwrap().is_endpoint)
+        {
+            return true;
+        }
+    }
+
+    let regions = state.get_regions();
+    let borders = |endpoint: CellId, region: &Vec<usize>| {
+        state
+            .get_cell(endpoint)
+            .unwrap()
+            .neighbors
+            .iter()
+            .any(|n| region.contains(&n.index))
+    };
+    let incomplete: Vec<_> = state
+        .flows
+        .iter()
+        .filter(|flow| !flow.is_complete(state))
+        .collect();
+
+    // Regions with a required cell need a flow that can go through them
+    for region in regions.iter() {
+        if region.iter().any(|i| required[*i])
+            && !incomplete.iter().any(|flow| {
+                borders(flow.get_endpoint(0), region) && borders(flow.get_endpoint(1), region)
+            })
+        {
+            return true;
+        }
+    }
+
+    // Every flow needs somewhere to go between its endpoints
+    incomplete.iter().any(|flow| {
+        !regions.iter().any(|region| {
+            borders(flow.get_endpoint(0), region) && borders(flow.get_endpoint(1), region)
+        })
+    })
+}
@@ -0,0 +1,339 @@
+use crate::parser::{flow_label, parse_puzzle, split_columns, PUZZLE_TYPES};
+use crate::puzzle::MAX_CELLS;
+use crate::solver::{count_solutions, SolutionCount, SolverOptions};
+use std::collections::HashMap;
+
+// How many random boards to try before giving up on finding one with a single solution
+const MAX_ATTEMPTS: usize = 500;
+
+// Moves to make (per cell) while shuffling the paths around, before giving up on a board
+const MOVES_PER_CELL: usize = 2000;
+// Moves to make (per cell) before the paths are random enough to be used
+const MIN_MOVES_PER_CELL: usize = 50;
+
+// Every so many cells of a bridges puzzle is a bridge
+const CELLS_PER_BRIDGE: usize = 20;
+
+// What sort of puzzle to make
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    // One of the puzzle types (STANDARD, WARPS...)
+    pub puzzle_type: String,
+    pub width: usize,
+    pub height: usize,
+    pub flows: usize,
+    // The same seed always makes the same puzzle
+    pub seed: u64,
+}
+
+impl GenerateOptions {
+    // Make sure a puzzle like this can be made at all
+    pub fn check(&self) -> Result<(), String> {
+        let puzzle_type = self.puzzle_type.to_uppercase();
+        if !PUZZLE_TYPES.contains(&puzzle_type.as_str()) {
+            return Err(format!(
+                "Unknown puzzle type {} (expected one of: {})",
+                self.puzzle_type,
+                PUZZLE_TYPES.join(", ").to_lowercase()
+            ));
+        }
+        // Warps any narrower would wrap around onto the cell next door
+        let min_size = if puzzle_type == "WARPS" { 3 } else { 2 };
+        if self.width < min_size || self.height < min_size {
+            return Err(format!(
+                "{} puzzles need to be at least {}x{}",
+                puzzle_type.to_lowercase(),
+                min_size,
+                min_size
+            ));
+        }
+        // The solver can only handle so many cells (and each bridge is two)
+        let cells = self.width.saturating_mul(self.height);
+        let bridges = if puzzle_type == "BRIDGES" {
+            (cells / CELLS_PER_BRIDGE).max(1)
+        } else {
+            0
+        };
+        if cells.saturating_add(bridges) > MAX_CELLS {
+            return Err(format!(
+                "A {}x{} board is too big: the most cells the solver can handle is {}",
+                self.width, self.height, MAX_CELLS
+            ));
+        }
+        // Every flow needs its two dots and at least one cell between them
+        if self.flows == 0 || self.flows * 3 > self.width * self.height {
+            return Err(format!(
+                "A {}x{} board has room for 1 to {} flows",
+                self.width,
+                self.height,
+                self.width * self.height / 3
+            ));
+        }
+        Ok(())
+    }
+}
+
+// A small, predictable random number generator (splitmix64), so puzzles can be made again from their seed
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // A number from 0 up to (but not including) the given one
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn coin(&mut self) -> bool {
+        self.next() & 1 == 1
+    }
+}
+
+// Make a random puzzle with exactly one solution, in the regular text format
+// The board is split up into random paths covering every cell, and the ends of each path become a flow's dots.
+// Boards the solver finds more than one solution to (or can't make its mind up about within the options' limits)
+// are thrown away, and another is tried
+pub fn generate(
+    options: &GenerateOptions,
+    solver_options: &SolverOptions,
+) -> Result<String, String> {
+    options.check()?;
+    let puzzle_type = options.puzzle_type.to_uppercase();
+
+    let mut random = Random::new(options.seed);
+    for _ in 0..MAX_ATTEMPTS {
+        let bridges = if puzzle_type == "BRIDGES" {
+            place_bridges(options.width, options.height, &mut random)
+        } else {
+            vec![]
+        };
+        let board = empty_board(&puzzle_type, options.width, options.height, &bridges);
+        let empty = parse_puzzle(&board)?;
+
+        let neighbors: Vec<Vec<usize>> = empty
+            .get_cells()
+            .iter()
+            .map(|cell| cell.neighbors.iter().map(|n| n.index).collect())
+            .collect();
+        // Dots can't go on bridges
+        let can_end: Vec<bool> = empty
+            .get_cells()
+            .iter()
+            .map(|cell| cell.bridge_direction.is_none())
+            .collect();
+        let paths = match random_paths(&neighbors, &can_end, options.flows, &mut random) {
+            Some(paths) => paths,
+            None => continue,
+        };
+
+        // Put each path's dots on the board
+        let mut lines: Vec<Vec<String>> = board.lines().map(split_columns).collect();
+        for (index, path) in paths.iter().enumerate() {
+            for end in [path[0], path[path.len() - 1]] {
+                let cell = &empty.get_cells()[end];
+                // The first line is the type of puzzle
+                lines[cell.row + 1][cell.col] = flow_label(index);
+            }
+        }
+        let text: String = lines
+            .iter()
+            .map(|line| format!("{}\n", line.concat()))
+            .collect();
+
+        let puzzle = parse_puzzle(&text)?;
+        if let SolutionCount::One(_) = count_solutions(puzzle, solver_options) {
+            return Ok(text);
+        }
+    }
+    Err(format!(
+        "Couldn't make a puzzle with only one solution in {} tries (try fewer flows, or a longer --timeout)",
+        MAX_ATTEMPTS
+    ))
+}
+
+// Pick where the bridges go. They're kept away from the edges (where nothing could cross them) and from each other
+fn place_bridges(width: usize, height: usize, random: &mut Random) -> Vec<(usize, usize)> {
+    let mut bridges: Vec<(usize, usize)> = vec![];
+    if width < 3 || height < 3 {
+        return bridges;
+    }
+    let wanted = (width * height / CELLS_PER_BRIDGE).max(1);
+    for _ in 0..wanted * 10 {
+        if bridges.len() == wanted {
+            break;
+        }
+        let (row, col) = (1 + random.below(height - 2), 1 + random.below(width - 2));
+        if bridges
+            .iter()
+            .all(|(r, c)| r.abs_diff(row) + c.abs_diff(col) > 1)
+        {
+            bridges.push((row, col));
+        }
+    }
+    bridges
+}
+
+// A board of the given type and size with nothing on it but empty cells (and bridges), fully connected
+// Warps wrap around every row and column
+fn empty_board(
+    puzzle_type: &str,
+    width: usize,
+    height: usize,
+    bridges: &[(usize, usize)],
+) -> String {
+    let mut board = format!("{}\n", puzzle_type);
+    for row in 0..height {
+        let cells: Vec<&str> = (0..width)
+            .map(|col| {
+                if bridges.contains(&(row, col)) {
+                    "*"
+                } else {
+                    "."
+                }
+            })
+            .collect();
+        board.push_str(&cells.join("-"));
+        if puzzle_type == "WARPS" {
+            board.push('-');
+        }
+        board.push('\n');
+
+        // The connections down to the next row (or back around to the top, for warps)
+        if row + 1 < height || puzzle_type == "WARPS" {
+            let mut below = String::new();
+            for col in 0..width {
+                below.push('|');
+                if col + 1 < width {
+                    // Hexes also join up diagonally, alternating direction
+                    below.push(match (puzzle_type == "HEX", col % 2) {
+                        (false, _) => ' ',
+                        (true, 0) => '/',
+                        (true, _) => '\\',
+                    });
+                }
+            }
+            board.push_str(below.trim_end());
+            board.push('\n');
+        }
+    }
+    board
+}
+
+// Split the cells up into the given number of paths, each at least 3 cells long and ending in cells that can be
+// dots, by shuffling the paths around at random. Returns None if it doesn't work out
+// Paths never run alongside themselves, as the solver can't always find those (and they're usually a second
+// solution waiting to happen)
+fn random_paths(
+    neighbors: &[Vec<usize>],
+    can_end: &[bool],
+    flows: usize,
+    random: &mut Random,
+) -> Option<Vec<Vec<usize>>> {
+    let num_cells = neighbors.len();
+    // Start with every cell on its own
+    let mut paths: Vec<Vec<usize>> = (0..num_cells).map(|cell| vec![cell]).collect();
+    let mut path_of: Vec<usize> = (0..num_cells).collect();
+
+    for step in 0..num_cells * MOVES_PER_CELL {
+        if step >= num_cells * MIN_MOVES_PER_CELL
+            && paths.len() == flows
+            && paths
+                .iter()
+                .all(|path| path.len() >= 3 && can_end[path[0]] && can_end[path[path.len() - 1]])
+        {
+            return Some(paths);
+        }
+
+        // Grow one of the paths from one of its ends
+        let p = random.below(paths.len());
+        if random.coin() {
+            paths[p].reverse();
+        }
+        let end = *paths[p].last().unwrap();
+        if neighbors[end].is_empty() {
+            continue;
+        }
+        let next = neighbors[end][random.below(neighbors[end].len())];
+        let q = path_of[next];
+        let mut i = paths[q].iter().position(|cell| *cell == next).unwrap();
+
+        if q == p {
+            // Back into itself: turn around, and run the rest of the path the other way
+            if i + 2 == paths[p].len() {
+                continue;
+            }
+            let mut turned = paths[p].clone();
+            turned[i + 1..].reverse();
+            if touches_itself(&turned, neighbors) {
+                continue;
+            }
+            paths[p] = turned;
+        } else if i == 0 || i + 1 == paths[q].len() {
+            // Onto the end of another path: join them up, as long as there are still too many paths
+            if paths.len() <= flows {
+                continue;
+            }
+            let mut joined = paths[p].clone();
+            if i == 0 {
+                joined.extend(paths[q].iter());
+            } else {
+                joined.extend(paths[q].iter().rev());
+            }
+            if touches_itself(&joined, neighbors) {
+                continue;
+            }
+            for cell in joined.iter() {
+                path_of[*cell] = p;
+            }
+            paths[p] = joined;
+            paths.swap_remove(q);
+            if q < paths.len() {
+                for cell in paths[q].iter() {
+                    path_of[*cell] = q;
+                }
+            }
+        } else {
+            // Into the middle of another path: take the cell, and the rest of that path on one side of it
+            if random.coin() {
+                paths[q].reverse();
+                i = paths[q].len() - 1 - i;
+            }
+            let mut grown = paths[p].clone();
+            grown.extend(paths[q][i..].iter());
+            if touches_itself(&grown, neighbors) {
+                continue;
+            }
+            for cell in paths[q].split_off(i) {
+                path_of[cell] = p;
+            }
+            paths[p] = grown;
+        }
+    }
+    None
+}
+
+// Does the path run alongside itself, with neighboring cells that aren't next to each other along it?
+fn touches_itself(path: &[usize], neighbors: &[Vec<usize>]) -> bool {
+    let position: HashMap<usize, usize> = path
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| (*cell, i))
+        .collect();
+    path.iter().enumerate().any(|(i, cell)| {
+        neighbors[*cell]
+            .iter()
+            .any(|n| position.get(n).is_some_and(|j| j.abs_diff(i) > 1))
+    })
+}
@@ -0,0 +1,419 @@
+use crate::layout::{
+    blend, flow_color, Color, Layout, Point, BACKGROUND, BRIDGE_GAP, BRIDGE_LANE_OPACITY,
+    BRIDGE_LANE_WIDTH, CELL_BACKGROUND, DOT_RADIUS, GRID_COLOR, PIPE_WIDTH, SHADE_OPACITY,
+    WALL_COLOR, WALL_WIDTH, WARP_COLOR,
+};
+use crate::puzzle::{CellId, FlowId, Puzzle, VERTICAL};
+use std::collections::HashMap;
+
+// How long each frame is shown for, in hundredths of a second
+pub const FRAME_DELAY: u16 = 5;
+
+// How long the last frame is shown for before the animation starts again
+pub const FINAL_DELAY: u16 = 300;
+
+// The colors every frame uses. Index 0 is left for pixels that haven't changed since the last frame
+const TRANSPARENT: u8 = 0;
+const BACKGROUND_INDEX: u8 = 1;
+const CELL_BACKGROUND_INDEX: u8 = 2;
+const GRID_INDEX: u8 = 3;
+const WALL_INDEX: u8 = 4;
+const WARP_INDEX: u8 = 5;
+const BRIDGE_LANE_INDEX: u8 = 6;
+// After these come two colors for each flow: the flow's color, and the shade of the cells it fills
+const FIRST_FLOW_INDEX: usize = 7;
+const MAX_FLOW_COLORS: usize = (256 - FIRST_FLOW_INDEX) / 2;
+
+// Draw the search for a solution to the puzzle as an animated GIF
+// Each recorded state is a frame. If there is a solution, it is then drawn in one flow at a time
+// If there's nothing else to draw, the puzzle itself is the only frame
+// (dots aren't labelled, as there's no font to draw them with)
+pub fn render_gif(puzzle: &Puzzle, states: &[Puzzle], solution: Option<&Puzzle>) -> Vec<u8> {
+    let mut frames: Vec<&Puzzle> = states.iter().collect();
+    let drawn_in = solution.map(solution_frames).unwrap_or_default();
+    frames.extend(drawn_in.iter());
+    if frames.is_empty() {
+        frames.push(puzzle);
+    }
+
+    let layout = Layout::new(frames[0]);
+    let width = layout.width.ceil() as usize;
+    let height = layout.height.ceil() as usize;
+
+    let mut gif = Vec::new();
+    gif.extend_from_slice(b"GIF89a");
+    push_u16(&mut gif, width as u16);
+    push_u16(&mut gif, height as u16);
+    // A global color table of 256 colors
+    gif.extend_from_slice(&[0xF7, BACKGROUND_INDEX, 0]);
+    for color in palette() {
+        gif.extend_from_slice(&[color.0, color.1, color.2]);
+    }
+    // Loop forever
+    gif.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+    gif.extend_from_slice(b"NETSCAPE2.0");
+    gif.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+
+    // Only the part of each frame that changed is stored, with everything else in it transparent
+    let mut previous: Option<Canvas> = None;
+    let mut pending: Option<(Vec<u8>, u16)> = None;
+    for frame in frames.iter() {
+        let canvas = draw(&layout, frame, width, height);
+        let image = match &previous {
+            None => Some(image_block(&canvas, None)),
+            Some(previous) => {
+                changes(previous, &canvas).map(|area| image_block(&canvas, Some((previous, area))))
+            }
+        };
+        match image {
+            Some(image) => {
+                if let Some((image, delay)) = pending.take() {
+                    push_frame(&mut gif, &image, delay);
+                }
+                pending = Some((image, FRAME_DELAY));
+            }
+            // Nothing changed, so show the last frame for longer instead
+            None => {
+                if let Some((_, delay)) = pending.as_mut() {
+                    *delay += FRAME_DELAY;
+                }
+            }
+        }
+        previous = Some(canvas);
+    }
+    if let Some((image, _)) = pending {
+        push_frame(&mut gif, &image, FINAL_DELAY);
+    }
+
+    gif.push(0x3B);
+    gif
+}
+
+// The frames for drawing in the solution, starting with just the dots and adding one cell at a time
+pub fn solution_frames(solution: &Puzzle) -> Vec<Puzzle> {
+    let mut state = solution.clone();
+    for index in 0..state.num_cells() {
+        let id = CellId { index };
+        if !solution.is_dot(id) {
+            let cell = state.get_cell_mut(id).unwrap();
+            cell.flow_id = None;
+            cell.previous = None;
+        }
+    }
+    for flow in state.flows.iter_mut() {
+        let [dot0, dot1] = flow.get_dots();
+        flow.update_endpoint(0, dot0);
+        flow.update_endpoint(1, dot1);
+    }
+
+    let mut frames = vec![state.clone()];
+    for index in 0..solution.flows.len() {
+        let flow_id = FlowId { index };
+        let path = match solution.trace_path(flow_id) {
+            Some(path) => path,
+            None => continue,
+        };
+        // Grow the flow out from its first dot. It's complete once it's next to the second
+        for i in 1..path.len() - 1 {
+            let cell = state.get_cell_mut(path[i]).unwrap();
+            cell.flow_id = Some(flow_id);
+            cell.previous = Some(path[i - 1]);
+            state
+                .get_flow_mut(flow_id)
+                .unwrap()
+                .update_endpoint(0, path[i]);
+            frames.push(state.clone());
+        }
+    }
+    frames
+}
+
+// Every color used, in palette order
+fn palette() -> Vec<Color> {
+    let mut colors = vec![
+        BACKGROUND,
+        BACKGROUND,
+        CELL_BACKGROUND,
+        GRID_COLOR,
+        WALL_COLOR,
+        WARP_COLOR,
+        blend(GRID_COLOR, CELL_BACKGROUND, BRIDGE_LANE_OPACITY),
+    ];
+    for index in 0..MAX_FLOW_COLORS {
+        colors.push(flow_color(index));
+        colors.push(blend(flow_color(index), CELL_BACKGROUND, SHADE_OPACITY));
+    }
+    colors.resize(256, BACKGROUND);
+    colors
+}
+
+// Where in the palette a flow's color is (puzzles with too many flows reuse colors)
+fn flow_index(flow_id: FlowId) -> u8 {
+    (FIRST_FLOW_INDEX + 2 * (flow_id.index % MAX_FLOW_COLORS)) as u8
+}
+
+// Draw a single frame, the same way as render_svg
+fn draw(layout: &Layout, puzzle: &Puzzle, width: usize, height: usize) -> Canvas {
+    let mut canvas = Canvas {
+        width,
+        height,
+        pixels: vec![BACKGROUND_INDEX; width * height],
+    };
+
+    for (index, cell) in puzzle.get_cells().iter().enumerate() {
+        if cell.bridge_direction == Some(VERTICAL) {
+            continue;
+        }
+        let fill = match cell.flow_id {
+            Some(flow_id) => flow_index(flow_id) + 1,
+            None => CELL_BACKGROUND_INDEX,
+        };
+        let corners = &layout.shapes[index];
+        canvas.fill_polygon(corners, fill);
+        for side in 0..corners.len() {
+            let segment = (corners[side], corners[(side + 1) % corners.len()]);
+            canvas.draw_line(segment, 1.0, GRID_INDEX);
+        }
+        if cell.bridge_direction.is_some() {
+            for lane in layout.bridge_lanes(CellId { index }) {
+                canvas.draw_line(lane, BRIDGE_LANE_WIDTH, BRIDGE_LANE_INDEX);
+            }
+        }
+    }
+
+    for wall in layout.walls.iter() {
+        canvas.draw_line(*wall, WALL_WIDTH, WALL_INDEX);
+    }
+    // Warps are dashed
+    for ((x1, y1), (x2, y2)) in layout.warps.iter() {
+        let dashes = ((x2 - x1).hypot(y2 - y1) / 8.0).ceil() as usize;
+        for dash in 0..dashes {
+            let at = |t: f64| (x1 + (x2 - x1) * t, y1 + (y2 - y1) * t);
+            let start = dash as f64 / dashes as f64;
+            let end = (dash as f64 + 0.5) / dashes as f64;
+            canvas.draw_line((at(start), at(end)), WALL_WIDTH, WARP_INDEX);
+        }
+    }
+
+    let pipes = layout.pipe_segments(puzzle, &puzzle.get_connections());
+    for outline in pipes.iter().filter_map(|piece| piece.outline) {
+        canvas.draw_line(outline, PIPE_WIDTH + 2.0 * BRIDGE_GAP, BACKGROUND_INDEX);
+    }
+    for piece in pipes.iter() {
+        canvas.draw_line(piece.segment, PIPE_WIDTH, flow_index(piece.flow_id));
+    }
+
+    for flow in puzzle.flows.iter() {
+        for dot in flow.get_dots().iter() {
+            canvas.fill_circle(
+                layout.centers[dot.index],
+                DOT_RADIUS,
+                flow_index(FlowId { index: flow.id }),
+            );
+        }
+    }
+
+    canvas
+}
+
+// A frame being drawn, as indices into the palette
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    // Color every pixel within the bounds whose center passes the test
+    fn fill_near(&mut self, bounds: (Point, Point), color: u8, inside: impl Fn(Point) -> bool) {
+        let ((left, top), (right, bottom)) = bounds;
+        let x_range =
+            (left.floor().max(0.0) as usize)..(right.ceil().max(0.0) as usize).min(self.width);
+        for y in (top.floor().max(0.0) as usize)..(bottom.ceil().max(0.0) as usize).min(self.height)
+        {
+            for x in x_range.clone() {
+                if inside((x as f64 + 0.5, y as f64 + 0.5)) {
+                    self.pixels[y * self.width + x] = color;
+                }
+            }
+        }
+    }
+
+    fn fill_polygon(&mut self, corners: &[Point], color: u8) {
+        let left = corners.iter().map(|p| p.0).fold(f64::MAX, f64::min);
+        let right = corners.iter().map(|p| p.0).fold(f64::MIN, f64::max);
+        let top = corners.iter().map(|p| p.1).fold(f64::MAX, f64::min);
+        let bottom = corners.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+        self.fill_near(((left, top), (right, bottom)), color, |(x, y)| {
+            // Count how many sides a line going right from the point crosses
+            let mut inside = false;
+            for i in 0..corners.len() {
+                let (x1, y1) = corners[i];
+                let (x2, y2) = corners[(i + 1) % corners.len()];
+                if (y1 > y) != (y2 > y) && x < (x2 - x1) * (y - y1) / (y2 - y1) + x1 {
+                    inside = !inside;
+                }
+            }
+            inside
+        });
+    }
+
+    // A line with rounded ends
+    fn draw_line(&mut self, segment: (Point, Point), width: f64, color: u8) {
+        let ((x1, y1), (x2, y2)) = segment;
+        let radius = width / 2.0;
+        let bounds = (
+            (x1.min(x2) - radius, y1.min(y2) - radius),
+            (x1.max(x2) + radius, y1.max(y2) + radius),
+        );
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let length = dx * dx + dy * dy;
+        self.fill_near(bounds, color, |(x, y)| {
+            // Distance to the closest point on the segment
+            let t = if length == 0.0 {
+                0.0
+            } else {
+                (((x - x1) * dx + (y - y1) * dy) / length).clamp(0.0, 1.0)
+            };
+            (x - (x1 + t * dx)).hypot(y - (y1 + t * dy)) <= radius
+        });
+    }
+
+    fn fill_circle(&mut self, center: Point, radius: f64, color: u8) {
+        self.draw_line((center, center), 2.0 * radius, color);
+    }
+}
+
+// A rectangle of a frame: left, top, width and height
+type Area = (usize, usize, usize, usize);
+
+// The smallest rectangle containing every pixel that's different between the two frames
+fn changes(previous: &Canvas, canvas: &Canvas) -> Option<Area> {
+    let (mut left, mut top, mut right, mut bottom) = (canvas.width, canvas.height, 0, 0);
+    for y in 0..canvas.height {
+        for x in 0..canvas.width {
+            let i = y * canvas.width + x;
+            if previous.pixels[i] != canvas.pixels[i] {
+                left = left.min(x);
+                right = right.max(x);
+                top = top.min(y);
+                bottom = bottom.max(y);
+            }
+        }
+    }
+    if left > right {
+        None
+    } else {
+        Some((left, top, right - left + 1, bottom - top + 1))
+    }
+}
+
+// The image descriptor and compressed pixels for a frame
+// If the previous frame is given, only the given area is included, with the pixels that haven't changed left transparent
+fn image_block(canvas: &Canvas, previous: Option<(&Canvas, Area)>) -> Vec<u8> {
+    let (left, top, width, height) = previous.map_or((0, 0, canvas.width, canvas.height), |p| p.1);
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in top..top + height {
+        for x in left..left + width {
+            let i = y * canvas.width + x;
+            match previous {
+                Some((previous, _)) if previous.pixels[i] == canvas.pixels[i] => {
+                    pixels.push(TRANSPARENT)
+                }
+                _ => pixels.push(canvas.pixels[i]),
+            }
+        }
+    }
+
+    let mut block = vec![0x2C];
+    for value in [left, top, width, height] {
+        push_u16(&mut block, value as u16);
+    }
+    // No local color table, not interlaced
+    block.push(0);
+    block.push(8);
+    for chunk in lzw_compress(&pixels).chunks(255) {
+        block.push(chunk.len() as u8);
+        block.extend_from_slice(chunk);
+    }
+    block.push(0);
+    block
+}
+
+// Add a frame to the GIF, shown for the given delay and then left in place for the next frame to be drawn over
+fn push_frame(gif: &mut Vec<u8>, image: &[u8], delay: u16) {
+    gif.extend_from_slice(&[0x21, 0xF9, 0x04, 0x05]);
+    push_u16(gif, delay);
+    gif.extend_from_slice(&[TRANSPARENT, 0x00]);
+    gif.extend_from_slice(image);
+}
+
+fn push_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+// Compress the pixels with the variable length LZW that GIFs use (8 bits per pixel)
+fn lzw_compress(pixels: &[u8]) -> Vec<u8> {
+    const CLEAR_CODE: u16 = 256;
+    const END_CODE: u16 = 257;
+    const MAX_CODE: u16 = 4096;
+
+    let mut output = BitWriter {
+        bytes: Vec::new(),
+        buffer: 0,
+        bits: 0,
+    };
+
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = END_CODE + 1;
+    let mut code_size = 9;
+    output.write(CLEAR_CODE, code_size);
+
+    let mut current = pixels[0] as u16;
+    for pixel in pixels[1..].iter() {
+        if let Some(code) = table.get(&(current, *pixel)) {
+            current = *code;
+            continue;
+        }
+        output.write(current, code_size);
+        // Once the table is full, start again with an empty one
+        if next_code == MAX_CODE {
+            output.write(CLEAR_CODE, code_size);
+            table.clear();
+            next_code = END_CODE + 1;
+            code_size = 9;
+        } else {
+            if next_code >= 1 << code_size {
+                code_size += 1;
+            }
+            table.insert((current, *pixel), next_code);
+            next_code += 1;
+        }
+        current = *pixel as u16;
+    }
+    output.write(current, code_size);
+    output.write(END_CODE, code_size);
+    if output.bits > 0 {
+        output.bytes.push(output.buffer as u8);
+    }
+    output.bytes
+}
+
+// Packs codes into bytes, starting from the lowest bit
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, code_size: u32) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += code_size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+}
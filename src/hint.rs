@@ -0,0 +1,183 @@
+use crate::puzzle::{CellId, FlowId, Puzzle};
+use crate::solver::{solve, Outcome, SolverOptions};
+
+// A hint for the next step in solving a puzzle
+#[derive(Debug, Clone)]
+pub enum Hint {
+    // Move a flow's endpoint into a neighboring cell
+    // forced is true if the current board alone is enough to prove the move, without solving the puzzle
+    Move {
+        from: CellId,
+        to: CellId,
+        forced: bool,
+    },
+    // The whole path of a flow, from one of its dots to the other
+    Flow {
+        flow_id: FlowId,
+        path: Vec<CellId>,
+    },
+}
+
+impl Hint {
+    // Apply the hint to the puzzle, extending the flow into the hinted cells (or all the way, for a whole flow)
+    pub fn apply(&self, puzzle: &mut Puzzle) {
+        match self {
+            Hint::Move { from, to, .. } => {
+                let flow_id = puzzle.get_cell(*from).unwrap().flow_id.unwrap();
+                let endpoint_index = if puzzle.get_flow(flow_id).unwrap().get_endpoint(0) == *from {
+                    0
+                } else {
+                    1
+                };
+                puzzle.get_cell_mut(*from).unwrap().is_endpoint = false;
+                let cell = puzzle.get_cell_mut(*to).unwrap();
+                cell.flow_id = Some(flow_id);
+                cell.is_endpoint = true;
+                cell.previous = Some(*from);
+                puzzle
+                    .get_flow_mut(flow_id)
+                    .unwrap()
+                    .update_endpoint(endpoint_index, *to);
+            }
+            Hint::Flow { flow_id, path } => {
+                // The path runs from the first dot to the second, so draw it all in from the first
+                puzzle.get_cell_mut(path[0]).unwrap().is_endpoint = false;
+                for i in 1..path.len() - 1 {
+                    let cell = puzzle.get_cell_mut(path[i]).unwrap();
+                    cell.flow_id = Some(*flow_id);
+                    cell.is_endpoint = false;
+                    cell.previous = Some(path[i - 1]);
+                }
+                let (last, end) = (path[path.len() - 2], path[path.len() - 1]);
+                puzzle.get_cell_mut(last).unwrap().is_endpoint = true;
+                let flow = puzzle.get_flow_mut(*flow_id).unwrap();
+                flow.update_endpoint(0, last);
+                flow.update_endpoint(1, end);
+            }
+        }
+    }
+
+    // Describe the hint in words
+    pub fn describe(&self, puzzle: &Puzzle) -> String {
+        match self {
+            Hint::Move { from, to, forced } => format!(
+                "Flow {} goes from {} to {}{}",
+                puzzle.get_cell_label(*from),
+                puzzle.get_cell(*from).unwrap().location(),
+                puzzle.get_cell(*to).unwrap().location(),
+                if *forced {
+                    " (there's nowhere else for it to go)"
+                } else {
+                    ""
+                }
+            ),
+            Hint::Flow { flow_id, path } => format!(
+                "Flow {} fills {} cells between {} and {}",
+                puzzle.get_flow(*flow_id).unwrap().get_label(),
+                path.len(),
+                puzzle.get_cell(path[0]).unwrap().location(),
+                puzzle.get_cell(path[path.len() - 1]).unwrap().location()
+            ),
+        }
+    }
+}
+
+// Find a single move that is part of the solution
+// Moves that are forced by the current board are preferred. Otherwise, the puzzle is solved and a move is taken
+// from the solution, which is only guaranteed to be right if the puzzle has one solution (like the ones in the game)
+// Returns None if the puzzle is already complete or can't be solved, or how the search ended if it gave up first
+pub fn find_move(puzzle: &Puzzle, options: &SolverOptions) -> Result<Option<Hint>, Outcome> {
+    if puzzle.is_complete() {
+        return Ok(None);
+    }
+
+    if let Some((from, to)) = puzzle.get_forced_move() {
+        return Ok(Some(Hint::Move {
+            from,
+            to,
+            forced: true,
+        }));
+    }
+
+    let solution = match find_solution(puzzle, options)? {
+        Some(solution) => solution,
+        None => return Ok(None),
+    };
+
+    // Start with the endpoint the solver would extend, then try the rest
+    // A move is only clear cut if exactly one of the endpoint's open neighbors is part of its flow
+    let mut endpoints = puzzle.get_endpoints_for_incomplete_flows();
+    let preferred = puzzle.get_endpoint_to_extend();
+    endpoints.sort_by_key(|id| *id != preferred);
+    for endpoint_id in endpoints.iter() {
+        let flow_id = puzzle.get_cell(*endpoint_id).unwrap().flow_id.unwrap();
+        let mut moves = puzzle
+            .get_cell(*endpoint_id)
+            .unwrap()
+            .neighbors
+            .iter()
+            .filter(|n| {
+                puzzle.get_cell(**n).unwrap().is_open_to(flow_id)
+                    && solution.get_cell(**n).unwrap().flow_id == Some(flow_id)
+            });
+        if let (Some(to), None) = (moves.next(), moves.next()) {
+            return Ok(Some(Hint::Move {
+                from: *endpoint_id,
+                to: *to,
+                forced: false,
+            }));
+        }
+    }
+
+    // Every endpoint runs alongside its own flow, so give away a whole flow instead
+    Ok(flow_hint(puzzle, &solution))
+}
+
+// Find the path of a single flow in the solution
+// The incomplete flow with the fewest cells left to fill is picked, to give away as little as possible
+// Returns None if the puzzle is already complete or can't be solved, or how the search ended if it gave up first
+pub fn find_flow(puzzle: &Puzzle, options: &SolverOptions) -> Result<Option<Hint>, Outcome> {
+    if puzzle.is_complete() {
+        return Ok(None);
+    }
+
+    Ok(match find_solution(puzzle, options)? {
+        Some(solution) => flow_hint(puzzle, &solution),
+        None => None,
+    })
+}
+
+// Solve the puzzle quietly with the given options
+// Returns None if there's no solution, or how the search ended if it gave up first
+fn find_solution(puzzle: &Puzzle, options: &SolverOptions) -> Result<Option<Puzzle>, Outcome> {
+    match solve(puzzle.clone(), options, &mut ()).outcome {
+        Outcome::Solved(solution) => Ok(Some(solution)),
+        Outcome::Unsolvable => Ok(None),
+        outcome => Err(outcome),
+    }
+}
+
+// Pick the incomplete flow with the fewest open cells in the solution, and return its path
+fn flow_hint(puzzle: &Puzzle, solution: &Puzzle) -> Option<Hint> {
+    let open_cells = |flow_id: FlowId| {
+        puzzle
+            .get_cells()
+            .iter()
+            .enumerate()
+            .filter(|(index, cell)| {
+                !cell.is_occupied()
+                    && solution.get_cell(CellId { index: *index }).unwrap().flow_id == Some(flow_id)
+            })
+            .count()
+    };
+
+    let flow_id = (0..puzzle.flows.len())
+        .map(|index| FlowId { index })
+        .filter(|flow_id| open_cells(*flow_id) > 0)
+        .min_by_key(|flow_id| open_cells(*flow_id))?;
+
+    Some(Hint::Flow {
+        flow_id,
+        path: solution.trace_path(flow_id)?,
+    })
+}
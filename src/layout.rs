@@ -0,0 +1,372 @@
+use crate::puzzle::{CellId, FlowId, Puzzle, VERTICAL};
+use std::collections::HashMap;
+
+// The width of a cell in a drawing of the puzzle (square or hexagon)
+pub const CELL_SIZE: f64 = 40.0;
+
+// The space around the edge of the board
+pub const MARGIN: f64 = 10.0;
+
+// The sizes of the things drawn on the board
+pub const WALL_WIDTH: f64 = 3.0;
+pub const PIPE_WIDTH: f64 = CELL_SIZE * 0.3;
+pub const DOT_RADIUS: f64 = CELL_SIZE * 0.35;
+// The gap around a pipe going over a bridge, so it can be seen crossing the pipe underneath
+pub const BRIDGE_GAP: f64 = 3.0;
+// Bridges are marked with a faint crossing
+pub const BRIDGE_LANE_WIDTH: f64 = CELL_SIZE * 0.5;
+pub const BRIDGE_LANE_OPACITY: f64 = 0.5;
+
+// How strongly filled in cells are shaded with their flow's color
+pub const SHADE_OPACITY: f64 = 0.3;
+
+pub type Color = (u8, u8, u8);
+
+// The colors of everything other than the flows
+pub const BACKGROUND: Color = (0, 0, 0);
+pub const CELL_BACKGROUND: Color = (17, 17, 17);
+pub const GRID_COLOR: Color = (68, 68, 68);
+pub const WALL_COLOR: Color = (221, 221, 221);
+pub const WARP_COLOR: Color = (51, 170, 255);
+
+// The colors of the flows, in the order the game uses them
+// Puzzles with more flows than this get evenly spread out hues for the rest
+const FLOW_COLORS: [Color; 16] = [
+    (255, 0, 0),     // Red
+    (0, 141, 0),     // Green
+    (12, 41, 254),   // Blue
+    (234, 224, 0),   // Yellow
+    (251, 137, 0),   // Orange
+    (0, 255, 255),   // Cyan
+    (255, 10, 201),  // Magenta
+    (165, 44, 41),   // Maroon
+    (128, 0, 128),   // Purple
+    (255, 255, 255), // White
+    (165, 164, 166), // Gray
+    (0, 255, 0),     // Lime
+    (164, 138, 83),  // Tan
+    (30, 31, 145),   // Navy
+    (0, 128, 128),   // Teal
+    (255, 182, 193), // Pink
+];
+
+pub type Point = (f64, f64);
+
+// A straight piece of a flow's pipe
+#[derive(Debug, Clone, Copy)]
+pub struct PipeSegment {
+    pub flow_id: FlowId,
+    pub segment: (Point, Point),
+    // Pieces going over a bridge have a gap drawn around them first (just the part inside the bridge)
+    pub outline: Option<(Point, Point)>,
+}
+
+// Where everything goes in a drawing of a puzzle
+// Shared by everything that draws puzzles, so they all look the same
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub width: f64,
+    pub height: f64,
+    // The center of each cell, indexed by cell id (the two halves of a bridge share a center)
+    pub centers: Vec<Point>,
+    // The corners of each cell
+    pub shapes: Vec<Vec<Point>>,
+    // Sides of cells that flows can't go through
+    pub walls: Vec<(Point, Point)>,
+    // Sides of cells that flows go through to get to the other side of the board
+    pub warps: Vec<(Point, Point)>,
+    // For two cells joined by a warp, the middle of the side the first cell is left through
+    exits: HashMap<(usize, usize), Point>,
+}
+
+impl Layout {
+    pub fn new(puzzle: &Puzzle) -> Layout {
+        // Cells are every other character in the puzzle file, so halve the coordinates to get the cell's position
+        let positions: Vec<(isize, isize)> = puzzle
+            .get_cells()
+            .iter()
+            .map(|cell| ((cell.col / 2) as isize, (cell.row / 2) as isize))
+            .collect();
+        let mut cells_at: HashMap<(isize, isize), Vec<usize>> = HashMap::new();
+        for (index, position) in positions.iter().enumerate() {
+            cells_at.entry(*position).or_default().push(index);
+        }
+        let max_c = positions.iter().map(|p| p.0).max().unwrap_or(0);
+        let max_r = positions.iter().map(|p| p.1).max().unwrap_or(0);
+
+        // Every neighbor relationship, once each
+        let pairs: Vec<(usize, usize)> = puzzle
+            .get_cells()
+            .iter()
+            .enumerate()
+            .flat_map(|(a, cell)| cell.neighbors.iter().map(move |b| (a, b.index)))
+            .filter(|(a, b)| a < b)
+            .collect();
+
+        let mut layout = Layout {
+            width: 0.0,
+            height: 0.0,
+            centers: vec![],
+            shapes: vec![],
+            walls: vec![],
+            warps: vec![],
+            exits: HashMap::new(),
+        };
+
+        // The corners of a cell, and the position of the cell on the other side of each side (between corner i and i + 1)
+        let hex_radius = CELL_SIZE / 2.0;
+        let hex_height = 3f64.sqrt() * hex_radius;
+        let down_parity = find_down_parity(&positions, &pairs);
+        let shape_at = |(c, r): (isize, isize)| -> (Point, Vec<Point>, Vec<(isize, isize)>) {
+            if puzzle.is_hex {
+                // Flat topped hexagons, in columns. Every other column is shifted down by half a hexagon
+                let is_down = c.rem_euclid(2) == down_parity;
+                let x = MARGIN + hex_radius + c as f64 * 1.5 * hex_radius;
+                let y = MARGIN
+                    + hex_height / 2.0
+                    + r as f64 * hex_height
+                    + if is_down { hex_height / 2.0 } else { 0.0 };
+                let corners = (0..6)
+                    .map(|i| {
+                        let angle = (i as f64 * 60.0).to_radians();
+                        (x + hex_radius * angle.cos(), y + hex_radius * angle.sin())
+                    })
+                    .collect();
+                // Starting from the right corner, going clockwise: SE, S, SW, NW, N, NE
+                let (upper, lower) = if is_down { (r, r + 1) } else { (r - 1, r) };
+                let across = vec![
+                    (c + 1, lower),
+                    (c, r + 1),
+                    (c - 1, lower),
+                    (c - 1, upper),
+                    (c, r - 1),
+                    (c + 1, upper),
+                ];
+                ((x, y), corners, across)
+            } else {
+                let left = MARGIN + c as f64 * CELL_SIZE;
+                let top = MARGIN + r as f64 * CELL_SIZE;
+                let corners = vec![
+                    (left, top),
+                    (left + CELL_SIZE, top),
+                    (left + CELL_SIZE, top + CELL_SIZE),
+                    (left, top + CELL_SIZE),
+                ];
+                // N, E, S, W
+                let across = vec![(c, r - 1), (c + 1, r), (c, r + 1), (c - 1, r)];
+                (
+                    (left + CELL_SIZE / 2.0, top + CELL_SIZE / 2.0),
+                    corners,
+                    across,
+                )
+            }
+        };
+
+        if puzzle.is_hex {
+            layout.width = 2.0 * MARGIN + 2.0 * hex_radius + max_c as f64 * 1.5 * hex_radius;
+            layout.height = 2.0 * MARGIN + (max_r as f64 + 1.5) * hex_height;
+        } else {
+            layout.width = 2.0 * MARGIN + (max_c + 1) as f64 * CELL_SIZE;
+            layout.height = 2.0 * MARGIN + (max_r + 1) as f64 * CELL_SIZE;
+        }
+
+        for position in positions.iter() {
+            let (center, corners, _) = shape_at(*position);
+            layout.centers.push(center);
+            layout.shapes.push(corners);
+        }
+
+        // Neighbors that aren't next to each other are warps (only square puzzles have them)
+        // The warp leaves through the side facing away from the other cell, and comes in the opposite side of it
+        let mut warp_sides: Vec<((isize, isize), usize)> = vec![];
+        for (a, b) in pairs.iter() {
+            let (pa, pb) = (positions[*a], positions[*b]);
+            let (_, _, across) = shape_at(pa);
+            if pa == pb || across.contains(&pb) || puzzle.is_hex {
+                continue;
+            }
+            // Sides are N, E, S, W
+            let (side_a, side_b) = if pa.1 == pb.1 {
+                if pb.0 < pa.0 {
+                    (1, 3)
+                } else {
+                    (3, 1)
+                }
+            } else if pb.1 < pa.1 {
+                (2, 0)
+            } else {
+                (0, 2)
+            };
+            let exit_a = layout.side_middle(*a, side_a);
+            let exit_b = layout.side_middle(*b, side_b);
+            layout.exits.insert((*a, *b), exit_a);
+            layout.exits.insert((*b, *a), exit_b);
+            warp_sides.push((pa, side_a));
+            warp_sides.push((pb, side_b));
+        }
+
+        // Every side of every cell is either a warp, open (there's a neighbor across it), or a wall
+        let mut sorted_positions: Vec<(isize, isize)> = cells_at.keys().copied().collect();
+        sorted_positions.sort();
+        for position in sorted_positions.iter() {
+            let (_, corners, across) = shape_at(*position);
+            for (side, other) in across.iter().enumerate() {
+                let segment = (corners[side], corners[(side + 1) % corners.len()]);
+                if warp_sides.contains(&(*position, side)) {
+                    layout.warps.push(segment);
+                    continue;
+                }
+                let other_cells = match cells_at.get(other) {
+                    // Sides between two cells only need to be drawn once
+                    Some(_) if other < position => continue,
+                    Some(other_cells) => other_cells,
+                    None => {
+                        layout.walls.push(segment);
+                        continue;
+                    }
+                };
+                let is_open = cells_at[position].iter().any(|a| {
+                    other_cells.iter().any(|b| {
+                        puzzle
+                            .get_cell(CellId { index: *a })
+                            .unwrap()
+                            .is_neighbor(&CellId { index: *b })
+                    })
+                });
+                if !is_open {
+                    layout.walls.push(segment);
+                }
+            }
+        }
+
+        layout
+    }
+
+    // The two lanes of a bridge's crossing, as lines with round ends (which reach the rest of the way to the edges)
+    pub fn bridge_lanes(&self, cell: CellId) -> [(Point, Point); 2] {
+        let (x, y) = self.centers[cell.index];
+        let half = (CELL_SIZE - BRIDGE_LANE_WIDTH) / 2.0;
+        [
+            ((x - half, y), (x + half, y)),
+            ((x, y - half), (x, y + half)),
+        ]
+    }
+
+    // The middle of one of a cell's sides
+    fn side_middle(&self, cell: usize, side: usize) -> Point {
+        let corners = &self.shapes[cell];
+        let (a, b) = (corners[side], corners[(side + 1) % corners.len()]);
+        ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+    }
+
+    // The pieces of pipe joining up the given pairs of cells, in the order they should be drawn
+    // Pieces going over bridges come last, so they're drawn on top of the other half of the bridge.
+    // Their outlines should all be drawn before any of them, so the outlines don't cut into each other
+    pub fn pipe_segments(
+        &self,
+        puzzle: &Puzzle,
+        connections: &[(CellId, CellId)],
+    ) -> Vec<PipeSegment> {
+        let is_vertical =
+            |id: CellId| puzzle.get_cell(id).unwrap().bridge_direction == Some(VERTICAL);
+        let mut segments: Vec<PipeSegment> = vec![];
+        for (a, b) in connections.iter() {
+            let flow_id = puzzle.get_cell(*a).unwrap().flow_id.unwrap();
+            for segment in self.pipe(*a, *b) {
+                let ((x1, y1), (x2, y2)) = segment;
+                let middle = ((x1 + x2) / 2.0, (y1 + y2) / 2.0);
+                let outline = if is_vertical(*a) {
+                    Some((segment.0, middle))
+                } else if is_vertical(*b) {
+                    Some((middle, segment.1))
+                } else {
+                    None
+                };
+                segments.push(PipeSegment {
+                    flow_id,
+                    segment,
+                    outline,
+                });
+            }
+        }
+        segments.sort_by_key(|piece| piece.outline.is_some());
+        segments
+    }
+
+    // The line segments for a flow going from one cell to its neighbor
+    // Usually straight from one center to the other, but warps go out of one side of the board and in the other
+    fn pipe(&self, from: CellId, to: CellId) -> Vec<(Point, Point)> {
+        let (a, b) = (from.index, to.index);
+        match (self.exits.get(&(a, b)), self.exits.get(&(b, a))) {
+            (Some(exit_a), Some(exit_b)) => {
+                vec![(self.centers[a], *exit_a), (*exit_b, self.centers[b])]
+            }
+            _ => vec![(self.centers[a], self.centers[b])],
+        }
+    }
+}
+
+// Which columns of a hex puzzle are shifted down (0 for even, 1 for odd)
+// This depends on the puzzle, so work it out from a diagonal neighbor relationship
+fn find_down_parity(positions: &[(isize, isize)], pairs: &[(usize, usize)]) -> isize {
+    for (a, b) in pairs.iter() {
+        let (mut pa, mut pb) = (positions[*a], positions[*b]);
+        if pa.0 > pb.0 {
+            std::mem::swap(&mut pa, &mut pb);
+        }
+        if pb.0 - pa.0 != 1 || pa.1 == pb.1 {
+            continue;
+        }
+        // Going up to the right means the right column is lower, going down to the right means the left column is
+        return if pb.1 < pa.1 {
+            pb.0.rem_euclid(2)
+        } else {
+            pa.0.rem_euclid(2)
+        };
+    }
+    1
+}
+
+// The color of the flow with the given index, as red, green and blue
+pub fn flow_color(index: usize) -> Color {
+    if index < FLOW_COLORS.len() {
+        return FLOW_COLORS[index];
+    }
+
+    // Spread the hues out using the golden angle, so neighboring indices look different
+    let hue = (index as f64 * 137.508) % 360.0;
+    let (saturation, lightness) = (0.75, 0.55);
+    let chroma = (1.0 - (2.0 * lightness - 1.0f64).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match (hue / 60.0) as usize {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |v: f64| ((v + m) * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+// Mix a color over another, as if drawn on top of it with the given opacity
+pub fn blend(color: Color, under: Color, opacity: f64) -> Color {
+    let mix = |a: u8, b: u8| (a as f64 * opacity + b as f64 * (1.0 - opacity)).round() as u8;
+    (
+        mix(color.0, under.0),
+        mix(color.1, under.1),
+        mix(color.2, under.2),
+    )
+}
+
+// Dark text on light colors, light text on dark ones
+pub fn text_color(background: Color) -> Color {
+    let (r, g, b) = background;
+    if 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64 > 128.0 {
+        (0, 0, 0)
+    } else {
+        (255, 255, 255)
+    }
+}
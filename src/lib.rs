@@ -0,0 +1,19 @@
+pub mod batch;
+pub mod bench;
+pub mod convert;
+pub mod exact;
+pub mod explain;
+pub mod generate;
+pub mod gif;
+pub mod hint;
+pub mod layout;
+pub mod logic;
+pub mod observer;
+pub mod parser;
+pub mod puzzle;
+pub mod rate;
+pub mod solver;
+pub mod spill;
+pub mod svg;
+pub mod terminal;
+pub mod verify;
@@ -0,0 +1,459 @@
+use crate::hint::Hint;
+use crate::puzzle::{CellId, Flow, FlowId, Puzzle};
+use crate::solver::{solve, Outcome, SolverOptions};
+
+// The ways of working out a move, roughly in the order a person would reach for them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Technique {
+    // An endpoint with only one empty cell to move into
+    OnlyMove,
+    // An empty cell with only two ways in (like a corner), so a path has to come in through both of them
+    Corner,
+    // Every other move would leave an empty cell that no path can get through, or an endpoint with nowhere to go
+    DeadEnd,
+    // Every other move would run the flow alongside itself
+    NoPool,
+    // Every other move would cut off some empty cells that no flow can reach from both ends, or cut a flow off
+    // from its other end
+    RegionOwnership,
+    // No rule applies, so the move was taken from a solution found by searching
+    Guess,
+}
+
+impl Technique {
+    pub const ALL: [Technique; 6] = [
+        Technique::OnlyMove,
+        Technique::Corner,
+        Technique::DeadEnd,
+        Technique::NoPool,
+        Technique::RegionOwnership,
+        Technique::Guess,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Technique::OnlyMove => "only move",
+            Technique::Corner => "corner",
+            Technique::DeadEnd => "dead end",
+            Technique::NoPool => "no pools",
+            Technique::RegionOwnership => "region ownership",
+            Technique::Guess => "guess",
+        }
+    }
+
+    // Why a move this technique rules out can't be right
+    fn rules_out(&self) -> &'static str {
+        match self {
+            Technique::DeadEnd => "would leave a dead end",
+            Technique::NoPool => "would run the flow alongside itself",
+            Technique::RegionOwnership => "would cut off cells no flow can fill",
+            _ => "was also possible, but the search went the other way",
+        }
+    }
+}
+
+// The techniques that work by ruling out every move but one, from the simplest to the hardest to spot
+const ELIMINATIONS: [Technique; 3] = [
+    Technique::DeadEnd,
+    Technique::NoPool,
+    Technique::RegionOwnership,
+];
+
+// A single move, and how it was worked out
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub technique: Technique,
+    pub flow_id: FlowId,
+    pub from: CellId,
+    pub to: CellId,
+    // The endpoint's other moves, and the technique that ruled each one out
+    pub ruled_out: Vec<(CellId, Technique)>,
+}
+
+impl Step {
+    // Describe the move and the reasoning behind it
+    pub fn describe(&self, puzzle: &Puzzle) -> String {
+        let location = |id: CellId| puzzle.get_cell(id).unwrap().location();
+        let mut description = format!(
+            "Flow {} goes from {} to {}: ",
+            puzzle.get_flow(self.flow_id).unwrap().get_label(),
+            location(self.from),
+            location(self.to)
+        );
+        description.push_str(match self.technique {
+            Technique::OnlyMove => "there's nowhere else for it to go",
+            Technique::Corner => {
+                "that cell only has two ways in, so whatever fills it has to come from here"
+            }
+            Technique::Guess => "no rule applies, so this is a guess (checked by searching)",
+            _ => "every other move is ruled out",
+        });
+        for (to, technique) in self.ruled_out.iter() {
+            description.push_str(&format!(
+                "\n\t{} {} ({})",
+                location(*to),
+                technique.rules_out(),
+                technique.name()
+            ));
+        }
+        description
+    }
+}
+
+// The steps taken to solve a puzzle by logic, as far as they got
+#[derive(Debug, Clone)]
+pub struct Deduction {
+    pub steps: Vec<Step>,
+    // The board after the last step: the solution, unless it gave up
+    pub board: Puzzle,
+    // If it gave up, how the search for a way on went (no solution, or it gave up)
+    pub gave_up: Option<Outcome>,
+}
+
+impl Deduction {
+    pub fn is_solved(&self) -> bool {
+        self.gave_up.is_none()
+    }
+
+    // How many steps used the technique
+    pub fn count(&self, technique: Technique) -> usize {
+        self.steps
+            .iter()
+            .filter(|step| step.technique == technique)
+            .count()
+    }
+
+    // The numbers of the steps (from 1) where a guess was needed
+    pub fn guesses(&self) -> Vec<usize> {
+        (1..=self.steps.len())
+            .filter(|number| self.steps[number - 1].technique == Technique::Guess)
+            .collect()
+    }
+
+    // Describe every step, then sum up which techniques were needed
+    pub fn describe(&self, puzzle: &Puzzle) -> String {
+        let mut description = String::new();
+        for (number, step) in self.steps.iter().enumerate() {
+            description.push_str(&format!("{}. {}\n", number + 1, step.describe(puzzle)));
+        }
+        if !self.steps.is_empty() {
+            description.push('\n');
+        }
+
+        description.push_str(&match &self.gave_up {
+            None => format!("Solved in {} steps\n", self.steps.len()),
+            Some(Outcome::Unsolvable) => format!(
+                "Stuck after {} steps: there's no way to finish the board from here\n",
+                self.steps.len()
+            ),
+            Some(_) => format!(
+                "Stuck after {} steps: the search for the next move gave up\n",
+                self.steps.len()
+            ),
+        });
+        for technique in Technique::ALL {
+            let count = self.count(technique);
+            if count > 0 {
+                description.push_str(&format!("\t{}: {}\n", technique.name(), count));
+            }
+        }
+
+        let guesses = self.guesses();
+        if guesses.is_empty() {
+            description.push_str("No guessing needed\n");
+        } else {
+            let numbers: Vec<String> = guesses.iter().map(|number| number.to_string()).collect();
+            description.push_str(&format!(
+                "Guessing needed at step(s) {}\n",
+                numbers.join(", ")
+            ));
+        }
+        description
+    }
+}
+
+// Solve the puzzle the way a person would, one move at a time, using the simplest technique that finds a move
+// Only when none of them do is a solution searched for (within the options' limits) and a move taken from it,
+// which is marked as a guess
+pub fn deduce(puzzle: &Puzzle, options: &SolverOptions) -> Deduction {
+    let mut state = puzzle.clone();
+    let mut steps = vec![];
+    while !state.is_complete() {
+        let step = match next_step(&state) {
+            Some(step) => step,
+            None => match guess(&state, options) {
+                Ok(step) => step,
+                Err(outcome) => {
+                    return Deduction {
+                        steps,
+                        board: state,
+                        gave_up: Some(outcome),
+                    }
+                }
+            },
+        };
+        Hint::Move {
+            from: step.from,
+            to: step.to,
+            forced: true,
+        }
+        .apply(&mut state);
+        steps.push(step);
+    }
+    Deduction {
+        steps,
+        board: state,
+        gave_up: None,
+    }
+}
+
+// Find a move with the simplest technique that gives one, or None if none of them do
+fn next_step(state: &Puzzle) -> Option<Step> {
+    let endpoints = state.get_endpoints_for_incomplete_flows();
+    let flow_of = |id: CellId| state.get_cell(id).unwrap().flow_id.unwrap();
+    let moves = |id: CellId| -> Vec<CellId> {
+        state
+            .get_cell(id)
+            .unwrap()
+            .neighbors
+            .iter()
+            .filter(|n| state.get_cell(**n).unwrap().is_open_to(flow_of(id)))
+            .copied()
+            .collect()
+    };
+
+    for from in endpoints.iter() {
+        if let [to] = moves(*from)[..] {
+            return Some(Step {
+                technique: Technique::OnlyMove,
+                flow_id: flow_of(*from),
+                from: *from,
+                to,
+                ruled_out: vec![],
+            });
+        }
+    }
+
+    for (index, cell) in state.get_cells().iter().enumerate() {
+        let id = CellId { index };
+        if cell.is_occupied() {
+            continue;
+        }
+        let ways = ways_in(state, id);
+        let heads: Vec<&CellId> = ways
+            .iter()
+            .filter(|way| state.get_cell(**way).unwrap().is_occupied())
+            .collect();
+        // Two different flows can't both go through it, so leave that for the other techniques to sort out
+        if ways.len() != 2
+            || heads.is_empty()
+            || heads
+                .iter()
+                .any(|head| flow_of(**head) != flow_of(*heads[0]))
+        {
+            continue;
+        }
+        return Some(Step {
+            technique: Technique::Corner,
+            flow_id: flow_of(*heads[0]),
+            from: *heads[0],
+            to: id,
+            ruled_out: vec![],
+        });
+    }
+
+    for (level, technique) in ELIMINATIONS.iter().enumerate() {
+        for from in endpoints.iter() {
+            let options = moves(*from);
+            if options.len() < 2 {
+                continue;
+            }
+            let mut survivors = vec![];
+            let mut ruled_out = vec![];
+            for to in options {
+                match ruled_out_by(state, *from, to, &ELIMINATIONS[..=level]) {
+                    Some(reason) => ruled_out.push((to, reason)),
+                    None => survivors.push(to),
+                }
+            }
+            if let [to] = survivors[..] {
+                return Some(Step {
+                    technique: *technique,
+                    flow_id: flow_of(*from),
+                    from: *from,
+                    to,
+                    ruled_out,
+                });
+            }
+        }
+    }
+    None
+}
+
+// The neighbors a path through the empty cell could come from: empty cells, and endpoints of incomplete flows
+// that are allowed in
+fn ways_in(state: &Puzzle, id: CellId) -> Vec<CellId> {
+    let cell = state.get_cell(id).unwrap();
+    cell.neighbors
+        .iter()
+        .filter(|n| {
+            let neighbor = state.get_cell(**n).unwrap();
+            match neighbor.flow_id {
+                None => true,
+                Some(flow_id) => {
+                    neighbor.is_endpoint
+                        && !state.get_flow(flow_id).unwrap().is_complete(state)
+                        && cell.is_open_to(flow_id)
+                }
+            }
+        })
+        .copied()
+        .collect()
+}
+
+// Which of the techniques (if any) rules out moving the endpoint into the cell
+fn ruled_out_by(
+    state: &Puzzle,
+    from: CellId,
+    to: CellId,
+    techniques: &[Technique],
+) -> Option<Technique> {
+    let flow_id = state.get_cell(from).unwrap().flow_id.unwrap();
+    let mut child = state.clone();
+    Hint::Move {
+        from,
+        to,
+        forced: true,
+    }
+    .apply(&mut child);
+
+    techniques
+        .iter()
+        .copied()
+        .find(|technique| match technique {
+            Technique::DeadEnd => leaves_dead_end(&child, [from, to]),
+            // Hexes always have the cell before last next to the new one, so this doesn't work for them
+            Technique::NoPool => !child.is_hex && runs_alongside(&child, flow_id, from, to),
+            Technique::RegionOwnership => cuts_off(&child),
+            _ => false,
+        })
+}
+
+// Is there an empty cell next to where the flow moved that can't be filled any more, or an endpoint with
+// nowhere to go?
+fn leaves_dead_end(state: &Puzzle, moved: [CellId; 2]) -> bool {
+    let stuck = state
+        .get_endpoints_for_incomplete_flows()
+        .iter()
+        .any(|id| state.get_cell(*id).unwrap().num_moves(state) == 0);
+    if stuck {
+        return true;
+    }
+
+    // The cells next to the move, and next to every endpoint (an endpoint might have just been completed)
+    let mut nearby: Vec<CellId> = moved.to_vec();
+    nearby.extend(
+        state
+            .flows
+            .iter()
+            .flat_map(|flow| flow.get_endpoints().into_iter()),
+    );
+    nearby.iter().any(|id| {
+        state.get_cell(*id).unwrap().neighbors.iter().any(|n| {
+            if state.get_cell(*n).unwrap().is_occupied() {
+                return false;
+            }
+            // A path needs two ways in, and two different flows can't share one
+            let ways = ways_in(state, *n);
+            let heads: Vec<FlowId> = ways
+                .iter()
+                .filter_map(|way| state.get_cell(*way).unwrap().flow_id)
+                .collect();
+            ways.len() < 2 || (ways.len() == 2 && heads.len() == 2 && heads[0] != heads[1])
+        })
+    })
+}
+
+// Has the flow just moved in next to its own path (other than where it came from, or its other end)?
+fn runs_alongside(state: &Puzzle, flow_id: FlowId, from: CellId, to: CellId) -> bool {
+    let other_end = state
+        .get_flow(flow_id)
+        .unwrap()
+        .get_endpoints()
+        .into_iter()
+        .find(|id| *id != to)
+        .unwrap();
+    state.get_cell(to).unwrap().neighbors.iter().any(|n| {
+        *n != from && *n != other_end && state.get_cell(*n).unwrap().flow_id == Some(flow_id)
+    })
+}
+
+// Is there an area of empty cells that no flow can reach from both ends, or a flow whose ends can't reach the
+// same area?
+fn cuts_off(state: &Puzzle) -> bool {
+    let regions = state.get_regions();
+    let borders = |endpoint: CellId, region: &Vec<usize>| {
+        state
+            .get_cell(endpoint)
+            .unwrap()
+            .neighbors
+            .iter()
+            .any(|n| region.contains(&n.index))
+    };
+    let owned_by = |flow: &&Flow, region: &Vec<usize>| {
+        borders(flow.get_endpoint(0), region) && borders(flow.get_endpoint(1), region)
+    };
+    let incomplete: Vec<_> = state
+        .flows
+        .iter()
+        .filter(|flow| !flow.is_complete(state))
+        .collect();
+
+    regions
+        .iter()
+        .any(|region| !incomplete.iter().any(|flow| owned_by(flow, region)))
+        || incomplete
+            .iter()
+            .any(|flow| !regions.iter().any(|region| owned_by(flow, region)))
+}
+
+// Search for a solution from here, and take the next move of the endpoint with the fewest options from it
+// Returns how the search went if it didn't find one
+fn guess(state: &Puzzle, options: &SolverOptions) -> Result<Step, Outcome> {
+    let solution = match solve(state.clone(), options, &mut ()).outcome {
+        Outcome::Solved(solution) => solution,
+        outcome => return Err(outcome),
+    };
+
+    // The solver remembers where each cell it filled came from, so follow that out of an endpoint
+    let mut endpoints = state.get_endpoints_for_incomplete_flows();
+    let preferred = state.get_endpoint_to_extend();
+    endpoints.sort_by_key(|id| *id != preferred);
+    for from in endpoints {
+        let cell = state.get_cell(from).unwrap();
+        let flow_id = cell.flow_id.unwrap();
+        let open: Vec<CellId> = cell
+            .neighbors
+            .iter()
+            .filter(|n| state.get_cell(**n).unwrap().is_open_to(flow_id))
+            .copied()
+            .collect();
+        if let Some(to) = open
+            .iter()
+            .find(|n| solution.get_cell(**n).unwrap().previous == Some(from))
+        {
+            return Ok(Step {
+                technique: Technique::Guess,
+                flow_id,
+                from,
+                to: *to,
+                ruled_out: open
+                    .iter()
+                    .filter(|n| *n != to)
+                    .map(|n| (*n, Technique::Guess))
+                    .collect(),
+            });
+        }
+    }
+    Err(Outcome::Unsolvable)
+}
@@ -1,958 +1,1038 @@
+extern crate flow_free_solver_rust;
 extern crate time;
 
-use std::cmp::max;
-use std::cmp::Ordering;
-use std::collections::BinaryHeap;
-use std::collections::HashMap;
+use flow_free_solver_rust::batch::{
+    find_puzzles, solve_batch, summary_table, to_csv, BatchOutcome,
+};
+use flow_free_solver_rust::bench::{
+    bench_table, read_baseline, run_bench, to_baseline, BenchConfig,
+};
+use flow_free_solver_rust::convert::PuzzleFile;
+use flow_free_solver_rust::explain::explain;
+use flow_free_solver_rust::generate::{generate, GenerateOptions};
+use flow_free_solver_rust::gif::render_gif;
+use flow_free_solver_rust::hint::{find_flow, find_move};
+use flow_free_solver_rust::logic::deduce;
+use flow_free_solver_rust::observer::{ConsoleObserver, Verbosity};
+use flow_free_solver_rust::parser::parse_puzzle;
+use flow_free_solver_rust::puzzle::Puzzle;
+use flow_free_solver_rust::rate::rate;
+use flow_free_solver_rust::solver::{
+    solve, Backend, Heuristic, Moves, Outcome, Recording, SolverOptions, DEFAULT_BEAM_WIDTH,
+};
+use flow_free_solver_rust::svg::render_svg;
+use flow_free_solver_rust::terminal::render_terminal;
+use flow_free_solver_rust::verify::verify_solution;
 use std::env;
-use std::ffi::OsStr;
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::Path;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use time::Instant;
 
-const NON_EXISTENT_CELL_ID: usize = 999;
-const MAX_NEIGHBORS: usize = 6;
-
-const SOLVABLE: i16 = 1;
-const UNSOLVABLE_NO_CHILDREN: i16 = -1;
-const UNSOLVABLE_DEAD_ENDS: i16 = -2;
-const UNSOLVABLE_POOLS: i16 = -3;
-const UNSOLVABLE_PATH_BLOCKED: i16 = -4;
-const UNSOLVABLE_REGION: i16 = -5;
-
-// All of the neighbor constants
-const VERTICAL: usize = 0;
-const HORIZONTAL: usize = 1;
-
-// Structures and implementations related to flows
-#[derive(Debug, Eq, Clone)]
-pub struct Flow {
-    pub id: usize,
-    endpoints: [Option<CellId>; 2],
-    letter: char,
+// Exit codes, so the result can be checked from scripts
+// Solved, or whatever else was asked for worked out (a valid solution, a hint, a puzzle that can be solved...)
+const EXIT_SOLVED: i32 = 0;
+// No solution, or whatever else was asked for didn't work out (an invalid solution, no hint...)
+const EXIT_UNSOLVABLE: i32 = 1;
+// A file couldn't be read, or isn't a valid puzzle
+const EXIT_PARSE_ERROR: i32 = 2;
+// The solver hit the time or state limit before finding an answer
+const EXIT_TIMEOUT: i32 = 3;
+// The command line didn't make sense
+const EXIT_USAGE: i32 = 64;
+
+// The filename that means standard input
+const STDIN: &str = "-";
+
+const COMMANDS: [&str; 12] = [
+    "solve", "verify", "hint", "steps", "explain", "rate", "render", "convert", "batch", "bench",
+    "generate", "help",
+];
+
+// How long generate spends checking each board for a second solution, unless told otherwise
+const GENERATE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The ways a board can be shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Color,
+    Svg,
+    Gif,
+    Json,
 }
-impl PartialEq for Flow {
-    fn eq(&self, _other: &Flow) -> bool {
-        false
-    }
-}
-impl Flow {
-    // Update the endpoint at the given index to the given cellID
-    // endpoint should be 0 or 1
-    pub fn update_endpoint(&mut self, endpoint: usize, cell_id: CellId) {
-        self.endpoints[endpoint] = Some(cell_id);
-    }
-
-    // Return true if this flow's endpoints are neighbors
-    pub fn is_complete(&self, puzzle: &Puzzle) -> bool {
-        puzzle
-            .get_cell(self.get_endpoint(0))
-            .unwrap()
-            .is_neighbor(&self.get_endpoint(1))
-    }
-
-    pub fn get_endpoints(&self) -> [CellId; 2] {
-        [self.endpoints[0].unwrap(), self.endpoints[1].unwrap()]
-    }
-
-    pub fn get_endpoint(&self, i: usize) -> CellId {
-        self.get_endpoints()[i]
-    }
-
-    // Getter for attribute 'letter'
-    pub fn get_letter(&self) -> char {
-        self.letter
-    }
-}
-
-#[derive(Debug, Eq, Clone, Copy)]
-pub struct FlowId {
-    pub index: usize,
-}
-impl PartialEq for FlowId {
-    fn eq(&self, other: &FlowId) -> bool {
-        self.index.eq(&other.index)
-    }
-}
-
-// Structures and implementations related to cells
-#[derive(Debug, Clone, Eq)]
-pub struct Cell {
-    pub is_endpoint: bool,
-    pub flow_id: Option<FlowId>,
-    pub neighbors: Vec<CellId>,
-    pub is_hex: bool,
-}
-impl PartialEq for Cell {
-    fn eq(&self, _other: &Cell) -> bool {
-        false
-    }
-}
-impl Cell {
-    // Update the given neighbor
-    pub fn add_neighbor(&mut self, cell: CellId) {
-        self.neighbors.push(cell);
-    }
 
-    // Given a CellId, check if that cell in a neighbor of this cell
-    pub fn is_neighbor(&self, other: &CellId) -> bool {
-        self.neighbors
-            .iter()
-            .filter(|n| n.index == other.index)
-            .count()
-            > 0
-    }
-
-    pub fn is_occupied(&self) -> bool {
-        self.flow_id.is_some()
+impl Format {
+    fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "text" => Some(Format::Text),
+            "color" => Some(Format::Color),
+            "svg" => Some(Format::Svg),
+            "gif" => Some(Format::Gif),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
     }
 
-    // Return the number of neighboring cells that are not occupied
-    pub fn num_open_neighbors(&self, puzzle: &Puzzle) -> usize {
-        self.neighbors
-            .iter()
-            .filter(|n| !puzzle.get_cell(**n).unwrap().is_occupied())
-            .count()
+    fn name(&self) -> &'static str {
+        match self {
+            Format::Text => "text",
+            Format::Color => "color",
+            Format::Svg => "svg",
+            Format::Gif => "gif",
+            Format::Json => "json",
+        }
     }
 
-    // Just the number of neighbors
-    pub fn num_neighbors(&self) -> usize {
-        self.neighbors.len()
+    // The formats each command can show its result in
+    fn allowed_for(command: &str) -> &'static [Format] {
+        match command {
+            "solve" => &[Format::Text, Format::Color, Format::Svg, Format::Gif],
+            "hint" | "steps" | "render" => &[Format::Text, Format::Color, Format::Svg],
+            "convert" | "generate" => &[Format::Text, Format::Json],
+            _ => &[],
+        }
     }
 }
 
-#[derive(Debug, Default, Eq, Copy, Clone)]
-pub struct CellId {
-    pub index: usize,
-}
-impl PartialEq for CellId {
-    fn eq(&self, other: &CellId) -> bool {
-        self.index.eq(&other.index)
-    }
+// Everything given on the command line
+#[derive(Debug)]
+struct Arguments {
+    command: String,
+    files: Vec<String>,
+    format: Option<Format>,
+    output: Option<String>,
+    // With the gif format, add a frame every this many states
+    every: u64,
+    // Hint a whole flow instead of a single move
+    whole_flow: bool,
+    // Which level to read from a level pack
+    level: usize,
+    // How many puzzles to solve at once in a batch
+    jobs: usize,
+    // Save the batch results to this CSV file
+    csv: Option<String>,
+    // The solvers, heuristics and ways of moving to compare in a benchmark (only ever one of each for the other
+    // commands)
+    backends: Vec<Backend>,
+    heuristics: Vec<Heuristic>,
+    moves: Vec<Moves>,
+    // How many times to solve each puzzle in a benchmark
+    runs: usize,
+    // Compare the benchmark against the results in this file
+    baseline: Option<String>,
+    // Save the benchmark results to this file, to compare against later
+    save_baseline: Option<String>,
+    // The puzzle to make with generate
+    generate: GenerateOptions,
+    help: bool,
+    verbosity: Verbosity,
+    options: SolverOptions,
 }
 
-#[derive(Debug, Eq, Clone, PartialEq)]
-pub struct Puzzle {
-    cells: Vec<Cell>,
-    pub flows: Vec<Flow>,
-    pub is_hex: bool,
-    pub print_string: String,
+fn help_text() -> String {
+    format!(
+        "Usage: flow_free_solver_rust [command] [options] <puzzle>
+
+The command goes first, and the options can go anywhere after it
+Puzzles can be in the regular text format, JSON (see convert) or one of the game's level packs, and - reads a
+puzzle from standard input
+
+Commands:
+  solve <puzzle>              Solve the puzzle (what happens if no command is given)
+  verify <puzzle> <solution>  Check a solution to the puzzle
+  hint <puzzle>               Show a single move (or a whole flow, with --flow) without spoiling the rest
+  steps <puzzle>              Solve the puzzle one move at a time the way a person would, explaining each move and
+                              pointing out where guessing was needed
+  explain <puzzle>            Find out why the puzzle can't be solved
+  rate <puzzle>               Grade how difficult the puzzle is, from how much of it can be worked out without
+                              searching and how hard the search has to work
+  render <puzzle>             Draw the puzzle as it is, along with any pre-drawn paths
+  convert <puzzle>            Check the puzzle, and write it back out tidied up (as text or JSON)
+  batch <puzzles>...          Solve all the puzzles (directories and wildcards like puzzles/*/Daily* are fine) and
+                              show how each went in a table
+  bench <puzzles>...          Solve the puzzles several times with each of the given solvers, heuristics and
+                              ways of moving, and compare how they did
+  generate                    Make a random puzzle with exactly one solution
+  help                        Show this message
+
+Options:
+  --solver <name>        How to search for a solution: {}
+  --heuristic <name>     How to choose which state to look at next: {}
+  --moves <name>         How to make the next states from each one: {}
+                         (bench takes a list of each, e.g. --heuristic default,open-cells)
+  -f, --format <format>  How to show the result: text, color or svg (or gif, when solving, or json, when converting)
+                         Color in a terminal (unless NO_COLOR is set), text anywhere else, if not given
+  -o, --output <file>    Save the result to a file instead of printing it (needed for gif)
+  --every <states>       Add a frame to the gif every so many states (1 if not given, and never more than 500 frames)
+  --timeout <seconds>    Give up solving after this long
+  --max-states <states>  Give up solving after looking at this many states
+  --weight <number>      How much the astar solver trusts its estimate of the moves left (1 if not given, which
+                         is plain A*; higher is weighted A*, which is usually quicker)
+  --beam <width>         How many states the beam solver keeps at each depth ({} if not given)
+  --spill <states>       Keep at most this many states in memory (greedy and astar), writing the rest to disk
+  --spill-dir <dir>      Where to write them (the system's temporary directory if not given)
+  --level <number>       Which level to read, for a level pack (1 if not given)
+  -j, --jobs <count>     Solve this many of the batch's puzzles at once (1 if not given)
+  --csv <file>           Save the batch's results to a CSV file too
+  --runs <count>         How many times bench solves each puzzle, taking the median time (3 if not given)
+  --baseline <file>      Compare the benchmark to the results saved in the file
+  --save-baseline <file> Save the benchmark's results to the file, to compare against later
+  --flow                 Give a whole flow as the hint, instead of a single move
+  --type <type>          The type of puzzle to generate: standard, bridges, hex or warps (standard if not given)
+  --size <width>x<height>
+                         The size of puzzle to generate, e.g. 7x7 or just 7 (5x5 if not given)
+  --flows <count>        How many flows to generate (as many as the board is wide, if not given)
+  --seed <number>        Generate the same puzzle as last time with this seed (random if not given)
+  -q, --quiet            Only print the result
+  -v, --verbose          Print more about the search (e.g. the last states looked at, if there's no solution)
+  -h, --help             Show this message
+
+Exit codes:
+  {}   Solved (or the solution is valid, there's a hint, the puzzle can be solved, the whole batch was solved)
+  {}   No solution (or the solution is invalid, there's no hint, the puzzle can't be solved)
+  {}   A file couldn't be read or written, or isn't a valid puzzle
+  {}   Gave up because of --timeout or --max-states (or the beam ran out of states, there were too many
+       routes, or the states spilled to disk couldn't be read back)
+       (for a batch: 2 if any puzzle couldn't be read, otherwise 1 if any had no solution, otherwise 3 if any gave up)
+  {}  The command line didn't make sense",
+        Backend::NAMES.join(", "),
+        Heuristic::NAMES.join(", "),
+        Moves::NAMES.join(", "),
+        DEFAULT_BEAM_WIDTH,
+        EXIT_SOLVED,
+        EXIT_UNSOLVABLE,
+        EXIT_PARSE_ERROR,
+        EXIT_TIMEOUT,
+        EXIT_USAGE
+    )
 }
 
-impl Ord for Puzzle {
-    fn cmp(&self, other: &Puzzle) -> Ordering {
-        let s = self.h();
-        let o = other.h();
-        s.cmp(&o)
-    }
-}
+// Make sense of the command line (without the program name)
+fn parse_arguments(args: &[String]) -> Result<Arguments, String> {
+    let mut arguments = Arguments {
+        command: String::from("solve"),
+        files: vec![],
+        format: None,
+        output: None,
+        every: 1,
+        whole_flow: false,
+        level: 1,
+        jobs: 1,
+        csv: None,
+        backends: vec![Backend::Greedy],
+        heuristics: vec![Heuristic::Default],
+        moves: vec![Moves::Endpoint],
+        runs: 3,
+        baseline: None,
+        save_baseline: None,
+        generate: GenerateOptions {
+            puzzle_type: String::from("standard"),
+            width: 5,
+            height: 5,
+            flows: 0,
+            seed: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_nanos() as u64),
+        },
+        help: false,
+        verbosity: Verbosity::Normal,
+        options: SolverOptions::default(),
+    };
 
-// 'PartialOrd' needs to be implemented as well.
-impl PartialOrd for Puzzle {
-    fn partial_cmp(&self, other: &Puzzle) -> Option<Ordering> {
-        Some(self.cmp(other))
+    let mut args = args.iter().peekable();
+    if let Some(command) = args.next_if(|arg| COMMANDS.contains(&arg.as_str())) {
+        arguments.command = command.clone();
     }
-}
 
-impl Puzzle {
-    // Create a new puzzle
-    pub fn new(is_hex: bool, print_string: String) -> Puzzle {
-        Puzzle {
-            cells: Vec::new(),
-            flows: Vec::new(),
-            is_hex,
-            print_string,
-        }
-    }
-
-    // Crate a new cell
-    pub fn new_cell(&mut self, is_endpoint: bool, flow_id: Option<FlowId>, is_hex: bool) -> CellId {
-        let next_index = self.num_cells();
-        self.cells.push(Cell {
-            is_endpoint,
-            flow_id,
-            neighbors: vec![],
-            is_hex,
-        });
-
-        CellId { index: next_index }
-    }
-
-    pub fn num_cells(&self) -> usize {
-        self.cells.len()
-    }
-
-    pub fn get_cell(&self, id: CellId) -> Option<&Cell> {
-        self.cells.get(id.index)
-    }
-
-    pub fn get_cell_mut(&mut self, id: CellId) -> Option<&mut Cell> {
-        self.cells.get_mut(id.index)
-    }
-
-    pub fn new_flow(&mut self, letter: char) -> FlowId {
-        let next_index = self.num_flows();
-        self.flows.push(Flow {
-            id: next_index as usize,
-            endpoints: [None; 2],
-            letter,
-        });
-
-        FlowId { index: next_index as usize }
-    }
-
-    pub fn print_self(&self) {
-        let mut split_input: Vec<&str> = self.print_string.split('\n').collect();
-        split_input.remove(0);
-        let mut bridge_addendum = String::new();
-        let mut bridge_count = 0;
-        let mut cell = 0;
-        // Loop over it printing everything out
-        // Unless it is a cell character. Then replace it with the appropriate letter from the solved puzzle
-        for line in &split_input {
-            for c in line.chars() {
-                if c.is_ascii_uppercase() || c == '.' || c == '*' {
-                    if c == '*' {
-                        bridge_count += 1;
-                        bridge_addendum = format!(
-                            "{}\nBridge {}: Vertical is {}, horizontal is {}\n",
-                            bridge_addendum,
-                            bridge_count,
-                            self.get_flow(
-                                self.get_cell(CellId { index: cell })
-                                    .unwrap()
-                                    .flow_id
-                                    .unwrap()
+    while let Some(arg) = args.next() {
+        // Most options need a value to go with them
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("{} needs a value", name))
+                .cloned()
+        };
+        match arg.as_str() {
+            "--solver" => {
+                arguments.backends = value(arg)?
+                    .split(',')
+                    .map(|name| {
+                        Backend::from_name(name).ok_or_else(|| {
+                            format!(
+                                "Unknown solver {} (expected one of: {})",
+                                name,
+                                Backend::NAMES.join(", ")
+                            )
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            "--heuristic" => {
+                arguments.heuristics = value(arg)?
+                    .split(',')
+                    .map(|name| {
+                        Heuristic::from_name(name).ok_or_else(|| {
+                            format!(
+                                "Unknown heuristic {} (expected one of: {})",
+                                name,
+                                Heuristic::NAMES.join(", ")
                             )
-                            .unwrap()
-                            .letter,
-                            self.get_flow(
-                                self.get_cell(CellId { index: cell + 1 })
-                                    .unwrap()
-                                    .flow_id
-                                    .unwrap()
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            "--moves" => {
+                arguments.moves = value(arg)?
+                    .split(',')
+                    .map(|name| {
+                        Moves::from_name(name).ok_or_else(|| {
+                            format!(
+                                "Unknown way of moving {} (expected one of: {})",
+                                name,
+                                Moves::NAMES.join(", ")
                             )
-                            .unwrap()
-                            .letter
-                        );
-                        cell += 1;
-                        print!("{}", bridge_count);
-                    } else {
-                        let flow = self.get_cell(CellId { index: cell }).unwrap().flow_id;
-                        if flow.is_some() {
-                            print!("{}", self.get_flow(flow.unwrap()).unwrap().get_letter());
-                        } else {
-                            print!("{}", c);
-                        }
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            "-f" | "--format" => {
+                let name = value(arg)?;
+                arguments.format = Some(
+                    Format::from_name(&name).ok_or_else(|| format!("Unknown format {}", name))?,
+                );
+            }
+            "-o" | "--output" => arguments.output = Some(value(arg)?),
+            "--every" => {
+                arguments.every = match value(arg)?.parse() {
+                    Ok(every) if every > 0 => every,
+                    _ => return Err(String::from("--every needs a number of states, above 0")),
+                };
+            }
+            "--timeout" => {
+                arguments.options.time_limit = match value(arg)?.parse::<f64>() {
+                    Ok(seconds) if seconds > 0.0 && seconds.is_finite() => {
+                        Some(Duration::from_secs_f64(seconds))
+                    }
+                    _ => return Err(String::from("--timeout needs a number of seconds, above 0")),
+                };
+            }
+            "--max-states" => {
+                arguments.options.state_limit = match value(arg)?.parse() {
+                    Ok(states) => Some(states),
+                    _ => return Err(String::from("--max-states needs a number of states")),
+                };
+            }
+            "--weight" => {
+                arguments.options.weight = match value(arg)?.parse::<f64>() {
+                    Ok(weight) if weight >= 1.0 && weight.is_finite() => weight,
+                    _ => return Err(String::from("--weight needs a number, 1 or above")),
+                };
+            }
+            "--beam" => {
+                arguments.options.beam_width = match value(arg)?.parse() {
+                    Ok(width) if width > 0 => width,
+                    _ => return Err(String::from("--beam needs a number of states, above 0")),
+                };
+            }
+            "--spill" => {
+                arguments.options.spill_after = match value(arg)?.parse() {
+                    Ok(states) if states > 0 => Some(states),
+                    _ => return Err(String::from("--spill needs a number of states, above 0")),
+                };
+            }
+            "--spill-dir" => arguments.options.spill_directory = Some(PathBuf::from(value(arg)?)),
+            "--level" => {
+                arguments.level = match value(arg)?.parse() {
+                    Ok(level) if level > 0 => level,
+                    _ => return Err(String::from("--level needs a level number, from 1")),
+                };
+            }
+            "-j" | "--jobs" => {
+                arguments.jobs = match value(arg)?.parse() {
+                    Ok(jobs) if jobs > 0 => jobs,
+                    _ => return Err(String::from("--jobs needs a number of puzzles, above 0")),
+                };
+            }
+            "--csv" => arguments.csv = Some(value(arg)?),
+            "--runs" => {
+                arguments.runs = match value(arg)?.parse() {
+                    Ok(runs) if runs > 0 => runs,
+                    _ => return Err(String::from("--runs needs a number of runs, above 0")),
+                };
+            }
+            "--baseline" => arguments.baseline = Some(value(arg)?),
+            "--save-baseline" => arguments.save_baseline = Some(value(arg)?),
+            "--type" => arguments.generate.puzzle_type = value(arg)?,
+            "--size" => {
+                let size = value(arg)?;
+                let (width, height) = size.split_once('x').unwrap_or((&size, &size));
+                match (width.parse(), height.parse()) {
+                    (Ok(width), Ok(height)) => {
+                        arguments.generate.width = width;
+                        arguments.generate.height = height;
                     }
-                    cell += 1;
-                } else {
-                    print!("{}", c);
+                    _ => return Err(String::from("--size needs a width and height, like 7x7")),
                 }
             }
-            println!();
+            "--flows" => {
+                arguments.generate.flows = match value(arg)?.parse() {
+                    Ok(flows) if flows > 0 => flows,
+                    _ => return Err(String::from("--flows needs a number of flows, above 0")),
+                };
+            }
+            "--seed" => {
+                arguments.generate.seed = match value(arg)?.parse() {
+                    Ok(seed) => seed,
+                    _ => return Err(String::from("--seed needs a number")),
+                };
+            }
+            "--flow" => arguments.whole_flow = true,
+            "-q" | "--quiet" => arguments.verbosity = Verbosity::Quiet,
+            "-v" | "--verbose" => arguments.verbosity = Verbosity::Verbose,
+            "-h" | "--help" => arguments.help = true,
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("Unknown option {}", arg))
+            }
+            _ => arguments.files.push(arg.clone()),
         }
-        println!("{}\n", bridge_addendum);
     }
 
-    pub fn num_flows(&self) -> u64 {
-        self.flows.len() as u64
+    if arguments.help || arguments.command == "help" {
+        arguments.help = true;
+        return Ok(arguments);
     }
 
-    pub fn get_flow(&self, id: FlowId) -> Option<&Flow> {
-        self.flows.get(id.index)
+    match arguments.command.as_str() {
+        "verify" if arguments.files.len() != 2 => {
+            return Err(String::from("verify needs a puzzle and a solution"))
+        }
+        "batch" | "bench" if arguments.files.is_empty() => {
+            return Err(format!("{} needs at least one puzzle", arguments.command))
+        }
+        "generate" if !arguments.files.is_empty() => {
+            return Err(String::from("generate doesn't take a puzzle"))
+        }
+        "verify" | "batch" | "bench" | "generate" => {}
+        command if arguments.files.len() != 1 => {
+            return Err(format!("{} needs a single puzzle", command))
+        }
+        _ => {}
     }
-
-    pub fn get_flow_mut(&mut self, id: FlowId) -> Option<&mut Flow> {
-        self.flows.get_mut(id.index)
+    if arguments.command != "bench"
+        && (arguments.backends.len() > 1
+            || arguments.heuristics.len() > 1
+            || arguments.moves.len() > 1)
+    {
+        return Err(String::from(
+            "Only bench can compare more than one solver, heuristic or way of moving",
+        ));
     }
-
-    pub fn num_complete(&self) -> u64 {
-        self.flows
-            .iter()
-            .filter(|flow| flow.is_complete(self))
-            .count() as u64
+    if arguments.whole_flow && arguments.command != "hint" {
+        return Err(String::from("Only hint can give a whole --flow"));
     }
-
-    pub fn num_open_cells(&self) -> u64 {
-        self.cells.iter().filter(|cell| !cell.is_occupied()).count() as u64
+    if arguments.command == "generate" {
+        if arguments.generate.flows == 0 {
+            arguments.generate.flows = arguments.generate.width;
+        }
+        arguments.generate.check()?;
+        if arguments.options.time_limit.is_none() {
+            arguments.options.time_limit = Some(GENERATE_TIMEOUT);
+        }
+    }
+    arguments.options.backend = arguments.backends[0];
+    arguments.options.heuristic = arguments.heuristics[0];
+    arguments.options.moves = arguments.moves[0];
+
+    if let Some(format) = arguments.format {
+        if !Format::allowed_for(&arguments.command).contains(&format) {
+            return Err(format!(
+                "{} can't be shown as {}",
+                arguments.command,
+                format.name()
+            ));
+        }
     }
+    if arguments.format == Some(Format::Gif) && arguments.output.is_none() {
+        return Err(String::from("A gif needs an --output file to go to"));
+    }
+    Ok(arguments)
+}
 
-    pub fn is_complete(&self) -> bool {
-        self.num_complete() == self.num_flows() && self.num_open_cells() == 0
+// Read the whole file with the given name, or standard input if the name is "-"
+fn read_file(filename: &str) -> Result<String, String> {
+    if filename == STDIN {
+        let mut input = String::new();
+        return match io::stdin().read_to_string(&mut input) {
+            Err(why) => Err(format!("couldn't read standard input: {}", why)),
+            Ok(_) => Ok(input),
+        };
     }
+    let path = Path::new(filename);
+    let display = path.display();
 
-    // Return a vector of all endpoints for flows that are not complete
-    pub fn get_endpoints_for_incomplete_flows(&self) -> Vec<CellId> {
-        let mut endpoints = Vec::new();
+    // Open the path in read-only mode, returns 'io::Result<File>'
+    let mut file = match File::open(path) {
+        // The 'description' method of 'io::Error' returns a string that describes the error
+        Err(why) => return Err(format!("couldn't open {}: {}", display, why)),
+        Ok(file) => file,
+    };
 
-        // If the flow is incomplete, push its two endpoints onto the vector
-        for flow in self.flows.iter().filter(|flow| !flow.is_complete(self)) {
-            endpoints.push(flow.get_endpoint(0));
-            endpoints.push(flow.get_endpoint(1));
-        }
+    // Read the file contents into a string, returns 'io::Result<usize>'
+    let mut input = String::new();
+    if let Err(why) = file.read_to_string(&mut input) {
+        return Err(format!("couldn't read {}: {}", display, why));
+    }
+    Ok(input)
+}
 
-        endpoints
+// How to refer to the file with the given name
+fn display_name(filename: &str) -> String {
+    if filename == STDIN {
+        String::from("standard input")
+    } else {
+        filename.to_string()
     }
+}
 
-    pub fn create_children(&self) -> Vec<Puzzle> {
-        let endpoint_id = self.get_endpoint_to_extend();
+// Read the puzzle in the given file, in whatever format it's in (picking out the given level from a level pack)
+fn load_puzzle_file(filename: &str, level: usize) -> Result<PuzzleFile, String> {
+    let input = read_file(filename)?;
+    PuzzleFile::read_level(&input, level)
+        .map_err(|why| format!("Couldn't parse {}: {}", display_name(filename), why))
+}
 
-        if endpoint_id.index == NON_EXISTENT_CELL_ID {
-            return vec![];
-        }
+// Read and parse the puzzle in the given file, returning it in the regular text format along with the puzzle
+fn load_puzzle(filename: &str, level: usize) -> Result<(String, Puzzle), String> {
+    let text = load_puzzle_file(filename, level)?.to_text();
+    match parse_puzzle(&text) {
+        Err(why) => Err(format!(
+            "Couldn't parse {}: {}",
+            display_name(filename),
+            why
+        )),
+        Ok(puzzle) => Ok((text, puzzle)),
+    }
+}
 
-        let endpoint_cell = self.get_cell(endpoint_id).unwrap();
-        let flow_id = endpoint_cell.flow_id.unwrap();
-        let flow = self.get_flow(flow_id).unwrap();
+// Let the user know the puzzle couldn't be loaded, and give the exit code for it
+fn parse_error(why: &str) -> i32 {
+    eprintln!("{}", why);
+    EXIT_PARSE_ERROR
+}
 
-        let endpoint_index = if flow.get_endpoint(0).index == endpoint_id.index {
-            0
-        } else {
-            1
-        };
+// Let the user know the output couldn't be written, and give the exit code for it
+fn write_error(why: &str) -> i32 {
+    eprintln!("{}", why);
+    EXIT_PARSE_ERROR
+}
 
-        let mut children: Vec<Puzzle> = vec![];
+// Write the bytes to the file at the given path, and let the user know it's been saved
+fn write_file(path: &str, contents: &[u8], description: &str, quiet: bool) -> Result<(), String> {
+    File::create(path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|why| format!("Couldn't write {}: {}", path, why))?;
+    if !quiet {
+        println!("Saved {} to {}", description, path);
+    }
+    Ok(())
+}
 
-        for n_id in endpoint_cell
-            .neighbors
-            .iter()
-            .filter(|n_id| !self.get_cell(**n_id).unwrap().is_occupied())
+// Show the board in the chosen format, either printed or saved to the output file
+// If no format was chosen, it's in color (with the paths drawn in) if it's going to a terminal
+// Otherwise (or if NO_COLOR is set), it sticks to plain text
+fn show_board(puzzle: &Puzzle, arguments: &Arguments) -> Result<(), String> {
+    let format = arguments.format.unwrap_or(
+        if arguments.output.is_none()
+            && io::stdout().is_terminal()
+            && env::var_os("NO_COLOR").is_none()
         {
-            let mut child = self.clone();
+            Format::Color
+        } else {
+            Format::Text
+        },
+    );
+    let (contents, description) = match format {
+        Format::Color => (format!("{}\n", render_terminal(puzzle)), "the board"),
+        Format::Svg => (render_svg(puzzle), "a drawing"),
+        _ => (puzzle.to_text(), "the board"),
+    };
+    match &arguments.output {
+        Some(output) => write_file(
+            output,
+            contents.as_bytes(),
+            description,
+            arguments.verbosity == Verbosity::Quiet,
+        ),
+        None => {
+            print!("{}", contents);
+            Ok(())
+        }
+    }
+}
 
-            let cell_to_move_to = child.get_cell_mut(*n_id).unwrap();
+// Begin solving the puzzle located in the given file
+// Includes parsing the puzzle, creating the initial puzzle state, and recursively solving the puzzle
+// Does most of the work
+// The solution (or the puzzle, if it can't be solved) is shown in the chosen format. A gif is an animation of the
+// search instead, with a frame for every so many states
+fn solve_puzzle(arguments: &Arguments) -> i32 {
+    let filename = &arguments.files[0];
+    let normal = arguments.verbosity != Verbosity::Quiet;
+
+    let display = display_name(filename);
+    let (input, puzzle) = match load_puzzle(filename, arguments.level) {
+        Err(why) => return parse_error(&why),
+        Ok(loaded) => loaded,
+    };
 
-            cell_to_move_to.flow_id = Some(FlowId {
-                index: flow_id.index,
-            });
-            cell_to_move_to.is_endpoint = true;
+    if normal {
+        println!("Solving the puzzle located at: {}\n", display);
+        print!("{}:\n{}\n\n", display, input);
 
-            child
-                .get_cell_mut(child.get_flow(flow_id).unwrap().endpoints[endpoint_index].unwrap())
-                .unwrap()
-                .is_endpoint = false;
+        // Status info
+        println!("Number of cells: {}", puzzle.num_cells());
+        println!("Number of flows: {}", puzzle.num_flows());
+        println!("Number of neighbors: {}\n\n", puzzle.num_neighbor_pairs());
+    }
 
-            let child_flow = child.get_flow_mut(flow_id).unwrap();
-            child_flow.update_endpoint(endpoint_index, CellId { index: n_id.index });
+    let start = Instant::now();
+    // Solve it. Just like that. It's done!
+    let gif = arguments.format == Some(Format::Gif);
+    let mut console = ConsoleObserver::new(arguments.verbosity);
+    let mut recording = gif.then(|| Recording::new(arguments.every));
+    let result = match recording.as_mut() {
+        Some(recording) => solve(
+            puzzle.clone(),
+            &arguments.options,
+            &mut (&mut console, recording),
+        ),
+        None => solve(puzzle.clone(), &arguments.options, &mut console),
+    };
 
-            children.push(child);
+    let mut code = match &result.outcome {
+        Outcome::Solved(_) => EXIT_SOLVED,
+        Outcome::Unsolvable => EXIT_UNSOLVABLE,
+        Outcome::TimedOut | Outcome::Cancelled(_) | Outcome::Abandoned(_) => EXIT_TIMEOUT,
+    };
+    if normal {
+        // The console observer has already owned up to there being no solution
+        match result.outcome {
+            Outcome::TimedOut => println!("Ran out of time before finding a solution!"),
+            Outcome::Abandoned(_) if arguments.options.backend == Backend::ExactCover => println!(
+                "There were too many routes to list before finding a solution! Another --solver might find one"
+            ),
+            Outcome::Abandoned(_) if arguments.options.backend == Backend::Beam => println!(
+                "The beam ran out of states before finding a solution! A wider --beam might find one"
+            ),
+            Outcome::Abandoned(_) => println!(
+                "Couldn't read the states spilled to disk back in before finding a solution! ({})",
+                result.stats.spill_error.as_deref().unwrap_or("unknown error")
+            ),
+            _ => {}
         }
+    }
 
-        children
+    let written = match (gif, recording) {
+        (true, Some(recording)) => {
+            let animation = render_gif(&puzzle, &recording.states, result.solution());
+            write_file(
+                arguments.output.as_ref().unwrap(),
+                &animation,
+                "an animation",
+                !normal,
+            )
+        }
+        // Only the solution is worth printing, but a drawing of the puzzle is better than no drawing
+        _ if result.solution().is_some() || arguments.format == Some(Format::Svg) => {
+            show_board(result.solution().unwrap_or(&puzzle), arguments)
+        }
+        _ => Ok(()),
+    };
+    if let Err(why) = written {
+        code = write_error(&why);
     }
 
-    pub fn num_possible_children(&self) -> u64 {
-        let endpoint_id = self.get_endpoint_to_extend();
-        // If there is no endpoint to extend, there are no possible children
-        if endpoint_id.index == NON_EXISTENT_CELL_ID {
-            return 0;
+    if normal {
+        match &result.outcome {
+            Outcome::Solved(_) => println!("Finished in {} seconds!", start.elapsed()),
+            _ => println!("Failed in {} seconds!", start.elapsed()),
         }
-        let endpoint_cell = self.get_cell(endpoint_id).unwrap();
-        endpoint_cell.num_open_neighbors(self) as u64
     }
+    code
+}
 
-    // Basically, find the endpoint with the fewest open neighbors (possibilities) and return that one
-    pub fn get_endpoint_to_extend(&self) -> CellId {
-        let possible_endpoints = self.get_endpoints_for_incomplete_flows();
-        let mut min_open = MAX_NEIGHBORS + 1; // Cannot have more open than the maximum number of neighbors
+// Check the solution in one file against the puzzle in another
+fn verify_puzzle_solution(puzzle_filename: &str, solution_filename: &str, level: usize) -> i32 {
+    let (_, puzzle) = match load_puzzle(puzzle_filename, level) {
+        Err(why) => return parse_error(&why),
+        Ok(loaded) => loaded,
+    };
+    let solution = match read_file(solution_filename) {
+        Err(why) => return parse_error(&why),
+        Ok(solution) => solution,
+    };
 
-        // Default value in case no endpoints are found
-        let mut min_open_cell_id = CellId {
-            index: NON_EXISTENT_CELL_ID,
-        };
-        for cell_id in &possible_endpoints {
-            let cell = self.get_cell(*cell_id);
-
-            // If this endpoint has fewer open neighbors than the current minimum,
-            // set the current minimum to this endpoint
-            if cell.unwrap().num_open_neighbors(self) < min_open {
-                min_open = cell.unwrap().num_open_neighbors(self);
-                min_open_cell_id = *cell_id;
-            }
+    match verify_solution(&puzzle, &solution) {
+        Ok(_) => {
+            println!("{} is a valid solution!", display_name(solution_filename));
+            EXIT_SOLVED
         }
-
-        // If no endpoints are found, return immediately
-        // This would likely happen if all flows are complete, but there remain cells open
-        if min_open_cell_id.index == NON_EXISTENT_CELL_ID {
-            return min_open_cell_id;
+        Err(why) => {
+            println!(
+                "{} is not a valid solution: {}",
+                display_name(solution_filename),
+                why
+            );
+            EXIT_UNSOLVABLE
         }
+    }
+}
 
-        // If there < 2 neighbors open for the selected endpoint,
-        // Determine if there is a neighbor which only has one open neighbor. This is a forced move
-        // Don't do this check if < 2 neighbors open, because that is already a forced move
-        if min_open > 1 {
-            for cell_id in &possible_endpoints {
-                let cell = self.get_cell(*cell_id).unwrap();
+// Give a hint for the puzzle in the given file: either a single move, or a whole flow
+// The board is shown with just the hint filled in, so the rest of the solution isn't spoiled
+fn hint_puzzle(arguments: &Arguments) -> i32 {
+    let (_, puzzle) = match load_puzzle(&arguments.files[0], arguments.level) {
+        Err(why) => return parse_error(&why),
+        Ok(loaded) => loaded,
+    };
 
-                // Check each of the endpoint's neighbors
-                for n_index in cell.neighbors.iter() {
-                    let neighbor = self.get_cell(*n_index).unwrap();
+    let hint = if arguments.whole_flow {
+        find_flow(&puzzle, &arguments.options)
+    } else {
+        find_move(&puzzle, &arguments.options)
+    };
 
-                    // If the neighbor is open and has only one neighbor, the endpoint must be
-                    // the next one to move, so return it
-                    if !neighbor.is_occupied() && neighbor.num_open_neighbors(self) == 1 {
-                        return *cell_id;
-                    }
-                }
+    match hint {
+        Ok(Some(hint)) => {
+            println!("{}\n", hint.describe(&puzzle));
+            let mut hinted = puzzle.clone();
+            hint.apply(&mut hinted);
+            match show_board(&hinted, arguments) {
+                Err(why) => write_error(&why),
+                Ok(_) => EXIT_SOLVED,
             }
         }
-
-        // Return the selected endpoint
-        min_open_cell_id
-    }
-
-    // Is this puzzle solvable in its current state?
-    // 1 if yes
-    // <1 if no, corresponding to the reason (for statistics)
-    pub fn is_solvable(&self) -> i16 {
-        if self.num_possible_children() == 0 {
-            return UNSOLVABLE_NO_CHILDREN;
+        Ok(None) => {
+            println!("No hint: the puzzle is either already complete or can't be solved");
+            EXIT_UNSOLVABLE
         }
-
-        if !self.is_hex {
-            // I think these checks need tweaking for hex puzzles. Not sure though
-            for i in 0..self.num_cells() {
-                let cell = self.get_cell(CellId { index: i }).unwrap();
-
-                // Detect dead ends - an empty cell connected only to one other empty cell and no endpoints
-                // Any flow going into this would have no endpoints to connect to and no way to get out,
-                // So it is impossible to solve
-                if !cell.is_occupied() && cell.num_open_neighbors(self) == 1 {
-                    let mut has_endpoint_neighbor = false;
-                    for n_index in cell.neighbors.iter() {
-                        if self.get_cell(*n_index).unwrap().is_endpoint {
-                            has_endpoint_neighbor = true;
-                            break;
-                        }
-                    }
-
-                    if !has_endpoint_neighbor {
-                        return UNSOLVABLE_DEAD_ENDS;
-                    }
-                }
-
-                // Check for "pools" - when a flow doubles back on itself - these are illegal and generally pesky
-                if cell.is_occupied() && cell.num_open_neighbors(self) <= 1 {
-                    let mut same_flow_count = 0;
-                    for n_index in cell.neighbors.iter() {
-                        let neighbor = self.get_cell(*n_index).unwrap();
-                        if neighbor.is_occupied()
-                            && neighbor.flow_id.unwrap().index == cell.flow_id.unwrap().index
-                        {
-                            same_flow_count += 1;
-                        }
-                    }
-                    if same_flow_count > 2 {
-                        return UNSOLVABLE_POOLS;
-                    }
-                }
-            }
+        Err(_) => {
+            println!("No hint: gave up solving the puzzle before finding one");
+            EXIT_TIMEOUT
         }
+    }
+}
 
-        // Idea for connected component analysis gotten from: https://mzucker.github.io/2016/08/28/flow-solver.html
-        // Algorithm for connected component analysis is from wikipedia: https://en.wikipedia.org/wiki/Connected-component_labeling
-        let mut preliminary_connected_component_sets: Vec<Vec<usize>> = vec![];
-        let mut visited: Vec<usize> = vec![];
-
-        // Create the connected components: basically each vector in the vector ccs contains the IDs of cells connected to each other
-        for i in 0..self.num_cells() {
-            if visited.contains(&i) {
-                continue;
-            }
-
-            let mut queue: Vec<usize> = vec![i];
-            // Loop through the queue of cells to visit
-            while !queue.is_empty() {
-                let curr_id = queue.pop().unwrap();
-
-                if visited.contains(&curr_id) {
-                    continue;
-                }
-
-                visited.push(curr_id);
-
-                let curr_cell = self.get_cell(CellId { index: curr_id }).unwrap();
-
-                // Occupied cells don't count as being in regions
-                if curr_cell.is_occupied() {
-                    continue;
-                }
+// Solve the puzzle in the given file by logic, showing the reasoning for every move and the board it ends up with
+fn steps_puzzle(arguments: &Arguments) -> i32 {
+    let (_, puzzle) = match load_puzzle(&arguments.files[0], arguments.level) {
+        Err(why) => return parse_error(&why),
+        Ok(loaded) => loaded,
+    };
 
-                // If a cell isn't added to an existing set, create a new region for it afterwards
-                let mut added_to_set = false;
-                // Check each of the neighbors
-                for neighbor_id in curr_cell.neighbors.iter() {
-                    // First, check if the neighbor is already in a region
-                    // If it is, add this cell to that region
-                    for ccs in preliminary_connected_component_sets.iter_mut() {
-                        if ccs.contains(&neighbor_id.index) {
-                            ccs.push(curr_id);
-                            added_to_set = true;
-                        }
-                    }
+    let deduction = deduce(&puzzle, &arguments.options);
+    println!("{}", deduction.describe(&puzzle));
+    if let Err(why) = show_board(&deduction.board, arguments) {
+        return write_error(&why);
+    }
+    match deduction.gave_up {
+        None => EXIT_SOLVED,
+        Some(Outcome::Unsolvable) => EXIT_UNSOLVABLE,
+        Some(_) => EXIT_TIMEOUT,
+    }
+}
 
-                    // Also, if the neighbor hasn't already been visited and isn't in the queue, add it to the queue to be considered
-                    if !visited.contains(&neighbor_id.index) && !queue.contains(&neighbor_id.index)
-                    {
-                        queue.push(neighbor_id.index);
-                    }
-                }
+// Explain why the puzzle in the given file can't be solved
+fn explain_puzzle(arguments: &Arguments) -> i32 {
+    let filename = &arguments.files[0];
+    let (_, puzzle) = match load_puzzle(filename, arguments.level) {
+        Err(why) => return parse_error(&why),
+        Ok(loaded) => loaded,
+    };
 
-                if !added_to_set {
-                    // If needed, create the region
-                    preliminary_connected_component_sets.push(vec![curr_id]);
-                }
-            }
+    match explain(&puzzle, &arguments.options) {
+        Ok(None) => {
+            println!("{} can be solved!", display_name(filename));
+            EXIT_SOLVED
         }
-
-        // Create the final regions, as there is a possibility that regions were not fully connected in the prior loop
-        let mut connected_component_sets: Vec<Vec<usize>> = vec![];
-        for i in 0..preliminary_connected_component_sets.len() {
-            let set_i = preliminary_connected_component_sets.get(i).unwrap();
-            let mut added_to_final = false;
-            'j_loop: for set_j in connected_component_sets.iter_mut() {
-                for id_j in set_j.iter() {
-                    let cell_j = self.get_cell(CellId { index: *id_j }).unwrap();
-
-                    for n in cell_j.neighbors.iter() {
-                        if set_i.contains(&n.index) {
-                            set_j.extend(set_i);
-                            added_to_final = true;
-                            break 'j_loop;
-                        }
-                    }
-                }
-            }
-
-            if !added_to_final {
-                connected_component_sets.push(set_i.clone());
-            }
+        Ok(Some(explanation)) => {
+            println!("\n{} can't be solved!\n", display_name(filename));
+            println!("{}", explanation.describe(&puzzle));
+            EXIT_UNSOLVABLE
         }
-
-        // Analyze the regions - Part 1: check if each region has at least one pair of
-        // endpoints neighboring it (both endpoints neighbor at least one member of the region)
-        'region_loop_1: for region in connected_component_sets.iter() {
-            'flow_loop_1: for f in 0..self.num_flows() {
-                let flow = self.get_flow(FlowId { index: f as usize }).unwrap();
-                if flow.is_complete(self) {
-                    continue;
-                }
-
-                let endpoint_0 = self.get_cell(flow.get_endpoint(0)).unwrap();
-                let endpoint_1 = self.get_cell(flow.get_endpoint(1)).unwrap();
-
-                let mut res = false;
-
-                for n in endpoint_0.neighbors.iter() {
-                    if region.contains(&n.index) {
-                        res = true;
-                    }
-                }
-                if !res {
-                    continue 'flow_loop_1; // Region doesn't neighbor the first endpoint. Continue to next flow
-                }
-
-                for n in endpoint_1.neighbors.iter() {
-                    if region.contains(&n.index) {
-                        continue 'region_loop_1; // If we hit this point, the region contains both endpoints. Check next region
-                    }
-                }
-            }
-            return UNSOLVABLE_REGION;
+        Err(_) => {
+            println!(
+                "Gave up before finding out whether {} can be solved",
+                display_name(filename)
+            );
+            EXIT_TIMEOUT
         }
+    }
+}
 
-        // Analyze each flow: both endpoints must have a neighboring region in common, otherwise connecting them is impossible
-        'flow_loop_2: for f in 0..self.num_flows() {
-            let flow = self.get_flow(FlowId { index: f as usize }).unwrap();
-            if flow.is_complete(self) {
-                continue;
-            }
-            let endpoint_0 = self.get_cell(flow.get_endpoint(0)).unwrap();
-            let endpoint_1 = self.get_cell(flow.get_endpoint(1)).unwrap();
-
-            'region_loop_2: for region in connected_component_sets.iter() {
-                let mut res = false;
-
-                for n in endpoint_0.neighbors.iter() {
-                    if region.contains(&n.index) {
-                        res = true;
-                    }
-                }
-                if !res {
-                    continue 'region_loop_2; // This region doesn't neighbor the first endpoint. Check the next region
-                }
+// Grade how difficult the puzzle in the given file is
+fn rate_puzzle(arguments: &Arguments) -> i32 {
+    let (_, puzzle) = match load_puzzle(&arguments.files[0], arguments.level) {
+        Err(why) => return parse_error(&why),
+        Ok(loaded) => loaded,
+    };
 
-                for n in endpoint_1.neighbors.iter() {
-                    if region.contains(&n.index) {
-                        continue 'flow_loop_2; // This region contains both endpoints. Check the next flow
-                    }
-                }
+    match rate(&puzzle, &arguments.options) {
+        None => {
+            println!(
+                "{} can't be solved, so it can't be rated",
+                display_name(&arguments.files[0])
+            );
+            EXIT_UNSOLVABLE
+        }
+        Some(rating) => {
+            print!("{}", rating.describe());
+            match rating.outcome {
+                Outcome::Solved(_) => EXIT_SOLVED,
+                _ => EXIT_TIMEOUT,
             }
-            return UNSOLVABLE_PATH_BLOCKED;
         }
-        SOLVABLE
     }
+}
 
-    // Magic numbers galore! (once upon a time)
-    // Anyway, return the score of a board
-    pub fn h(&self) -> u64 {
-        // Modified from https://mzucker.github.io/2016/08/28/flow-solver.html (incorporates parts of g() and h() into one)
-        1000 - self.num_open_cells() + self.num_complete() * 2 - self.num_possible_children()
+// Show the puzzle in the given file as it is, with any pre-drawn paths filled in
+fn render_puzzle(arguments: &Arguments) -> i32 {
+    match load_puzzle(&arguments.files[0], arguments.level) {
+        Err(why) => parse_error(&why),
+        Ok((_, puzzle)) => match show_board(&puzzle, arguments) {
+            Err(why) => write_error(&why),
+            Ok(_) => EXIT_SOLVED,
+        },
     }
 }
 
-// Begin solving the puzzle located in the given file
-// Includes parsing the puzzle, creating the initial puzzle state, and recursively solving the puzzle
-// Does most of the work
-fn solve_puzzle(filename: &str) {
-    let path = Path::new(filename);
+// Check the puzzle in the given file, and write it back out tidied up, in the chosen format
+fn convert_puzzle(arguments: &Arguments) -> i32 {
+    let puzzle_file = match load_puzzle_file(&arguments.files[0], arguments.level) {
+        Err(why) => return parse_error(&why),
+        Ok(puzzle_file) => puzzle_file,
+    };
 
-    // Verify valid extension
-    let extension = path.extension().and_then(OsStr::to_str);
-    if extension != Some("txt") {
-        println!("At this time, puzzle files must be text files (.txt)!");
-        return;
+    let contents = match arguments.format {
+        Some(Format::Json) => puzzle_file.to_json(),
+        _ => puzzle_file.to_text(),
+    };
+    let written = match &arguments.output {
+        Some(output) => write_file(
+            output,
+            contents.as_bytes(),
+            "the puzzle",
+            arguments.verbosity == Verbosity::Quiet,
+        ),
+        None => {
+            print!("{}", contents);
+            Ok(())
+        }
+    };
+    match written {
+        Err(why) => write_error(&why),
+        Ok(_) => EXIT_SOLVED,
     }
+}
 
-    let display = path.display();
-    println!("Solving the puzzle located at: {}\n", display);
-
-    // Open the path in read-only mode, returns 'io::Result<File>'
-    let mut file = match File::open(&path) {
-        // The 'description' method of 'io::Error' returns a string that describes the error
-        Err(why) => panic!("couldn't open {}: {}", display, why),
-        Ok(file) => file,
+// Make a new puzzle, and show it in the chosen format
+fn generate_puzzle(arguments: &Arguments) -> i32 {
+    let text = match generate(&arguments.generate, &arguments.options) {
+        Err(why) => {
+            eprintln!("{}", why);
+            return EXIT_UNSOLVABLE;
+        }
+        Ok(text) => text,
     };
 
-    // Read the file contents into a string, returns 'io::Result<usize>'
-    let mut input = String::new();
-    match file.read_to_string(&mut input) {
-        Err(why) => panic!("couldn't read {}: {}", display, why),
-        Ok(_) => print!("{}:\n{}\n\n", display, input),
-    }
-
-    // Split the input based on lines
-    let mut split_input: Vec<&str> = input.split('\n').collect();
-    split_input.remove(0);
-
-    // Check if the puzzle is a HEX puzzle
-    let puzzle_type: String = input
-        .chars()
-        .skip(0)
-        .take(input.find('\n').unwrap() - 1)
-        .collect();
-    let is_hex = puzzle_type == "HEX";
-
-    // The initial puzzle
-    let mut puzzle: Puzzle = Puzzle::new(is_hex, input.clone());
-
-    // Used to map locations of cells via the cell's id (as a usize, for reasons beyond my comprehension)
-    // Used to create neighbor relationships later
-    let mut cell_map: HashMap<String, usize> = HashMap::new();
-
-    let mut row = 0; // Track the current row
-                     // Track the max height and width of the puzzle (note: this is because not all puzzles are squares and rectangles)
-    let mut max_cell_row = 0;
-    let mut max_cell_col = 0;
-    // Nested for loops: iterate through each character in the input board
-    // If it a cell character (capital letter, period, or asterisk, create a cell for it), update/create a flow
-    for line in &split_input {
-        if line.starts_with("//") {
-            continue;
-        }
-        for (col, c) in line.chars().enumerate() {
-            // Check if the character is a cell character
-            if c.is_ascii_uppercase() || c == '.' || c == '*' {
-                let is_bridge = c == '*'; // Asterisks are bridges
-                let is_endpoint = c.is_ascii_uppercase();
-
-                if !is_bridge {
-                    // Create the new cell
-                    let cell_id: CellId = puzzle.new_cell(is_endpoint, None, is_hex);
-
-                    // Create a key for the map from the coordinates of the cell, and insert it into the map with the new cell id
-                    let key: String = format!("{}-{}", col, row);
-                    cell_map.insert(key, cell_id.index);
-
-                    // If the cell is an endpoint, either create a new flow if needed or update an existing one
-                    if c.is_ascii_uppercase() {
-                        let mut flow_id_1 = None;
-                        let mut flow_exists = false;
-
-                        // Check if a flow with the current letter already exists
-                        // If it does, update hte appropriate values
-                        for (count, flow) in puzzle.flows.iter_mut().enumerate() {
-                            if flow.get_letter() == c {
-                                flow_exists = true;
-                                flow.update_endpoint(1, cell_id);
-                                flow_id_1 = Some(FlowId { index: count });
-                            }
-                        }
-
-                        // If the flow doesn't exist, create a new one
-                        if !flow_exists {
-                            let flow_id = puzzle.new_flow(c);
-                            let flow = puzzle.get_flow_mut(flow_id).unwrap();
-                            flow_id_1 = Some(flow_id);
-                            flow.update_endpoint(0, cell_id);
-                        }
-
-                        // Update the new cell with the appropriate flow
-                        puzzle.get_cell_mut(cell_id).unwrap().flow_id = flow_id_1;
-                    }
-                } else {
-                    // Bridges can't have a flow to set up, but do have an extra cell associated with them
-                    let cell_id1: CellId = puzzle.new_cell(is_endpoint, None, is_hex);
-                    let cell_id2: CellId = puzzle.new_cell(is_endpoint, None, is_hex);
-
-                    let key1: String = format!("{}-{}--", col, row);
-                    let key2: String = format!("{}-{}-|", col, row);
-                    cell_map.insert(key1, cell_id1.index);
-                    cell_map.insert(key2, cell_id2.index);
-                }
-
-                // Update the max size variables
-                max_cell_row = max(max_cell_row, row);
-                max_cell_col = max(max_cell_col, col);
-            }
+    let contents = match arguments.format {
+        Some(Format::Json) => PuzzleFile::read(&text).unwrap().to_json(),
+        // Note down where it came from, so it can be made again
+        _ => text.replacen(
+            '\n',
+            &format!("\n// Generated from seed {}\n", arguments.generate.seed),
+            1,
+        ),
+    };
+    let written = match &arguments.output {
+        Some(output) => write_file(
+            output,
+            contents.as_bytes(),
+            "the puzzle",
+            arguments.verbosity == Verbosity::Quiet,
+        ),
+        None => {
+            print!("{}", contents);
+            Ok(())
         }
-        row += 1;
-    }
-
-    row = 0;
-    let mut neighbors = 0; // Count the number of neighbor relationships
-                           // Again, loop through all characters in the board configuration
-                           // This time, look for neighbor characters: '-', '|', '/', '\'
-                           // When one is found, update the appropriate cells
-    for line in &split_input {
-        if line.starts_with("//") {
-            continue;
-        }
-        for (col, c) in line.chars().enumerate() {
-            // If the character is a neighbor character, proceed
-            if c == '-' || c == '/' || c == '\\' || c == '|' {
-                // Get the coordinates of the two neighbors, and the neighbor relationship for each
-                let (col1, row1, col2, row2, direction) = if c == '-' {
-                    // If the current column is greater than the max width, this is a warped relationship. The neighbor is in the first column.
-                    if col > max_cell_col {
-                        (col - 1, row, 0, row, HORIZONTAL)
-                    } else {
-                        (col - 1, row, col + 1, row, HORIZONTAL)
-                    }
-                } else if c == '/' {
-                    (col - 1, row + 1, col + 1, row - 1, HORIZONTAL) // These aren't really horizontal, but close enough, and it doesn't really matter
-                } else if c == '\\' {
-                    (col - 1, row - 1, col + 1, row + 1, HORIZONTAL)
-                } else {
-                    // If the row is greater than the max height, this is warped relationship. The neighbor is in the first row.
-                    if row > max_cell_row {
-                        (col, row - 1, col, 0, VERTICAL)
-                    } else {
-                        (col, row - 1, col, row + 1, VERTICAL)
-                    }
-                };
+    };
+    match written {
+        Err(why) => write_error(&why),
+        Ok(_) => EXIT_SOLVED,
+    }
+}
 
-                // Recreate the map keys for the two neighbors
-                neighbors += 1;
-                let mut key1: String = format!("{}-{}", col1, row1);
-                let mut key2: String = format!("{}-{}", col2, row2);
-
-                if !cell_map.contains_key(&key1) {
-                    key1 = if direction == VERTICAL {
-                        format!("{}-{}-|", col1, row1)
-                    } else {
-                        format!("{}-{}--", col1, row1)
-                    };
-                }
-                if !cell_map.contains_key(&key2) {
-                    key2 = if direction == VERTICAL {
-                        format!("{}-{}-|", col2, row2)
-                    } else {
-                        format!("{}-{}--", col2, row2)
-                    };
-                }
+// Solve all the given puzzles, and show how each one went
+fn batch_solve(arguments: &Arguments) -> i32 {
+    let paths = match find_puzzles(&arguments.files) {
+        Err(why) => return parse_error(&why),
+        Ok(paths) => paths,
+    };
+    let normal = arguments.verbosity != Verbosity::Quiet;
+    if normal {
+        println!("Solving {} puzzles\n", paths.len());
+    }
 
-                puzzle
-                    .get_cell_mut(CellId {
-                        index: *cell_map.get(&key1).unwrap(),
-                    })
-                    .unwrap()
-                    .add_neighbor(CellId {
-                        index: *cell_map.get(&key2).unwrap(),
-                    });
-                puzzle
-                    .get_cell_mut(CellId {
-                        index: *cell_map.get(&key2).unwrap(),
-                    })
-                    .unwrap()
-                    .add_neighbor(CellId {
-                        index: *cell_map.get(&key1).unwrap(),
-                    });
-            }
+    // Let the user know how each puzzle went as they finish, since the whole lot can take a while
+    let results = solve_batch(&paths, &arguments.options, arguments.jobs, |result| {
+        if normal {
+            println!("{}: {}", result.path.display(), result.outcome.name());
         }
-        row += 1;
+    });
+    if normal {
+        println!();
     }
+    print!("{}", summary_table(&results));
 
-    // Status info
-    println!("Number of cells: {}", puzzle.num_cells());
-    println!("Number of flows: {}", puzzle.num_flows());
-    println!("Number of neighbors: {}\n\n", neighbors);
-
-    let start = Instant::now();
-    // Solve it. Just like that. It's done!
-    let res = greedy_best_first(puzzle);
+    if let Some(csv) = &arguments.csv {
+        if let Err(why) = write_file(csv, to_csv(&results).as_bytes(), "the results", !normal) {
+            return write_error(&why);
+        }
+    }
 
-    if let Some(r) = res {
-        r.print_self();
-        println!("Finished in {} seconds!", start.elapsed());
+    let happened =
+        |matches: fn(&BatchOutcome) -> bool| results.iter().any(|result| matches(&result.outcome));
+    if happened(|outcome| matches!(outcome, BatchOutcome::Error(_))) {
+        EXIT_PARSE_ERROR
+    } else if happened(|outcome| matches!(outcome, BatchOutcome::Unsolvable)) {
+        EXIT_UNSOLVABLE
+    } else if happened(|outcome| matches!(outcome, BatchOutcome::TimedOut)) {
+        EXIT_TIMEOUT
     } else {
-        println!("Uh oh, no solution!");
-        println!("Failed in {} seconds!", start.elapsed());
+        EXIT_SOLVED
     }
 }
 
-// Not really sure if this is greedy best first any more, but I'm not changing the name now
-// Solve the given PuzzleState, if possible. If not, return None
-fn greedy_best_first(puzzle: Puzzle) -> Option<Puzzle> {
-    let mut frontier: BinaryHeap<Puzzle> = BinaryHeap::new(); // Puzzles to consider
-    frontier.push(puzzle);
-
-    // Stats
-    let mut frontier_max = 0;
-    let mut states_visited: u64 = 0;
-    let mut children_discarded: u64 = 0;
-    let mut states_created = 1;
-    let mut max_flows_completed: u64 = 0;
-    let mut discarded_no_children: u64 = 0;
-    let mut discarded_dead_end: u64 = 0;
-    let mut discarded_pools: u64 = 0;
-    let mut discarded_blocked: u64 = 0;
-    let mut discarded_cc: u64 = 0;
-
-    let mut avg_num_flows_complete = 0;
-    let mut avg_num_cells_open = 0;
-
-    let mut latest: Option<Puzzle> = None;
-
-    while !frontier.is_empty() {
-        states_visited += 1;
-        frontier_max = max(frontier_max, frontier.len());
-
-        let curr_state = frontier.pop().unwrap();
-
-        max_flows_completed = max(max_flows_completed, curr_state.num_complete());
-        avg_num_flows_complete += curr_state.num_complete();
-        avg_num_cells_open += curr_state.num_open_cells();
-
-        let mut children = curr_state.create_children();
-        states_created += children.len();
-
-        // Evaluate each child
-        while !children.is_empty() {
-            let child = children.pop().unwrap();
-
-            // Yay! We're done! Print some stats and return
-            if child.is_complete() {
-                println!("---STATS AT {}---", states_visited);
-                println!("States visited: {}\nMax Frontier Size: {}\nChildren Discarded: {}\nPercent Discarded: {}\nCurrent Frontier: {}\nStates created: {}",
-                         states_visited, frontier_max, children_discarded, (children_discarded as f64 / states_created as f64), frontier.len(), states_created);
-                println!(
-                    "Num Cells Open: {}\nMax Flows Complete: {}",
-                    child.num_open_cells(),
-                    max_flows_completed
-                );
-                println!("Discard Stats:\n\tNum children: {}\n\tDead end: {}\n\tPools: {}\n\tBlocked Flow: {}\n\tCC Failed: {}\n",
-                           (discarded_no_children as f64 / children_discarded as f64),
-                           (discarded_dead_end as f64 / children_discarded as f64),
-                           (discarded_pools as f64 / children_discarded as f64),
-                           (discarded_blocked as f64 / children_discarded as f64),
-                           (discarded_cc as f64 / children_discarded as f64));
-
-                return Some(child);
-            }
-            let solvable_status = child.is_solvable(); // Determine if child is solvable
-                                                       // If solvable, add it to the list to consider
-            if solvable_status == 1 {
-                frontier.push(child);
-            } else {
-                // Otherwise, update some stats and then forget about the child
-                children_discarded += 1;
-                if solvable_status == UNSOLVABLE_NO_CHILDREN {
-                    discarded_no_children += 1;
-                } else if solvable_status == UNSOLVABLE_DEAD_ENDS {
-                    discarded_dead_end += 1;
-                } else if solvable_status == UNSOLVABLE_POOLS {
-                    discarded_pools += 1;
-                } else if solvable_status == UNSOLVABLE_PATH_BLOCKED {
-                    discarded_blocked += 1;
-                } else if solvable_status == UNSOLVABLE_REGION {
-                    discarded_cc += 1;
-                }
+// Solve the puzzles with each of the solvers, heuristics and ways of moving, and compare how they did
+fn bench(arguments: &Arguments) -> i32 {
+    let paths = match find_puzzles(&arguments.files) {
+        Err(why) => return parse_error(&why),
+        Ok(paths) => paths,
+    };
+    let baseline = match &arguments.baseline {
+        None => None,
+        Some(filename) => match read_file(filename).and_then(|input| read_baseline(&input)) {
+            Err(why) => return parse_error(&format!("Couldn't read the baseline: {}", why)),
+            Ok(baseline) => Some(baseline),
+        },
+    };
+
+    let mut configs = vec![];
+    for backend in arguments.backends.iter() {
+        for heuristic in arguments.heuristics.iter() {
+            for moves in arguments.moves.iter() {
+                configs.push(BenchConfig {
+                    backend: *backend,
+                    heuristic: *heuristic,
+                    moves: *moves,
+                });
             }
         }
+    }
 
-        // Print some stats every so often to keep the user happy, and let them know that we're still chugging along
-        let skip = 10000;
-        if states_visited % skip == 0 {
-            println!(
-                "{}\t{}\t{:.4}\t{:.1}\t{}\t{:.4}",
-                states_visited,
-                frontier_max,
-                (children_discarded as f64 / states_created as f64),
-                avg_num_cells_open as f64 / skip as f64,
-                max_flows_completed,
-                avg_num_flows_complete as f64 / skip as f64
-            );
-            max_flows_completed = 0;
-            avg_num_cells_open = 0;
-            avg_num_flows_complete = 0;
+    let normal = arguments.verbosity != Verbosity::Quiet;
+    if normal {
+        println!(
+            "Solving {} puzzles {} times each, with {} configurations\n",
+            paths.len(),
+            arguments.runs,
+            configs.len()
+        );
+    }
+    let results = run_bench(
+        &paths,
+        &configs,
+        arguments.runs,
+        &arguments.options,
+        |result| {
+            if normal {
+                println!(
+                    "{} ({}, {}, {}): {} in {:.4} seconds",
+                    result.path.display(),
+                    result.config.backend.name(),
+                    result.config.heuristic.name(),
+                    result.config.moves.name(),
+                    result.outcome.name(),
+                    result.median_time()
+                );
+            }
+        },
+    );
+    if normal {
+        println!();
+    }
+    print!("{}", bench_table(&results, baseline.as_deref()));
+
+    if let Some(filename) = &arguments.save_baseline {
+        if let Err(why) = write_file(
+            filename,
+            to_baseline(&results).as_bytes(),
+            "the baseline",
+            !normal,
+        ) {
+            return write_error(&why);
         }
-
-        latest = Some(curr_state);
     }
 
-    // Never want to get here - if we did, the solver failed
-    println!("---STATS AT {}---", states_visited);
-    println!("States visited: {}\nMax Frontier Size: {}\nChildren Discarded: {}\nPercent Discarded: {}\nCurrent Frontier: {}\nStates created: {}",
-             states_visited, frontier_max, children_discarded, (children_discarded as f64 / states_created as f64), frontier.len(), states_created);
-    println!("Max Flows Complete: {}", max_flows_completed);
-    println!("Discard Stats:\n\tNum children: {}\n\tDead end: {}\n\tPools: {}\n\tBlocked Flow: {}\n\tCC Failed: {}\n",
-             (discarded_no_children as f64 / children_discarded as f64),
-             (discarded_dead_end as f64 / children_discarded as f64),
-             (discarded_pools as f64 / children_discarded as f64),
-             (discarded_blocked as f64 / children_discarded as f64),
-             (discarded_cc as f64 / children_discarded as f64));
-    println!("Uh oh, no solution!");
-
-    if let Some(l) = latest {
-        println!("Latest configuration:");
-        l.print_self();
-
-        println!("Latest children:");
-        let children = l.create_children();
-
-        for child in children.iter() {
-            child.print_self();
-            println!("Solvable: {}\n", child.is_solvable());
-        }
+    if results
+        .iter()
+        .any(|result| matches!(result.outcome, BatchOutcome::Error(_)))
+    {
+        EXIT_PARSE_ERROR
+    } else {
+        EXIT_SOLVED
     }
-    None
 }
 
 // Handle arguments
 // Basically, yell at the user if they did something wrong. It's really a one sided argument
 // If only it could handle my arguments with the borrow checker...
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    match args.len() {
-        2 => {
-            let filename = &args[1];
-            solve_puzzle(filename);
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        eprintln!("{}", help_text());
+        process::exit(EXIT_USAGE);
+    }
+
+    let arguments = match parse_arguments(&args) {
+        Err(why) => {
+            eprintln!("{}\nRun with --help to see what can be done", why);
+            process::exit(EXIT_USAGE);
         }
-        _ => {
-            println!("Enter the path to a puzzle to be solved!");
+        Ok(arguments) => arguments,
+    };
+    if arguments.help {
+        println!("{}", help_text());
+        process::exit(EXIT_SOLVED);
+    }
+
+    let code = match arguments.command.as_str() {
+        "verify" => {
+            verify_puzzle_solution(&arguments.files[0], &arguments.files[1], arguments.level)
         }
+        "hint" => hint_puzzle(&arguments),
+        "steps" => steps_puzzle(&arguments),
+        "explain" => explain_puzzle(&arguments),
+        "rate" => rate_puzzle(&arguments),
+        "render" => render_puzzle(&arguments),
+        "convert" => convert_puzzle(&arguments),
+        "batch" => batch_solve(&arguments),
+        "bench" => bench(&arguments),
+        "generate" => generate_puzzle(&arguments),
+        _ => solve_puzzle(&arguments),
     };
+    process::exit(code);
 }
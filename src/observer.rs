@@ -0,0 +1,177 @@
+use crate::puzzle::{Puzzle, Unsolvable};
+use crate::solver::{Outcome, Stats};
+
+// What the search should do after an observer hears about a state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    // Give up, e.g. because the user got bored of waiting. The search ends as cancelled
+    Stop,
+}
+
+// Something that wants to hear how the search is going, e.g. to show progress or log it
+// Every event comes with the stats so far. All of them do nothing unless they're implemented
+pub trait Observer {
+    // A state was taken off the frontier, and its children are about to be made
+    fn state_expanded(&mut self, _state: &Puzzle, _stats: &Stats) -> Control {
+        Control::Continue
+    }
+
+    // A child was thrown away, because it can't be solved for the given reason
+    fn child_pruned(&mut self, _child: &Puzzle, _reason: &Unsolvable, _stats: &Stats) {}
+
+    // A state has more cells filled in than any before it
+    fn new_best_depth(&mut self, _state: &Puzzle, _depth: usize, _stats: &Stats) {}
+
+    fn solution_found(&mut self, _solution: &Puzzle, _stats: &Stats) {}
+
+    // The search is over, however it ended. The last state expanded is given, if there was one
+    fn search_ended(&mut self, _outcome: &Outcome, _last_state: Option<&Puzzle>, _stats: &Stats) {}
+}
+
+// Nobody's watching
+impl Observer for () {}
+
+impl<T: Observer + ?Sized> Observer for &mut T {
+    fn state_expanded(&mut self, state: &Puzzle, stats: &Stats) -> Control {
+        (**self).state_expanded(state, stats)
+    }
+
+    fn child_pruned(&mut self, child: &Puzzle, reason: &Unsolvable, stats: &Stats) {
+        (**self).child_pruned(child, reason, stats)
+    }
+
+    fn new_best_depth(&mut self, state: &Puzzle, depth: usize, stats: &Stats) {
+        (**self).new_best_depth(state, depth, stats)
+    }
+
+    fn solution_found(&mut self, solution: &Puzzle, stats: &Stats) {
+        (**self).solution_found(solution, stats)
+    }
+
+    fn search_ended(&mut self, outcome: &Outcome, last_state: Option<&Puzzle>, stats: &Stats) {
+        (**self).search_ended(outcome, last_state, stats)
+    }
+}
+
+// Two observers watching the same search. The search stops if either of them wants it to
+impl<A: Observer, B: Observer> Observer for (A, B) {
+    fn state_expanded(&mut self, state: &Puzzle, stats: &Stats) -> Control {
+        let first = self.0.state_expanded(state, stats);
+        let second = self.1.state_expanded(state, stats);
+        if first == Control::Stop || second == Control::Stop {
+            Control::Stop
+        } else {
+            Control::Continue
+        }
+    }
+
+    fn child_pruned(&mut self, child: &Puzzle, reason: &Unsolvable, stats: &Stats) {
+        self.0.child_pruned(child, reason, stats);
+        self.1.child_pruned(child, reason, stats);
+    }
+
+    fn new_best_depth(&mut self, state: &Puzzle, depth: usize, stats: &Stats) {
+        self.0.new_best_depth(state, depth, stats);
+        self.1.new_best_depth(state, depth, stats);
+    }
+
+    fn solution_found(&mut self, solution: &Puzzle, stats: &Stats) {
+        self.0.solution_found(solution, stats);
+        self.1.solution_found(solution, stats);
+    }
+
+    fn search_ended(&mut self, outcome: &Outcome, last_state: Option<&Puzzle>, stats: &Stats) {
+        self.0.search_ended(outcome, last_state, stats);
+        self.1.search_ended(outcome, last_state, stats);
+    }
+}
+
+// How much the console observer prints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    // Nothing at all
+    Quiet,
+    // Progress every so often, and the stats at the end
+    Normal,
+    // As well as the above, the last state looked at and its children if the puzzle can't be solved
+    Verbose,
+}
+
+// Print some stats every this many states to keep the user happy, and let them know that we're still chugging along
+const PROGRESS_EVERY: u64 = 10000;
+
+// Prints how the search is going to the console
+#[derive(Debug, Clone)]
+pub struct ConsoleObserver {
+    verbosity: Verbosity,
+    // Since the last progress line
+    max_flows_completed: u64,
+    total_flows_complete: u64,
+    total_cells_open: u64,
+}
+
+impl ConsoleObserver {
+    pub fn new(verbosity: Verbosity) -> ConsoleObserver {
+        ConsoleObserver {
+            verbosity,
+            max_flows_completed: 0,
+            total_flows_complete: 0,
+            total_cells_open: 0,
+        }
+    }
+}
+
+impl Observer for ConsoleObserver {
+    fn state_expanded(&mut self, state: &Puzzle, stats: &Stats) -> Control {
+        if self.verbosity == Verbosity::Quiet {
+            return Control::Continue;
+        }
+        self.max_flows_completed = self.max_flows_completed.max(state.num_complete());
+        self.total_flows_complete += state.num_complete();
+        self.total_cells_open += state.num_open_cells();
+
+        if stats.states_visited.is_multiple_of(PROGRESS_EVERY) {
+            println!(
+                "{}\t{}\t{:.4}\t{:.1}\t{}\t{:.4}",
+                stats.states_visited,
+                stats.max_frontier,
+                stats.discard_ratio(),
+                self.total_cells_open as f64 / PROGRESS_EVERY as f64,
+                self.max_flows_completed,
+                self.total_flows_complete as f64 / PROGRESS_EVERY as f64
+            );
+            self.max_flows_completed = 0;
+            self.total_cells_open = 0;
+            self.total_flows_complete = 0;
+        }
+        Control::Continue
+    }
+
+    fn search_ended(&mut self, outcome: &Outcome, last_state: Option<&Puzzle>, stats: &Stats) {
+        if self.verbosity == Verbosity::Quiet {
+            return;
+        }
+        stats.print();
+        if !matches!(outcome, Outcome::Unsolvable) {
+            return;
+        }
+        // Never want to get here - if we did, the solver failed
+        println!("Uh oh, no solution!");
+
+        if let (Verbosity::Verbose, Some(l)) = (self.verbosity, last_state) {
+            println!("Latest configuration:");
+            l.print_self();
+
+            println!("Latest children:");
+            let children = l.create_children();
+            for child in children.iter() {
+                child.print_self();
+                match child.find_unsolvable() {
+                    Some(reason) => println!("Unsolvable: {}\n", reason.describe(child)),
+                    None => println!("Solvable\n"),
+                }
+            }
+        }
+    }
+}
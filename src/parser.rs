@@ -0,0 +1,418 @@
+use crate::puzzle::{CellId, FlowId, Puzzle, BRIDGE_NOTE, HORIZONTAL, MAX_CELLS, VERTICAL};
+use std::cmp::max;
+use std::collections::HashMap;
+
+// Marks the start of the pre-drawn paths section of a puzzle file
+pub const PATHS_SECTION: &str = "PATHS";
+
+// The types of puzzle, one of which is the first line of every puzzle file
+pub const PUZZLE_TYPES: [&str; 4] = ["STANDARD", "BRIDGES", "HEX", "WARPS"];
+
+// Split a line of a puzzle file into its columns
+// Every character is a column of its own, except for escaped labels ("{...}"), which are a single column
+// An unterminated brace is just treated as a regular character
+pub fn split_columns(line: &str) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut columns = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '{' {
+            // Look for the closing brace. Empty labels ("{}") aren't labels
+            if let Some(len) = chars[i + 1..].iter().position(|c| *c == '}') {
+                if len > 0 {
+                    columns.push(chars[i..i + len + 2].iter().collect());
+                    i += len + 2;
+                    continue;
+                }
+            }
+        }
+        columns.push(chars[i].to_string());
+        i += 1;
+    }
+    columns
+}
+
+// If the column is a flow endpoint, return the label of its flow
+// Endpoints are single letters or digits, or any other label escaped in braces (e.g. "{Red}")
+pub fn parse_label(token: &str) -> Option<String> {
+    if token.len() > 2 && token.starts_with('{') && token.ends_with('}') {
+        return Some(token[1..token.len() - 1].to_string());
+    }
+    let mut chars = token.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_string()),
+        _ => None,
+    }
+}
+
+// The label for the flow at the given position, for puzzles made without any labels of their own:
+// letters, then digits, then numbers in braces
+pub fn flow_label(index: usize) -> String {
+    let single: Vec<char> = ('A'..='Z').chain('a'..='z').chain('0'..='9').collect();
+    match single.get(index) {
+        Some(c) => c.to_string(),
+        None => format!("{{{}}}", index + 1),
+    }
+}
+
+// Is the column a cell (an endpoint, an empty cell or a bridge), as opposed to a neighbor character or whitespace?
+pub fn is_cell_token(token: &str) -> bool {
+    token == "." || token == "*" || parse_label(token).is_some()
+}
+
+// Find the ids of the two cells joined by the neighbor character c, located at the given column and row
+// Returns None if the character doesn't sit between two cells
+fn find_neighbor_pair(
+    cell_map: &HashMap<String, usize>,
+    c: char,
+    col: usize,
+    row: usize,
+    max_cell_col: usize,
+    max_cell_row: usize,
+) -> Option<(CellId, CellId)> {
+    // Get the coordinates of the two neighbors, and the neighbor relationship for each
+    let (col1, row1, col2, row2, direction) = match c {
+        '-' => {
+            // If the current column is greater than the max width, this is a warped relationship. The neighbor is in the first column.
+            if col > max_cell_col {
+                (col.checked_sub(1)?, row, 0, row, HORIZONTAL)
+            } else {
+                (col.checked_sub(1)?, row, col + 1, row, HORIZONTAL)
+            }
+        }
+        '/' => (
+            col.checked_sub(1)?,
+            row + 1,
+            col + 1,
+            row.checked_sub(1)?,
+            HORIZONTAL,
+        ), // These aren't really horizontal, but close enough, and it doesn't really matter
+        '\\' => (
+            col.checked_sub(1)?,
+            row.checked_sub(1)?,
+            col + 1,
+            row + 1,
+            HORIZONTAL,
+        ),
+        '|' => {
+            // If the row is greater than the max height, this is warped relationship. The neighbor is in the first row.
+            if row > max_cell_row {
+                (col, row.checked_sub(1)?, col, 0, VERTICAL)
+            } else {
+                (col, row.checked_sub(1)?, col, row + 1, VERTICAL)
+            }
+        }
+        _ => return None,
+    };
+
+    // Recreate the map keys for the two neighbors
+    // If there is no regular cell at the location, it might be the appropriate half of a bridge
+    let lookup = |col: usize, row: usize| {
+        let suffix = if direction == VERTICAL { "|" } else { "-" };
+        cell_map
+            .get(&format!("{}-{}", col, row))
+            .or_else(|| cell_map.get(&format!("{}-{}-{}", col, row, suffix)))
+            .map(|index| CellId { index: *index })
+    };
+
+    Some((lookup(col1, row1)?, lookup(col2, row2)?))
+}
+
+// Parse the contents of a puzzle file into the initial puzzle state
+// If something is wrong with the file, return a description of the problem instead
+pub fn parse_puzzle(input: &str) -> Result<Puzzle, String> {
+    // Split the input based on lines, and split off the pre-drawn paths (if there are any)
+    let lines: Vec<&str> = input.split('\n').collect();
+    let (board_lines, path_lines) = match lines
+        .iter()
+        .position(|line| line.trim_end() == PATHS_SECTION)
+    {
+        Some(i) => (&lines[..i], &lines[i + 1..]),
+        None => (&lines[..], &lines[lines.len()..]),
+    };
+    if board_lines.is_empty() {
+        return Err(String::from("the board is missing before PATHS"));
+    }
+    let split_input = &board_lines[1..];
+
+    // Check if the puzzle is a HEX puzzle
+    // (trim, as the line may or may not end with a carriage return)
+    let puzzle_type = board_lines[0].trim();
+    let is_hex = puzzle_type == "HEX";
+
+    // The initial puzzle
+    let mut puzzle: Puzzle = Puzzle::new(is_hex, board_lines.join("\n"));
+
+    // Used to map locations of cells via the cell's id (as a usize, for reasons beyond my comprehension)
+    // Used to create neighbor relationships later
+    let mut cell_map: HashMap<String, usize> = HashMap::new();
+
+    let mut row = 0; // Track the current row
+                     // Track the max height and width of the puzzle (note: this is because not all puzzles are squares and rectangles)
+    let mut max_cell_row = 0;
+    let mut max_cell_col = 0;
+    // Nested for loops: iterate through each character in the input board
+    // If it a cell (label, period, or asterisk), create a cell for it and update/create a flow
+    for line in split_input {
+        if line.starts_with("//") {
+            continue;
+        }
+        for (col, token) in split_columns(line).iter().enumerate() {
+            // Check if the column is a cell
+            if is_cell_token(token) {
+                let is_bridge = token == "*"; // Asterisks are bridges
+                let label = parse_label(token);
+                let is_endpoint = label.is_some();
+
+                if !is_bridge {
+                    // Create the new cell
+                    let cell_id: CellId = puzzle.new_cell(is_endpoint, None, is_hex, row, col);
+
+                    // Create a key for the map from the coordinates of the cell, and insert it into the map with the new cell id
+                    let key: String = format!("{}-{}", col, row);
+                    cell_map.insert(key, cell_id.index);
+
+                    // If the cell is an endpoint, either create a new flow if needed or update an existing one
+                    if let Some(label) = label {
+                        let mut flow_id_1 = None;
+                        let mut flow_exists = false;
+
+                        // Check if a flow with the current label already exists
+                        // If it does, update hte appropriate values
+                        for (count, flow) in puzzle.flows.iter_mut().enumerate() {
+                            if flow.get_label() == label {
+                                // Each flow has exactly two dots, so a third can't be right
+                                if flow.has_both_dots() {
+                                    return Err(format!(
+                                        "flow {} has more than two endpoints (the third is at row {}, column {})",
+                                        label,
+                                        row + 1,
+                                        col + 1
+                                    ));
+                                }
+                                flow_exists = true;
+                                flow.set_dot(1, cell_id);
+                                flow_id_1 = Some(FlowId { index: count });
+                            }
+                        }
+
+                        // If the flow doesn't exist, create a new one
+                        if !flow_exists {
+                            let flow_id = puzzle.new_flow(&label);
+                            let flow = puzzle.get_flow_mut(flow_id).unwrap();
+                            flow_id_1 = Some(flow_id);
+                            flow.set_dot(0, cell_id);
+                        }
+
+                        // Update the new cell with the appropriate flow
+                        puzzle.get_cell_mut(cell_id).unwrap().flow_id = flow_id_1;
+                    }
+                } else {
+                    // Bridges can't have a flow to set up, but do have an extra cell associated with them
+                    let cell_id1: CellId = puzzle.new_cell(is_endpoint, None, is_hex, row, col);
+                    let cell_id2: CellId = puzzle.new_cell(is_endpoint, None, is_hex, row, col);
+                    puzzle.get_cell_mut(cell_id1).unwrap().bridge_direction = Some(HORIZONTAL);
+                    puzzle.get_cell_mut(cell_id2).unwrap().bridge_direction = Some(VERTICAL);
+
+                    let key1: String = format!("{}-{}--", col, row);
+                    let key2: String = format!("{}-{}-|", col, row);
+                    cell_map.insert(key1, cell_id1.index);
+                    cell_map.insert(key2, cell_id2.index);
+                }
+
+                // Update the max size variables
+                max_cell_row = max(max_cell_row, row);
+                max_cell_col = max(max_cell_col, col);
+            }
+        }
+        row += 1;
+    }
+
+    // Cell ids past this would be mistaken for the solver's "no cell"
+    if puzzle.num_cells() > MAX_CELLS {
+        return Err(format!(
+            "the puzzle has {} cells (counting each bridge as two), but the most the solver can handle is {}",
+            puzzle.num_cells(),
+            MAX_CELLS
+        ));
+    }
+
+    row = 0;
+    // Again, loop through all characters in the board configuration
+    // This time, look for neighbor characters: '-', '|', '/', '\'
+    // When one is found, update the appropriate cells
+    for line in split_input {
+        if line.starts_with("//") {
+            continue;
+        }
+        for (col, token) in split_columns(line).iter().enumerate() {
+            let c = token.chars().next().unwrap();
+            // If the character is a neighbor character, proceed
+            if token.len() == 1 && (c == '-' || c == '/' || c == '\\' || c == '|') {
+                let (id1, id2) =
+                    find_neighbor_pair(&cell_map, c, col, row, max_cell_col, max_cell_row).ok_or(
+                        format!(
+                            "'{}' at row {}, column {} doesn't connect two cells",
+                            c,
+                            row + 1,
+                            col + 1
+                        ),
+                    )?;
+
+                puzzle.get_cell_mut(id1).unwrap().add_neighbor(id2);
+                puzzle.get_cell_mut(id2).unwrap().add_neighbor(id1);
+            }
+        }
+        row += 1;
+    }
+
+    // Every flow needs a pair of endpoints
+    for flow in puzzle.flows.iter() {
+        if !flow.has_both_dots() {
+            return Err(format!("flow {} only has one endpoint", flow.get_label()));
+        }
+    }
+
+    apply_paths(
+        &mut puzzle,
+        path_lines,
+        &cell_map,
+        max_cell_col,
+        max_cell_row,
+    )?;
+
+    Ok(puzzle)
+}
+
+// Apply the pre-drawn paths section of a puzzle file to the initial puzzle state
+// The section is laid out exactly like the board. A label restricts that cell to the flow with the same label,
+// and a neighbor character is a connection that must be part of a flow's path.
+// Just like in the game, paths must be drawn out from one of the flow's endpoints, so every connection has to
+// be reachable from an endpoint. The endpoint is moved to the end of the drawn path, the same way the solver extends it.
+fn apply_paths(
+    puzzle: &mut Puzzle,
+    path_lines: &[&str],
+    cell_map: &HashMap<String, usize>,
+    max_cell_col: usize,
+    max_cell_row: usize,
+) -> Result<(), String> {
+    // The drawn connections, along with where they were found (for error messages)
+    let mut connections: Vec<(CellId, CellId, usize, usize)> = vec![];
+
+    let mut row = 0;
+    for line in path_lines {
+        if line.starts_with("//") || line.starts_with(BRIDGE_NOTE) {
+            continue;
+        }
+        for (col, token) in split_columns(line).iter().enumerate() {
+            let c = token.chars().next().unwrap();
+            if let Some(label) = parse_label(token) {
+                let cell_id = cell_map
+                    .get(&format!("{}-{}", col, row))
+                    .map(|index| CellId { index: *index })
+                    .ok_or(format!(
+                        "pre-drawn {} at row {}, column {} is not on a cell (bridges can only be drawn through)",
+                        label,
+                        row + 1,
+                        col + 1
+                    ))?;
+                let flow_id = puzzle
+                    .flows
+                    .iter()
+                    .position(|flow| flow.get_label() == label)
+                    .map(|index| FlowId { index })
+                    .ok_or(format!("pre-drawn flow {} has no endpoints", label))?;
+
+                let cell = puzzle.get_cell_mut(cell_id).unwrap();
+                if cell.flow_id.is_some_and(|id| id != flow_id) {
+                    return Err(format!(
+                        "pre-drawn {} at row {}, column {} is on another flow's endpoint",
+                        label,
+                        row + 1,
+                        col + 1
+                    ));
+                }
+                cell.restricted_to = Some(flow_id);
+            } else if token.len() == 1 && (c == '-' || c == '/' || c == '\\' || c == '|') {
+                let (id1, id2) =
+                    find_neighbor_pair(cell_map, c, col, row, max_cell_col, max_cell_row)
+                        .filter(|(id1, id2)| puzzle.get_cell(*id1).unwrap().is_neighbor(id2))
+                        .ok_or(format!(
+                    "pre-drawn '{}' at row {}, column {} doesn't connect two neighboring cells",
+                    c,
+                    row + 1,
+                    col + 1
+                ))?;
+                connections.push((id1, id2, row, col));
+            }
+        }
+        row += 1;
+    }
+
+    // Follow the connections out from each endpoint, extending the flow as we go
+    for f in 0..puzzle.flows.len() {
+        let flow_id = FlowId { index: f };
+        for endpoint_index in 0..2 {
+            loop {
+                let flow = puzzle.get_flow(flow_id).unwrap();
+                let head = flow.get_endpoint(endpoint_index);
+                let other_head = flow.get_endpoint(1 - endpoint_index);
+
+                let mut next = connections
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (id1, id2, _, _))| *id1 == head || *id2 == head);
+                let (i, (id1, id2, c_row, c_col)) = match (next.next(), next.next()) {
+                    (None, _) => break,
+                    (Some(connection), None) => connection,
+                    (Some(_), Some((_, (_, _, c_row, c_col)))) => {
+                        return Err(format!(
+                            "pre-drawn path of flow {} branches at row {}, column {}",
+                            flow.get_label(),
+                            c_row + 1,
+                            c_col + 1
+                        ));
+                    }
+                };
+                let next_id = if *id1 == head { *id2 } else { *id1 };
+                let (c_row, c_col) = (*c_row, *c_col);
+                connections.remove(i);
+
+                // Reaching the other end of the flow means the whole path has been drawn
+                if next_id == other_head {
+                    break;
+                }
+
+                let label = flow.get_label().to_string();
+                let next_cell = puzzle.get_cell_mut(next_id).unwrap();
+                if next_cell.is_occupied() || !next_cell.is_open_to(flow_id) {
+                    return Err(format!(
+                        "pre-drawn path of flow {} runs into another flow at row {}, column {}",
+                        label,
+                        c_row + 1,
+                        c_col + 1
+                    ));
+                }
+                next_cell.flow_id = Some(flow_id);
+                next_cell.is_endpoint = true;
+                next_cell.previous = Some(head);
+                puzzle.get_cell_mut(head).unwrap().is_endpoint = false;
+                puzzle
+                    .get_flow_mut(flow_id)
+                    .unwrap()
+                    .update_endpoint(endpoint_index, next_id);
+            }
+        }
+    }
+
+    // Anything left over isn't attached to an endpoint, which the solver has no way of representing
+    if let Some((_, _, c_row, c_col)) = connections.first() {
+        return Err(format!(
+            "pre-drawn connection at row {}, column {} isn't part of a path drawn from an endpoint",
+            c_row + 1,
+            c_col + 1
+        ));
+    }
+
+    Ok(())
+}
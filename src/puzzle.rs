@@ -0,0 +1,1148 @@
+use crate::parser::{is_cell_token, split_columns};
+use std::cmp::{max, Ordering};
+use std::collections::{HashSet, VecDeque};
+
+pub const NON_EXISTENT_CELL_ID: usize = 999;
+// The most cells a puzzle can have, so that every cell's id is below NON_EXISTENT_CELL_ID
+pub const MAX_CELLS: usize = NON_EXISTENT_CELL_ID;
+pub const MAX_NEIGHBORS: usize = 6;
+
+pub const SOLVABLE: i16 = 1;
+pub const UNSOLVABLE_NO_CHILDREN: i16 = -1;
+pub const UNSOLVABLE_DEAD_ENDS: i16 = -2;
+pub const UNSOLVABLE_POOLS: i16 = -3;
+pub const UNSOLVABLE_PATH_BLOCKED: i16 = -4;
+pub const UNSOLVABLE_REGION: i16 = -5;
+
+// Printed boards list which flows cross each bridge underneath, one line per bridge starting with this
+// Anything reading a printed board back in skips these lines
+pub const BRIDGE_NOTE: &str = "Bridge at row";
+
+// All of the neighbor constants
+pub const VERTICAL: usize = 0;
+pub const HORIZONTAL: usize = 1;
+
+// Why a puzzle can't be solved, according to the checks in is_solvable
+#[derive(Debug, Clone)]
+pub enum Unsolvable {
+    // The endpoint that would be extended next has nowhere to go
+    // None if there isn't one, because every flow is complete but there are still empty cells
+    NoChildren(Option<CellId>),
+    // An empty cell with only one empty neighbor and no endpoints next to it
+    DeadEnd(CellId),
+    // A flow that doubles back on itself at the given cell
+    Pool(CellId),
+    // A flow whose endpoints don't border any of the same empty region
+    PathBlocked(FlowId),
+    // A region of empty cells that doesn't border both endpoints of any flow
+    Region(Vec<CellId>),
+}
+impl Unsolvable {
+    // The code is_solvable returns for this reason
+    pub fn code(&self) -> i16 {
+        match self {
+            Unsolvable::NoChildren(_) => UNSOLVABLE_NO_CHILDREN,
+            Unsolvable::DeadEnd(_) => UNSOLVABLE_DEAD_ENDS,
+            Unsolvable::Pool(_) => UNSOLVABLE_POOLS,
+            Unsolvable::PathBlocked(_) => UNSOLVABLE_PATH_BLOCKED,
+            Unsolvable::Region(_) => UNSOLVABLE_REGION,
+        }
+    }
+
+    // Explain the reason in plain terms
+    pub fn describe(&self, puzzle: &Puzzle) -> String {
+        let location = |id: &CellId| puzzle.get_cell(*id).unwrap().location();
+        match self {
+            Unsolvable::NoChildren(Some(id)) => format!(
+                "Flow {} is stuck at {}: it has nowhere left to go",
+                puzzle.get_cell_label(*id),
+                location(id)
+            ),
+            Unsolvable::NoChildren(None) => String::from(
+                "Every flow is connected, but there are still empty cells that nothing can fill",
+            ),
+            Unsolvable::DeadEnd(id) => format!(
+                "Dead end at {}: it only has one empty neighbor and no endpoints next to it, so no flow can pass through it",
+                location(id)
+            ),
+            Unsolvable::Pool(id) => format!(
+                "Pool in flow {} at {}: the flow doubles back on itself",
+                puzzle.get_cell_label(*id),
+                location(id)
+            ),
+            Unsolvable::PathBlocked(flow_id) => {
+                let flow = puzzle.get_flow(*flow_id).unwrap();
+                format!(
+                    "Flow {} can't connect its endpoints at {} and {}: they don't border any of the same empty cells",
+                    flow.get_label(),
+                    location(&flow.get_endpoint(0)),
+                    location(&flow.get_endpoint(1))
+                )
+            }
+            Unsolvable::Region(cells) => format!(
+                "The {} empty cell(s) around {} can't be filled: no flow has both of its endpoints next to them",
+                cells.len(),
+                location(&cells[0])
+            ),
+        }
+    }
+}
+
+// Structures and implementations related to flows
+#[derive(Debug, Eq, Clone)]
+pub struct Flow {
+    pub id: usize,
+    endpoints: [Option<CellId>; 2],
+    dots: [Option<CellId>; 2],
+    label: String,
+}
+impl PartialEq for Flow {
+    fn eq(&self, _other: &Flow) -> bool {
+        false
+    }
+}
+impl Flow {
+    // Update the endpoint at the given index to the given cellID
+    // endpoint should be 0 or 1
+    pub fn update_endpoint(&mut self, endpoint: usize, cell_id: CellId) {
+        self.endpoints[endpoint] = Some(cell_id);
+    }
+
+    // Set one of the flow's dots (the endpoints given in the puzzle), which is also where that endpoint starts out
+    // Unlike the endpoints, the dots never move
+    pub fn set_dot(&mut self, endpoint: usize, cell_id: CellId) {
+        self.dots[endpoint] = Some(cell_id);
+        self.update_endpoint(endpoint, cell_id);
+    }
+
+    pub fn get_dots(&self) -> [CellId; 2] {
+        [self.dots[0].unwrap(), self.dots[1].unwrap()]
+    }
+
+    // A flow is only valid if both of its dots are in the puzzle
+    pub fn has_both_dots(&self) -> bool {
+        self.dots[1].is_some()
+    }
+
+    // Return true if this flow's endpoints are neighbors
+    pub fn is_complete(&self, puzzle: &Puzzle) -> bool {
+        puzzle
+            .get_cell(self.get_endpoint(0))
+            .unwrap()
+            .is_neighbor(&self.get_endpoint(1))
+    }
+
+    pub fn get_endpoints(&self) -> [CellId; 2] {
+        [self.endpoints[0].unwrap(), self.endpoints[1].unwrap()]
+    }
+
+    pub fn get_endpoint(&self, i: usize) -> CellId {
+        self.get_endpoints()[i]
+    }
+
+    // Getter for attribute 'label'
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    // The label as it would be written in a puzzle file
+    // Single character labels are written as-is, longer ones are escaped in braces
+    pub fn get_display_label(&self) -> String {
+        if self.label.chars().count() == 1 {
+            self.label.clone()
+        } else {
+            format!("{{{}}}", self.label)
+        }
+    }
+}
+
+#[derive(Debug, Eq, Clone, Copy)]
+pub struct FlowId {
+    pub index: usize,
+}
+impl PartialEq for FlowId {
+    fn eq(&self, other: &FlowId) -> bool {
+        self.index.eq(&other.index)
+    }
+}
+
+// Structures and implementations related to cells
+#[derive(Debug, Clone, Eq)]
+pub struct Cell {
+    pub is_endpoint: bool,
+    pub flow_id: Option<FlowId>,
+    pub neighbors: Vec<CellId>,
+    pub is_hex: bool,
+    pub restricted_to: Option<FlowId>,
+    // Where the cell is in the puzzle file (counting from 0, not including the first line)
+    pub row: usize,
+    pub col: usize,
+    // Bridges are split into two cells, one for each direction (VERTICAL or HORIZONTAL)
+    pub bridge_direction: Option<usize>,
+    // The cell the flow came from to get here, if the flow was extended into this cell
+    pub previous: Option<CellId>,
+}
+impl PartialEq for Cell {
+    fn eq(&self, _other: &Cell) -> bool {
+        false
+    }
+}
+impl Cell {
+    // Update the given neighbor
+    pub fn add_neighbor(&mut self, cell: CellId) {
+        self.neighbors.push(cell);
+    }
+
+    // Given a CellId, check if that cell in a neighbor of this cell
+    pub fn is_neighbor(&self, other: &CellId) -> bool {
+        self.neighbors
+            .iter()
+            .filter(|n| n.index == other.index)
+            .count()
+            > 0
+    }
+
+    pub fn is_occupied(&self) -> bool {
+        self.flow_id.is_some()
+    }
+
+    // Can the given flow move into this cell?
+    // The cell must be empty, and not pre-assigned to a different flow
+    pub fn is_open_to(&self, flow_id: FlowId) -> bool {
+        !self.is_occupied() && self.restricted_to.is_none_or(|id| id == flow_id)
+    }
+
+    // Return the number of neighboring cells that are not occupied
+    pub fn num_open_neighbors(&self, puzzle: &Puzzle) -> usize {
+        self.neighbors
+            .iter()
+            .filter(|n| !puzzle.get_cell(**n).unwrap().is_occupied())
+            .count()
+    }
+
+    // Return the number of neighboring cells this cell's flow could move into
+    pub fn num_moves(&self, puzzle: &Puzzle) -> usize {
+        match self.flow_id {
+            Some(flow_id) => self
+                .neighbors
+                .iter()
+                .filter(|n| puzzle.get_cell(**n).unwrap().is_open_to(flow_id))
+                .count(),
+            None => 0,
+        }
+    }
+
+    // Just the number of neighbors
+    pub fn num_neighbors(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    // Describe where the cell is in the puzzle file, for messages to the user
+    pub fn location(&self) -> String {
+        let bridge = match self.bridge_direction {
+            Some(VERTICAL) => " (vertical crossing of the bridge)",
+            Some(_) => " (horizontal crossing of the bridge)",
+            None => "",
+        };
+        format!("row {}, column {}{}", self.row + 1, self.col + 1, bridge)
+    }
+}
+
+#[derive(Debug, Default, Eq, Copy, Clone)]
+pub struct CellId {
+    pub index: usize,
+}
+impl PartialEq for CellId {
+    fn eq(&self, other: &CellId) -> bool {
+        self.index.eq(&other.index)
+    }
+}
+
+#[derive(Debug, Eq, Clone, PartialEq)]
+pub struct Puzzle {
+    cells: Vec<Cell>,
+    pub flows: Vec<Flow>,
+    pub is_hex: bool,
+    pub print_string: String,
+}
+
+impl Ord for Puzzle {
+    fn cmp(&self, other: &Puzzle) -> Ordering {
+        let s = self.h();
+        let o = other.h();
+        s.cmp(&o)
+    }
+}
+
+// 'PartialOrd' needs to be implemented as well.
+impl PartialOrd for Puzzle {
+    fn partial_cmp(&self, other: &Puzzle) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Puzzle {
+    // Create a new puzzle
+    pub fn new(is_hex: bool, print_string: String) -> Puzzle {
+        Puzzle {
+            cells: Vec::new(),
+            flows: Vec::new(),
+            is_hex,
+            print_string,
+        }
+    }
+
+    // Crate a new cell
+    pub fn new_cell(
+        &mut self,
+        is_endpoint: bool,
+        flow_id: Option<FlowId>,
+        is_hex: bool,
+        row: usize,
+        col: usize,
+    ) -> CellId {
+        let next_index = self.num_cells();
+        self.cells.push(Cell {
+            is_endpoint,
+            flow_id,
+            neighbors: vec![],
+            is_hex,
+            restricted_to: None,
+            row,
+            col,
+            bridge_direction: None,
+            previous: None,
+        });
+
+        CellId { index: next_index }
+    }
+
+    pub fn num_cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn get_cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    // Each neighbor relationship is stored on both cells, so halve the total
+    pub fn num_neighbor_pairs(&self) -> usize {
+        self.cells
+            .iter()
+            .map(|cell| cell.num_neighbors())
+            .sum::<usize>()
+            / 2
+    }
+
+    pub fn get_cell(&self, id: CellId) -> Option<&Cell> {
+        self.cells.get(id.index)
+    }
+
+    pub fn get_cell_mut(&mut self, id: CellId) -> Option<&mut Cell> {
+        self.cells.get_mut(id.index)
+    }
+
+    pub fn new_flow(&mut self, label: &str) -> FlowId {
+        let next_index = self.num_flows();
+        self.flows.push(Flow {
+            id: next_index as usize,
+            endpoints: [None; 2],
+            dots: [None; 2],
+            label: label.to_string(),
+        });
+
+        FlowId {
+            index: next_index as usize,
+        }
+    }
+
+    // Print the board in the same format as the puzzle file (see to_text)
+    pub fn print_self(&self) {
+        print!("{}", self.to_text());
+    }
+
+    // The board in the same format as the puzzle file, with every filled cell showing its flow's label
+    // Only the connectors that the flows actually go through are kept, so the output shows exactly where each
+    // path runs (and can be read back in, e.g. as a PATHS section or by verify)
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let used: HashSet<(usize, usize)> = self
+            .get_connections()
+            .iter()
+            .map(|(a, b)| self.connector_between(*a, *b))
+            .collect();
+
+        let mut bridge_addendum = String::new();
+        let mut cell = 0;
+        let mut row = 0;
+        // Loop over it printing everything out
+        // Unless it is a cell character. Then replace it with the appropriate label from the solved puzzle
+        for line in self.print_string.split('\n').skip(1) {
+            if line.starts_with("//") {
+                text.push_str(line);
+                text.push('\n');
+                continue;
+            }
+            let mut output = String::new();
+            for (col, token) in split_columns(line).iter().enumerate() {
+                if is_cell_token(token) {
+                    if token == "*" {
+                        // The horizontal half of a bridge comes first, then the vertical half
+                        bridge_addendum.push_str(&format!(
+                            "{} {}, column {}: vertical is {}, horizontal is {}\n",
+                            BRIDGE_NOTE,
+                            row + 1,
+                            col + 1,
+                            self.get_cell_label(CellId { index: cell + 1 }),
+                            self.get_cell_label(CellId { index: cell })
+                        ));
+                        output.push('*');
+                        cell += 2;
+                    } else {
+                        match self.get_cell(CellId { index: cell }).unwrap().flow_id {
+                            Some(flow_id) => output
+                                .push_str(&self.get_flow(flow_id).unwrap().get_display_label()),
+                            None => output.push('.'),
+                        }
+                        cell += 1;
+                    }
+                } else if ["-", "|", "/", "\\"].contains(&token.as_str())
+                    && !used.contains(&(row, col))
+                {
+                    // A connector that no flow goes through
+                    output.push(' ');
+                } else {
+                    output.push_str(token);
+                }
+            }
+            text.push_str(output.trim_end());
+            text.push('\n');
+            row += 1;
+        }
+        text.push_str(&format!("\n{}\n", bridge_addendum));
+        text
+    }
+
+    // Where the connector between two neighboring cells is in the puzzle file, as (row, column)
+    // Usually it's between the two, but warps are joined by the connector past the end of the row or column
+    pub fn connector_between(&self, a: CellId, b: CellId) -> (usize, usize) {
+        let (a, b) = (self.get_cell(a).unwrap(), self.get_cell(b).unwrap());
+        if a.row.abs_diff(b.row) <= 2 && a.col.abs_diff(b.col) <= 2 {
+            ((a.row + b.row) / 2, (a.col + b.col) / 2)
+        } else if a.row == b.row {
+            (a.row, max(a.col, b.col) + 1)
+        } else {
+            (max(a.row, b.row) + 1, a.col)
+        }
+    }
+
+    // The label of the flow in the given cell, or "empty" if there isn't one
+    pub fn get_cell_label(&self, id: CellId) -> String {
+        match self.get_cell(id).unwrap().flow_id {
+            Some(flow_id) => self.get_flow(flow_id).unwrap().get_label().to_string(),
+            None => String::from("empty"),
+        }
+    }
+
+    pub fn num_flows(&self) -> u64 {
+        self.flows.len() as u64
+    }
+
+    pub fn get_flow(&self, id: FlowId) -> Option<&Flow> {
+        self.flows.get(id.index)
+    }
+
+    pub fn get_flow_mut(&mut self, id: FlowId) -> Option<&mut Flow> {
+        self.flows.get_mut(id.index)
+    }
+
+    pub fn num_complete(&self) -> u64 {
+        self.flows
+            .iter()
+            .filter(|flow| flow.is_complete(self))
+            .count() as u64
+    }
+
+    pub fn num_open_cells(&self) -> u64 {
+        self.cells.iter().filter(|cell| !cell.is_occupied()).count() as u64
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.num_complete() == self.num_flows() && self.num_open_cells() == 0
+    }
+
+    // Is the cell one of the dots of its flow?
+    pub fn is_dot(&self, id: CellId) -> bool {
+        match self.get_cell(id).unwrap().flow_id {
+            Some(flow_id) => self.get_flow(flow_id).unwrap().get_dots().contains(&id),
+            None => false,
+        }
+    }
+
+    // Find the path a flow takes, from its first dot to its second, going through every cell it occupies
+    // The puzzle only records which flow is in each cell, so the path is walked from the first dot. That only works
+    // if each dot has one neighbor in the flow and every other cell has two, so there's just one way to go
+    // Returns None if the flow's cells can't be joined up into a single path that way
+    pub fn trace_path(&self, flow_id: FlowId) -> Option<Vec<CellId>> {
+        let [start, end] = self.get_flow(flow_id)?.get_dots();
+        let same_flow_neighbors = |id: CellId| -> Vec<CellId> {
+            self.get_cell(id)
+                .unwrap()
+                .neighbors
+                .iter()
+                .copied()
+                .filter(|n| self.get_cell(*n).unwrap().flow_id == Some(flow_id))
+                .collect()
+        };
+
+        let mut length = 0;
+        for (index, cell) in self.cells.iter().enumerate() {
+            if cell.flow_id != Some(flow_id) {
+                continue;
+            }
+            length += 1;
+            let id = CellId { index };
+            let expected = if id == start || id == end { 1 } else { 2 };
+            if same_flow_neighbors(id).len() != expected {
+                return None;
+            }
+        }
+
+        // Every cell has one way in and one way out, so the walk can't go wrong. It reaches the other dot having
+        // been through every cell, unless some of them are off in a loop of their own
+        let mut path = vec![start];
+        let mut previous = None;
+        let mut curr = start;
+        while curr != end {
+            let next = same_flow_neighbors(curr)
+                .into_iter()
+                .find(|n| Some(*n) != previous)?;
+            previous = Some(curr);
+            curr = next;
+            path.push(curr);
+        }
+        if path.len() == length {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    // Every pair of neighboring cells that a flow runs between, for drawing the flows
+    // Flows extended by the solver remember where they came from. Otherwise (e.g. a solution read from a file),
+    // the path is traced instead, and a flow whose path can't be traced is left out
+    pub fn get_connections(&self) -> Vec<(CellId, CellId)> {
+        let mut connections = Vec::new();
+        for (f, flow) in self.flows.iter().enumerate() {
+            let flow_id = FlowId { index: f };
+            let cells: Vec<CellId> = (0..self.num_cells())
+                .map(|index| CellId { index })
+                .filter(|id| self.get_cell(*id).unwrap().flow_id == Some(flow_id))
+                .collect();
+
+            let linked = cells
+                .iter()
+                .all(|id| self.is_dot(*id) || self.get_cell(*id).unwrap().previous.is_some());
+            if linked {
+                for id in cells.iter() {
+                    if let Some(previous) = self.get_cell(*id).unwrap().previous {
+                        connections.push((previous, *id));
+                    }
+                }
+                // The two ends of a complete flow are next to each other, but neither came from the other
+                if flow.is_complete(self) {
+                    connections.push((flow.get_endpoint(0), flow.get_endpoint(1)));
+                }
+            } else if let Some(path) = self.trace_path(flow_id) {
+                connections.extend(path.windows(2).map(|pair| (pair[0], pair[1])));
+            }
+        }
+        connections
+    }
+
+    // Return a vector of all endpoints for flows that are not complete
+    pub fn get_endpoints_for_incomplete_flows(&self) -> Vec<CellId> {
+        let mut endpoints = Vec::new();
+
+        // If the flow is incomplete, push its two endpoints onto the vector
+        for flow in self.flows.iter().filter(|flow| !flow.is_complete(self)) {
+            endpoints.push(flow.get_endpoint(0));
+            endpoints.push(flow.get_endpoint(1));
+        }
+
+        endpoints
+    }
+
+    pub fn create_children(&self) -> Vec<Puzzle> {
+        let endpoint_id = self.get_endpoint_to_extend();
+
+        if endpoint_id.index == NON_EXISTENT_CELL_ID {
+            return vec![];
+        }
+        self.create_children_from(endpoint_id)
+    }
+
+    // A child for every cell the given endpoint could move into
+    pub fn create_children_from(&self, endpoint_id: CellId) -> Vec<Puzzle> {
+        let endpoint_cell = self.get_cell(endpoint_id).unwrap();
+        let flow_id = endpoint_cell.flow_id.unwrap();
+        let flow = self.get_flow(flow_id).unwrap();
+
+        let endpoint_index = if flow.get_endpoint(0).index == endpoint_id.index {
+            0
+        } else {
+            1
+        };
+
+        let mut children: Vec<Puzzle> = vec![];
+
+        for n_id in endpoint_cell
+            .neighbors
+            .iter()
+            .filter(|n_id| self.get_cell(**n_id).unwrap().is_open_to(flow_id))
+        {
+            let mut child = self.clone();
+
+            let cell_to_move_to = child.get_cell_mut(*n_id).unwrap();
+
+            cell_to_move_to.flow_id = Some(FlowId {
+                index: flow_id.index,
+            });
+            cell_to_move_to.is_endpoint = true;
+            cell_to_move_to.previous = Some(endpoint_id);
+
+            child
+                .get_cell_mut(child.get_flow(flow_id).unwrap().endpoints[endpoint_index].unwrap())
+                .unwrap()
+                .is_endpoint = false;
+
+            let child_flow = child.get_flow_mut(flow_id).unwrap();
+            child_flow.update_endpoint(endpoint_index, CellId { index: n_id.index });
+
+            children.push(child);
+        }
+
+        children
+    }
+
+    // Every way of finishing the endpoint's flow in one go: each child has the path drawn in from the endpoint until
+    // it reaches the flow's other end
+    // Returns None if there are more than the given number of ways (or it takes too long to find them all)
+    pub fn create_path_children(&self, endpoint_id: CellId, limit: usize) -> Option<Vec<Puzzle>> {
+        let flow_id = self.get_cell(endpoint_id).unwrap().flow_id.unwrap();
+        let flow = self.get_flow(flow_id).unwrap();
+        let (endpoint_index, other_end) = if flow.get_endpoint(0) == endpoint_id {
+            (0, flow.get_endpoint(1))
+        } else {
+            (1, flow.get_endpoint(0))
+        };
+
+        let mut paths: Vec<Vec<CellId>> = vec![];
+        let mut path = vec![endpoint_id];
+        let mut visited = vec![false; self.num_cells()];
+        visited[endpoint_id.index] = true;
+        // Give up after looking at this many partial paths, as well as after finding too many whole ones
+        let mut budget = limit * MAX_NEIGHBORS * MAX_NEIGHBORS;
+        if !self.find_paths(
+            flow_id,
+            other_end,
+            &mut path,
+            &mut visited,
+            &mut paths,
+            limit,
+            &mut budget,
+        ) {
+            return None;
+        }
+
+        let children = paths
+            .iter()
+            .map(|path| {
+                let mut child = self.clone();
+                child.get_cell_mut(endpoint_id).unwrap().is_endpoint = false;
+                for pair in path.windows(2) {
+                    let cell = child.get_cell_mut(pair[1]).unwrap();
+                    cell.flow_id = Some(flow_id);
+                    cell.previous = Some(pair[0]);
+                }
+                let end = *path.last().unwrap();
+                child.get_cell_mut(end).unwrap().is_endpoint = true;
+                child
+                    .get_flow_mut(flow_id)
+                    .unwrap()
+                    .update_endpoint(endpoint_index, end);
+                child
+            })
+            .collect();
+        Some(children)
+    }
+
+    // Depth first search for create_path_children: every way of continuing the path through empty cells until it
+    // reaches the other end (where the flow is complete, so it stops)
+    // Returns false if it ran out of budget or found more than the limit
+    #[allow(clippy::too_many_arguments)]
+    fn find_paths(
+        &self,
+        flow_id: FlowId,
+        other_end: CellId,
+        path: &mut Vec<CellId>,
+        visited: &mut Vec<bool>,
+        paths: &mut Vec<Vec<CellId>>,
+        limit: usize,
+        budget: &mut usize,
+    ) -> bool {
+        if *budget == 0 {
+            return false;
+        }
+        *budget -= 1;
+
+        let curr = *path.last().unwrap();
+        if path.len() > 1 && self.get_cell(curr).unwrap().is_neighbor(&other_end) {
+            paths.push(path.clone());
+            return paths.len() <= limit;
+        }
+
+        for n in self.get_cell(curr).unwrap().neighbors.iter() {
+            if visited[n.index] || !self.get_cell(*n).unwrap().is_open_to(flow_id) {
+                continue;
+            }
+            visited[n.index] = true;
+            path.push(*n);
+            let within = self.find_paths(flow_id, other_end, path, visited, paths, limit, budget);
+            path.pop();
+            visited[n.index] = false;
+            if !within {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn num_possible_children(&self) -> u64 {
+        let endpoint_id = self.get_endpoint_to_extend();
+        // If there is no endpoint to extend, there are no possible children
+        if endpoint_id.index == NON_EXISTENT_CELL_ID {
+            return 0;
+        }
+        let endpoint_cell = self.get_cell(endpoint_id).unwrap();
+        endpoint_cell.num_moves(self) as u64
+    }
+
+    // Look for a move that has to be made, no matter what the rest of the solution looks like
+    // Returns the endpoint to move, and the cell it has to move into
+    pub fn get_forced_move(&self) -> Option<(CellId, CellId)> {
+        let endpoints = self.get_endpoints_for_incomplete_flows();
+
+        // An endpoint with only one place to go has to go there
+        for endpoint_id in &endpoints {
+            let cell = self.get_cell(*endpoint_id).unwrap();
+            let flow_id = cell.flow_id.unwrap();
+            let mut moves = cell
+                .neighbors
+                .iter()
+                .filter(|n| self.get_cell(**n).unwrap().is_open_to(flow_id));
+            if let (Some(n), None) = (moves.next(), moves.next()) {
+                return Some((*endpoint_id, *n));
+            }
+        }
+
+        // A path going through an empty cell needs two neighbors. If the cell only has one empty neighbor,
+        // one of the path's neighbors has to be an endpoint moving into it. If there's only one endpoint that
+        // can do that, it's a forced move
+        for (index, cell) in self.cells.iter().enumerate() {
+            if cell.is_occupied() || cell.num_open_neighbors(self) > 1 {
+                continue;
+            }
+
+            let mut candidates = endpoints.iter().filter(|e| {
+                let flow_id = self.get_cell(**e).unwrap().flow_id.unwrap();
+                cell.is_neighbor(e) && cell.is_open_to(flow_id)
+            });
+            if let (Some(endpoint_id), None) = (candidates.next(), candidates.next()) {
+                return Some((*endpoint_id, CellId { index }));
+            }
+        }
+
+        None
+    }
+
+    // Find the incomplete flow with the fewest moves between both of its ends, and return whichever of its ends has
+    // fewer (the first, if they're even)
+    pub fn get_flow_end_to_extend(&self) -> CellId {
+        let num_moves = |id: CellId| self.get_cell(id).unwrap().num_moves(self);
+        self.flows
+            .iter()
+            .filter(|flow| !flow.is_complete(self))
+            .min_by_key(|flow| num_moves(flow.get_endpoint(0)) + num_moves(flow.get_endpoint(1)))
+            .map_or(
+                CellId {
+                    index: NON_EXISTENT_CELL_ID,
+                },
+                |flow| {
+                    let [first, second] = flow.get_endpoints();
+                    if num_moves(second) < num_moves(first) {
+                        second
+                    } else {
+                        first
+                    }
+                },
+            )
+    }
+
+    // Basically, find the endpoint with the fewest open neighbors (possibilities) and return that one
+    pub fn get_endpoint_to_extend(&self) -> CellId {
+        let possible_endpoints = self.get_endpoints_for_incomplete_flows();
+        let mut min_open = MAX_NEIGHBORS + 1; // Cannot have more open than the maximum number of neighbors
+
+        // Default value in case no endpoints are found
+        let mut min_open_cell_id = CellId {
+            index: NON_EXISTENT_CELL_ID,
+        };
+        for cell_id in &possible_endpoints {
+            let cell = self.get_cell(*cell_id);
+
+            // If this endpoint has fewer open neighbors than the current minimum,
+            // set the current minimum to this endpoint
+            if cell.unwrap().num_moves(self) < min_open {
+                min_open = cell.unwrap().num_moves(self);
+                min_open_cell_id = *cell_id;
+            }
+        }
+
+        // If no endpoints are found, return immediately
+        // This would likely happen if all flows are complete, but there remain cells open
+        if min_open_cell_id.index == NON_EXISTENT_CELL_ID {
+            return min_open_cell_id;
+        }
+
+        // If there < 2 neighbors open for the selected endpoint,
+        // Determine if there is a neighbor which only has one open neighbor. This is a forced move
+        // Don't do this check if < 2 neighbors open, because that is already a forced move
+        if min_open > 1 {
+            for cell_id in &possible_endpoints {
+                let cell = self.get_cell(*cell_id).unwrap();
+
+                // Check each of the endpoint's neighbors
+                for n_index in cell.neighbors.iter() {
+                    let neighbor = self.get_cell(*n_index).unwrap();
+
+                    // If the neighbor is open and has only one neighbor, the endpoint must be
+                    // the next one to move, so return it
+                    if !neighbor.is_occupied() && neighbor.num_open_neighbors(self) == 1 {
+                        return *cell_id;
+                    }
+                }
+            }
+        }
+
+        // Return the selected endpoint
+        min_open_cell_id
+    }
+
+    // Is this puzzle solvable in its current state?
+    // 1 if yes
+    // <1 if no, corresponding to the reason (for statistics)
+    pub fn is_solvable(&self) -> i16 {
+        match self.find_unsolvable() {
+            None => SOLVABLE,
+            Some(reason) => reason.code(),
+        }
+    }
+
+    // Check if the puzzle can't be solved in its current state, and if so, why
+    // Returns None if no problems were found (which doesn't necessarily mean it can be solved)
+    pub fn find_unsolvable(&self) -> Option<Unsolvable> {
+        if self.num_possible_children() == 0 {
+            let endpoint_id = self.get_endpoint_to_extend();
+            if endpoint_id.index == NON_EXISTENT_CELL_ID {
+                return Some(Unsolvable::NoChildren(None));
+            }
+            return Some(Unsolvable::NoChildren(Some(endpoint_id)));
+        }
+
+        if !self.is_hex {
+            // I think these checks need tweaking for hex puzzles. Not sure though
+            for i in 0..self.num_cells() {
+                let cell = self.get_cell(CellId { index: i }).unwrap();
+
+                // Detect dead ends - an empty cell connected only to one other empty cell and no endpoints
+                // Any flow going into this would have no endpoints to connect to and no way to get out,
+                // So it is impossible to solve
+                if !cell.is_occupied() && cell.num_open_neighbors(self) == 1 {
+                    let mut has_endpoint_neighbor = false;
+                    for n_index in cell.neighbors.iter() {
+                        if self.get_cell(*n_index).unwrap().is_endpoint {
+                            has_endpoint_neighbor = true;
+                            break;
+                        }
+                    }
+
+                    if !has_endpoint_neighbor {
+                        return Some(Unsolvable::DeadEnd(CellId { index: i }));
+                    }
+                }
+
+                // Check for "pools" - when a flow doubles back on itself - these are illegal and generally pesky
+                if cell.is_occupied() && cell.num_open_neighbors(self) <= 1 {
+                    let mut same_flow_count = 0;
+                    for n_index in cell.neighbors.iter() {
+                        let neighbor = self.get_cell(*n_index).unwrap();
+                        if neighbor.is_occupied()
+                            && neighbor.flow_id.unwrap().index == cell.flow_id.unwrap().index
+                        {
+                            same_flow_count += 1;
+                        }
+                    }
+                    if same_flow_count > 2 {
+                        return Some(Unsolvable::Pool(CellId { index: i }));
+                    }
+                }
+            }
+        }
+
+        let connected_component_sets = self.get_regions();
+
+        // Analyze the regions - Part 1: check if each region has at least one pair of
+        // endpoints neighboring it (both endpoints neighbor at least one member of the region)
+        'region_loop_1: for region in connected_component_sets.iter() {
+            'flow_loop_1: for f in 0..self.num_flows() {
+                let flow = self.get_flow(FlowId { index: f as usize }).unwrap();
+                if flow.is_complete(self) {
+                    continue;
+                }
+
+                let endpoint_0 = self.get_cell(flow.get_endpoint(0)).unwrap();
+                let endpoint_1 = self.get_cell(flow.get_endpoint(1)).unwrap();
+
+                let mut res = false;
+
+                for n in endpoint_0.neighbors.iter() {
+                    if region.contains(&n.index) {
+                        res = true;
+                    }
+                }
+                if !res {
+                    continue 'flow_loop_1; // Region doesn't neighbor the first endpoint. Continue to next flow
+                }
+
+                for n in endpoint_1.neighbors.iter() {
+                    if region.contains(&n.index) {
+                        continue 'region_loop_1; // If we hit this point, the region contains both endpoints. Check next region
+                    }
+                }
+            }
+            return Some(Unsolvable::Region(
+                region
+                    .iter()
+                    .map(|index| CellId { index: *index })
+                    .collect(),
+            ));
+        }
+
+        // Analyze each flow: both endpoints must have a neighboring region in common, otherwise connecting them is impossible
+        'flow_loop_2: for f in 0..self.num_flows() {
+            let flow = self.get_flow(FlowId { index: f as usize }).unwrap();
+            if flow.is_complete(self) {
+                continue;
+            }
+            let endpoint_0 = self.get_cell(flow.get_endpoint(0)).unwrap();
+            let endpoint_1 = self.get_cell(flow.get_endpoint(1)).unwrap();
+
+            'region_loop_2: for region in connected_component_sets.iter() {
+                let mut res = false;
+
+                for n in endpoint_0.neighbors.iter() {
+                    if region.contains(&n.index) {
+                        res = true;
+                    }
+                }
+                if !res {
+                    continue 'region_loop_2; // This region doesn't neighbor the first endpoint. Check the next region
+                }
+
+                for n in endpoint_1.neighbors.iter() {
+                    if region.contains(&n.index) {
+                        continue 'flow_loop_2; // This region contains both endpoints. Check the next flow
+                    }
+                }
+            }
+            return Some(Unsolvable::PathBlocked(FlowId { index: f as usize }));
+        }
+        None
+    }
+
+    // Split the empty cells of the puzzle into regions, where every cell in a region can be reached from
+    // the others without going through an occupied cell
+    pub fn get_regions(&self) -> Vec<Vec<usize>> {
+        // Idea for connected component analysis gotten from: https://mzucker.github.io/2016/08/28/flow-solver.html
+        // Algorithm for connected component analysis is from wikipedia: https://en.wikipedia.org/wiki/Connected-component_labeling
+        let mut preliminary_connected_component_sets: Vec<Vec<usize>> = vec![];
+        let mut visited: Vec<usize> = vec![];
+
+        // Create the connected components: basically each vector in the vector ccs contains the IDs of cells connected to each other
+        for i in 0..self.num_cells() {
+            if visited.contains(&i) {
+                continue;
+            }
+
+            let mut queue: Vec<usize> = vec![i];
+            // Loop through the queue of cells to visit
+            while let Some(curr_id) = queue.pop() {
+                if visited.contains(&curr_id) {
+                    continue;
+                }
+
+                visited.push(curr_id);
+
+                let curr_cell = self.get_cell(CellId { index: curr_id }).unwrap();
+
+                // Occupied cells don't count as being in regions
+                if curr_cell.is_occupied() {
+                    continue;
+                }
+
+                // If a cell isn't added to an existing set, create a new region for it afterwards
+                let mut added_to_set = false;
+                // Check each of the neighbors
+                for neighbor_id in curr_cell.neighbors.iter() {
+                    // First, check if the neighbor is already in a region
+                    // If it is, add this cell to that region
+                    for ccs in preliminary_connected_component_sets.iter_mut() {
+                        if ccs.contains(&neighbor_id.index) {
+                            ccs.push(curr_id);
+                            added_to_set = true;
+                        }
+                    }
+
+                    // Also, if the neighbor hasn't already been visited and isn't in the queue, add it to the queue to be considered
+                    if !visited.contains(&neighbor_id.index) && !queue.contains(&neighbor_id.index)
+                    {
+                        queue.push(neighbor_id.index);
+                    }
+                }
+
+                if !added_to_set {
+                    // If needed, create the region
+                    preliminary_connected_component_sets.push(vec![curr_id]);
+                }
+            }
+        }
+
+        // Create the final regions, as there is a possibility that regions were not fully connected in the prior loop
+        let mut connected_component_sets: Vec<Vec<usize>> = vec![];
+        for i in 0..preliminary_connected_component_sets.len() {
+            let set_i = preliminary_connected_component_sets.get(i).unwrap();
+            let mut added_to_final = false;
+            'j_loop: for set_j in connected_component_sets.iter_mut() {
+                for id_j in set_j.iter() {
+                    let cell_j = self.get_cell(CellId { index: *id_j }).unwrap();
+
+                    for n in cell_j.neighbors.iter() {
+                        if set_i.contains(&n.index) {
+                            set_j.extend(set_i);
+                            added_to_final = true;
+                            break 'j_loop;
+                        }
+                    }
+                }
+            }
+
+            if !added_to_final {
+                connected_component_sets.push(set_i.clone());
+            }
+        }
+
+        connected_component_sets
+    }
+
+    // How many bytes write_state takes for this puzzle
+    pub fn state_size(&self) -> usize {
+        self.cells.len() * 5 + self.flows.len() * 4
+    }
+
+    // Write down just the parts that change while solving (which flow is in each cell, where it came from, and where
+    // each flow's endpoints are), e.g. to keep the state on disk. read_state turns it back into a puzzle
+    // Ids are written in 16 bits, with the biggest meaning None, so this is only for puzzles with fewer cells than that
+    pub fn write_state(&self, bytes: &mut Vec<u8>) {
+        let id = |index: Option<usize>| index.map_or(u16::MAX, |index| index as u16).to_le_bytes();
+        for cell in self.cells.iter() {
+            bytes.extend(id(cell.flow_id.map(|flow_id| flow_id.index)));
+            bytes.extend(id(cell.previous.map(|previous| previous.index)));
+            bytes.push(u8::from(cell.is_endpoint));
+        }
+        for flow in self.flows.iter() {
+            for endpoint in flow.endpoints.iter() {
+                bytes.extend(id(endpoint.map(|endpoint| endpoint.index)));
+            }
+        }
+    }
+
+    // This puzzle, with a state written by write_state (from this same puzzle, solved some of the way) filled in
+    pub fn read_state(&self, bytes: &[u8]) -> Puzzle {
+        let id = |at: usize| {
+            let index = u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+            (index != u16::MAX).then_some(index as usize)
+        };
+        let mut state = self.clone();
+        for (i, cell) in state.cells.iter_mut().enumerate() {
+            cell.flow_id = id(i * 5).map(|index| FlowId { index });
+            cell.previous = id(i * 5 + 2).map(|index| CellId { index });
+            cell.is_endpoint = bytes[i * 5 + 4] == 1;
+        }
+        let flows_start = self.cells.len() * 5;
+        for (i, flow) in state.flows.iter_mut().enumerate() {
+            for (endpoint, id_at) in [0, 2].into_iter().enumerate() {
+                flow.endpoints[endpoint] =
+                    id(flows_start + i * 4 + id_at).map(|index| CellId { index });
+            }
+        }
+        state
+    }
+
+    // A lower bound on the moves left to connect every flow: for each incomplete flow, the fewest empty cells
+    // a path between its endpoints would have to fill. Paths can't share cells, so the real number can only be higher
+    // Returns None if some flow's endpoints can't reach each other at all
+    pub fn min_moves_left(&self) -> Option<u64> {
+        let mut total = 0;
+        for (index, flow) in self.flows.iter().enumerate() {
+            if !flow.is_complete(self) {
+                total += self.cells_between(
+                    FlowId { index },
+                    flow.get_endpoint(0),
+                    flow.get_endpoint(1),
+                )?;
+            }
+        }
+        Some(total)
+    }
+
+    // The fewest empty cells the flow would have to fill to get from one cell to the other (breadth first)
+    pub fn cells_between(&self, flow_id: FlowId, from: CellId, to: CellId) -> Option<u64> {
+        let mut seen = vec![false; self.num_cells()];
+        let mut queue: VecDeque<(CellId, u64)> = VecDeque::new();
+        queue.push_back((from, 0));
+        seen[from.index] = true;
+        while let Some((id, filled)) = queue.pop_front() {
+            let cell = self.get_cell(id).unwrap();
+            if cell.is_neighbor(&to) {
+                return Some(filled);
+            }
+            for n in cell.neighbors.iter() {
+                if !seen[n.index] && self.get_cell(*n).unwrap().is_open_to(flow_id) {
+                    seen[n.index] = true;
+                    queue.push_back((*n, filled + 1));
+                }
+            }
+        }
+        None
+    }
+
+    // Magic numbers galore! (once upon a time)
+    // Anyway, return the score of a board
+    pub fn h(&self) -> u64 {
+        // Modified from https://mzucker.github.io/2016/08/28/flow-solver.html (incorporates parts of g() and h() into one)
+        // Starts from the number of cells (rather than the 1000 it used to), so it can't go below zero on big boards
+        (self.num_cells() + MAX_NEIGHBORS) as u64 - self.num_open_cells() + self.num_complete() * 2
+            - self.num_possible_children()
+    }
+}
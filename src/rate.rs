@@ -0,0 +1,188 @@
+use crate::hint::Hint;
+use crate::puzzle::{Puzzle, SOLVABLE};
+use crate::solver::{solve, Outcome, SolverOptions, Stats};
+
+// The grades, from easiest to hardest, and the score each one starts at
+pub const GRADES: [(&str, u32); 5] = [
+    ("easy", 0),
+    ("medium", 20),
+    ("hard", 40),
+    ("expert", 60),
+    ("extreme", 80),
+];
+
+// How many states per open cell the search needs before a puzzle scores full marks for searching
+// (a puzzle solved by walking straight to the answer needs one state per cell)
+const MAX_STATES_PER_CELL: f64 = 10_000.0;
+// How much of the score comes from the cells that can't be worked out without searching. The rest comes from how
+// hard the search had to work
+const DEDUCTION_WEIGHT: f64 = 0.3;
+
+// How difficult a puzzle is, and what went into deciding that
+#[derive(Debug, Clone)]
+pub struct Rating {
+    // The cells left to fill at the start
+    pub open_cells: u64,
+    // The cells filled by moves with only one option (an endpoint with one way to go, or a cell only one
+    // endpoint can reach)
+    pub forced_cells: u64,
+    // The cells filled by moves where every other option fails the solver's checks
+    pub pruned_cells: u64,
+    // How the search from the start went
+    pub outcome: Outcome,
+    pub stats: Stats,
+}
+
+impl Rating {
+    // The cells that couldn't be worked out without searching
+    pub fn searched_cells(&self) -> u64 {
+        self.open_cells - self.forced_cells - self.pruned_cells
+    }
+
+    // How many states the search looked at for every cell it had to fill
+    pub fn states_per_cell(&self) -> f64 {
+        self.stats.states_visited as f64 / self.open_cells.max(1) as f64
+    }
+
+    // The difficulty, from 0 (every cell can be worked out without searching) to 100
+    pub fn score(&self) -> u32 {
+        let searched = self.searched_cells() as f64 / self.open_cells.max(1) as f64;
+        let effort =
+            (self.states_per_cell().max(1.0).log10() / MAX_STATES_PER_CELL.log10()).min(1.0);
+        (100.0 * (DEDUCTION_WEIGHT * searched + (1.0 - DEDUCTION_WEIGHT) * effort)).round() as u32
+    }
+
+    pub fn grade(&self) -> &'static str {
+        let score = self.score();
+        GRADES
+            .iter()
+            .rev()
+            .find(|(_, from)| score >= *from)
+            .map_or(GRADES[0].0, |(grade, _)| grade)
+    }
+
+    // Describe the rating, along with the numbers behind it
+    pub fn describe(&self) -> String {
+        let percent = |cells: u64| 100.0 * cells as f64 / self.open_cells.max(1) as f64;
+        let mut description = match self.outcome {
+            Outcome::Solved(_) => format!("Difficulty: {} ({}/100)\n\n", self.grade(), self.score()),
+            // The search gave up, so it would have taken at least this much work
+            _ => format!(
+                "Difficulty: at least {} ({}/100, the search gave up before finding a solution)\n\n",
+                self.grade(),
+                self.score()
+            ),
+        };
+        description.push_str(&format!("Open cells:           {}\n", self.open_cells));
+        description.push_str(&format!(
+            "Forced moves:         {} ({:.1}%)\n",
+            self.forced_cells,
+            percent(self.forced_cells)
+        ));
+        description.push_str(&format!(
+            "Found by pruning:     {} ({:.1}%)\n",
+            self.pruned_cells,
+            percent(self.pruned_cells)
+        ));
+        description.push_str(&format!(
+            "Left to the search:   {} ({:.1}%)\n",
+            self.searched_cells(),
+            percent(self.searched_cells())
+        ));
+        description.push_str(&format!(
+            "States visited:       {}\n",
+            self.stats.states_visited
+        ));
+        description.push_str(&format!(
+            "States per cell:      {:.1}\n",
+            self.states_per_cell()
+        ));
+        description.push_str(&format!(
+            "States discarded:     {:.1}%\n",
+            self.stats.discard_ratio() * 100.0
+        ));
+        description.push_str(&format!(
+            "Biggest frontier:     {}\n",
+            self.stats.max_frontier
+        ));
+        description.push_str(&format!(
+            "Time:                 {:.3} seconds\n",
+            self.stats.elapsed.as_secs_f64()
+        ));
+        description
+    }
+}
+
+// Rate how difficult the puzzle is
+// First, as much of the puzzle as possible is filled in without searching: moves with only one option, then moves
+// whose every alternative fails the solver's checks. Then the whole puzzle is solved from the start, to see how
+// much branching and backtracking the search needs
+// Returns None if the puzzle can't be solved
+pub fn rate(puzzle: &Puzzle, options: &SolverOptions) -> Option<Rating> {
+    let open_cells = puzzle.num_open_cells();
+    let (forced_cells, pruned_cells) = deduce(puzzle);
+
+    let result = solve(puzzle.clone(), options, &mut ());
+    if let Outcome::Unsolvable = result.outcome {
+        return None;
+    }
+    Some(Rating {
+        open_cells,
+        forced_cells,
+        pruned_cells,
+        outcome: result.outcome,
+        stats: result.stats,
+    })
+}
+
+// Fill in as much of the puzzle as can be worked out without searching
+// Returns how many cells were filled by forced moves, and how many by pruning the alternatives
+fn deduce(puzzle: &Puzzle) -> (u64, u64) {
+    let mut state = puzzle.clone();
+    let (mut forced, mut pruned) = (0, 0);
+    while !state.is_complete() {
+        if let Some((from, to)) = state.get_forced_move() {
+            Hint::Move {
+                from,
+                to,
+                forced: true,
+            }
+            .apply(&mut state);
+            forced += 1;
+        } else if let Some(next) = pruned_move(&state) {
+            state = next;
+            pruned += 1;
+        } else {
+            break;
+        }
+    }
+    (forced, pruned)
+}
+
+// Look for an endpoint where only one of its moves leaves a board that passes the solver's checks
+// Returns the board after making that move
+fn pruned_move(state: &Puzzle) -> Option<Puzzle> {
+    for from in state.get_endpoints_for_incomplete_flows() {
+        let cell = state.get_cell(from).unwrap();
+        let flow_id = cell.flow_id.unwrap();
+        let mut survivors = cell
+            .neighbors
+            .iter()
+            .filter(|to| state.get_cell(**to).unwrap().is_open_to(flow_id))
+            .map(|to| {
+                let mut child = state.clone();
+                Hint::Move {
+                    from,
+                    to: *to,
+                    forced: true,
+                }
+                .apply(&mut child);
+                child
+            })
+            .filter(|child| child.is_complete() || child.is_solvable() == SOLVABLE);
+        if let (Some(child), None) = (survivors.next(), survivors.next()) {
+            return Some(child);
+        }
+    }
+    None
+}
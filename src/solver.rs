@@ -0,0 +1,887 @@
+use crate::exact::exact_cover;
+use crate::observer::{ConsoleObserver, Control, Observer, Verbosity};
+use crate::puzzle::{
+    Puzzle, MAX_NEIGHBORS, NON_EXISTENT_CELL_ID, SOLVABLE, UNSOLVABLE_DEAD_ENDS,
+    UNSOLVABLE_NO_CHILDREN, UNSOLVABLE_PATH_BLOCKED, UNSOLVABLE_POOLS, UNSOLVABLE_REGION,
+};
+use crate::spill::DiskFrontier;
+use std::cmp::{max, Ordering};
+use std::collections::BinaryHeap;
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// The most states a recording keeps. Past this, every other state is dropped and states are recorded half as often
+pub const MAX_RECORDED_STATES: usize = 500;
+
+// A* scores are kept in whole numbers, so weighted scores are multiplied by this first to keep some of the fraction
+const A_STAR_SCALE: f64 = 1000.0;
+
+// The most whole paths to make children of at once, before going back to moving a cell at a time
+const MAX_PATHS: usize = 1000;
+
+// How many states the beam search keeps at each depth, unless told otherwise
+pub const DEFAULT_BEAM_WIDTH: usize = 1000;
+
+// A sample of the states visited while solving a puzzle, e.g. for drawing the search afterwards
+#[derive(Debug, Clone)]
+pub struct Recording {
+    // Record every this many states
+    every: u64,
+    pub states: Vec<Puzzle>,
+}
+
+impl Recording {
+    pub fn new(every: u64) -> Recording {
+        Recording {
+            every: max(every, 1),
+            states: vec![],
+        }
+    }
+
+    // Keep the state if it's one of the ones to sample
+    pub fn record(&mut self, states_visited: u64, state: &Puzzle) {
+        if !(states_visited - 1).is_multiple_of(self.every) {
+            return;
+        }
+        if self.states.len() == MAX_RECORDED_STATES {
+            let mut keep = false;
+            self.states.retain(|_| {
+                keep = !keep;
+                keep
+            });
+            self.every *= 2;
+            if !(states_visited - 1).is_multiple_of(self.every) {
+                return;
+            }
+        }
+        self.states.push(state.clone());
+    }
+}
+
+// Recordings are made by watching the search
+impl Observer for Recording {
+    fn state_expanded(&mut self, state: &Puzzle, stats: &Stats) -> Control {
+        self.record(stats.states_visited, state);
+        Control::Continue
+    }
+}
+
+// The search algorithms the solver can use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    // Always expand the best looking state found so far
+    Greedy,
+    // A*: expand the state with the fewest moves made plus (a lower bound on the) moves left, weighted by
+    // SolverOptions::weight. Ignores the heuristic
+    AStar,
+    // Go one move deeper at a time, keeping only the best looking SolverOptions::beam_width states at each depth
+    // Quick and light on memory, but it can throw away the way to the solution
+    Beam,
+    // IDA*: depth first, as deep as the moves made plus (a lower bound on the) moves left allow, starting over with a
+    // higher limit each time that isn't enough. Needs very little memory, but looks at the same states again and again
+    IdaStar,
+    // List every route each flow could take, then pick one per flow so every cell is filled exactly once (Algorithm X).
+    // Quick on small and medium boards, but gives up if there are too many routes. Ignores the heuristic and moves
+    ExactCover,
+}
+
+impl Backend {
+    pub const NAMES: [&'static str; 5] = ["greedy", "astar", "beam", "idastar", "exact"];
+
+    pub fn from_name(name: &str) -> Option<Backend> {
+        match name {
+            "greedy" => Some(Backend::Greedy),
+            "astar" => Some(Backend::AStar),
+            "beam" => Some(Backend::Beam),
+            "idastar" => Some(Backend::IdaStar),
+            "exact" => Some(Backend::ExactCover),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Greedy => "greedy",
+            Backend::AStar => "astar",
+            Backend::Beam => "beam",
+            Backend::IdaStar => "idastar",
+            Backend::ExactCover => "exact",
+        }
+    }
+}
+
+// The ways the solver can score states, to decide which to look at next. Higher scores are looked at first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    // Puzzle::h - few open cells, many complete flows and few choices for the next move
+    Default,
+    // Fewest open cells first, i.e. the deepest states
+    OpenCells,
+    // Fewest choices for the next move first, then fewest open cells
+    Constrained,
+}
+
+impl Heuristic {
+    pub const NAMES: [&'static str; 3] = ["default", "open-cells", "constrained"];
+
+    pub fn from_name(name: &str) -> Option<Heuristic> {
+        match name {
+            "default" => Some(Heuristic::Default),
+            "open-cells" => Some(Heuristic::OpenCells),
+            "constrained" => Some(Heuristic::Constrained),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Heuristic::Default => "default",
+            Heuristic::OpenCells => "open-cells",
+            Heuristic::Constrained => "constrained",
+        }
+    }
+
+    pub fn score(&self, puzzle: &Puzzle) -> u64 {
+        match self {
+            Heuristic::Default => puzzle.h(),
+            Heuristic::OpenCells => u64::MAX - puzzle.num_open_cells(),
+            Heuristic::Constrained => {
+                let choices = MAX_NEIGHBORS as u64 - puzzle.num_possible_children();
+                (choices << 32) + (u32::MAX as u64 - puzzle.num_open_cells())
+            }
+        }
+    }
+}
+
+// The ways the solver can make the children of a state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Moves {
+    // Move the endpoint with the fewest moves by one cell (Puzzle::get_endpoint_to_extend)
+    Endpoint,
+    // Move the flow with the fewest moves between its two ends by one cell, from whichever end has fewer
+    FlowEnds,
+    // Draw in the whole path of that same flow, every way it can go. If there are too many ways, it's moved one
+    // cell at a time instead
+    WholePaths,
+}
+
+impl Moves {
+    pub const NAMES: [&'static str; 3] = ["endpoint", "flow-ends", "whole-paths"];
+
+    pub fn from_name(name: &str) -> Option<Moves> {
+        match name {
+            "endpoint" => Some(Moves::Endpoint),
+            "flow-ends" => Some(Moves::FlowEnds),
+            "whole-paths" => Some(Moves::WholePaths),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Moves::Endpoint => "endpoint",
+            Moves::FlowEnds => "flow-ends",
+            Moves::WholePaths => "whole-paths",
+        }
+    }
+
+    pub fn children(&self, puzzle: &Puzzle) -> Vec<Puzzle> {
+        if let Moves::Endpoint = self {
+            return puzzle.create_children();
+        }
+        let endpoint_id = puzzle.get_flow_end_to_extend();
+        if endpoint_id.index == NON_EXISTENT_CELL_ID {
+            return vec![];
+        }
+        match self {
+            Moves::WholePaths => puzzle
+                .create_path_children(endpoint_id, MAX_PATHS)
+                .unwrap_or_else(|| puzzle.create_children_from(endpoint_id)),
+            _ => puzzle.create_children_from(endpoint_id),
+        }
+    }
+}
+
+// A way to stop a search from somewhere else, e.g. another thread or a cancel button
+// Clones all share the same flag, so keep one and hand a clone to the solver
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    // Ask the search to stop. It notices before looking at the next state
+    pub fn cancel(&self) {
+        self.cancelled.store(true, AtomicOrdering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(AtomicOrdering::Relaxed)
+    }
+}
+
+// Everything that can be changed about how a puzzle is solved
+#[derive(Debug, Clone)]
+pub struct SolverOptions {
+    pub backend: Backend,
+    pub heuristic: Heuristic,
+    pub moves: Moves,
+    // Give up after this long
+    pub time_limit: Option<Duration>,
+    // Give up after looking at this many states
+    pub state_limit: Option<u64>,
+    // Give up when this is cancelled
+    pub cancel: Option<CancelToken>,
+    // How much A* trusts its estimate of the moves left, compared to the moves made. 1 is plain A*, and anything
+    // higher is weighted A*, which heads for the answer quicker but may look at more states on the way
+    pub weight: f64,
+    // How many states the beam search keeps at each depth
+    pub beam_width: usize,
+    // For greedy and A*: once the frontier has more than this many states in memory, write the worse half to disk
+    // (in spill_directory) and read them back when they're needed. None keeps every state in memory
+    pub spill_after: Option<usize>,
+    // Where to make the directory the spilled states go in. The system's temporary directory if None
+    pub spill_directory: Option<PathBuf>,
+}
+
+impl SolverOptions {
+    // Has the search gone on for longer, or looked at more states, than it's allowed to?
+    pub fn out_of_time_or_states(&self, start: Instant, states_visited: u64) -> bool {
+        self.time_limit
+            .is_some_and(|limit| start.elapsed() >= limit)
+            || self
+                .state_limit
+                .is_some_and(|limit| states_visited >= limit)
+    }
+
+    // How good a state looks to the backend. Higher scores are looked at first
+    // A* scores by the moves made so far (from the given number of open cells) plus the weighted moves left, lowest
+    // first, then by the moves made, most first
+    fn score(&self, puzzle: &Puzzle, start_open: u64) -> u64 {
+        match self.backend {
+            Backend::Greedy | Backend::Beam | Backend::IdaStar | Backend::ExactCover => {
+                self.heuristic.score(puzzle)
+            }
+            Backend::AStar => match puzzle.min_moves_left() {
+                // Can't be finished, so it goes to the back
+                None => 0,
+                Some(left) => {
+                    let made = start_open - puzzle.num_open_cells();
+                    let f = (made as f64 + self.weight * left as f64) * A_STAR_SCALE;
+                    let f = (f.round() as u64).min(u32::MAX as u64);
+                    ((u32::MAX as u64 - f) << 32) + made
+                }
+            },
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.is_cancelled())
+    }
+}
+
+impl Default for SolverOptions {
+    fn default() -> SolverOptions {
+        SolverOptions {
+            backend: Backend::Greedy,
+            heuristic: Heuristic::Default,
+            moves: Moves::Endpoint,
+            time_limit: None,
+            state_limit: None,
+            cancel: None,
+            weight: 1.0,
+            beam_width: DEFAULT_BEAM_WIDTH,
+            spill_after: None,
+            spill_directory: None,
+        }
+    }
+}
+
+// How a search went
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub states_visited: u64,
+    pub states_created: u64,
+    pub max_frontier: usize,
+    // The size of the frontier when the search stopped
+    pub frontier: usize,
+    pub max_flows_completed: u64,
+    pub children_discarded: u64,
+    pub discarded_no_children: u64,
+    pub discarded_dead_end: u64,
+    pub discarded_pools: u64,
+    pub discarded_blocked: u64,
+    pub discarded_cc: u64,
+    pub elapsed: Duration,
+    // For IDA*, the cost limit of each pass, and how many states were visited in it
+    pub thresholds: Vec<(u64, u64)>,
+    // How many states were written to disk (see SolverOptions::spill_after), and how many are there now
+    pub states_spilled: u64,
+    pub frontier_on_disk: usize,
+    // Why states couldn't be written to (or read back from) disk, if they couldn't
+    // States stay in memory after a failed write, but after a failed read the search gives up (Outcome::Abandoned)
+    pub spill_error: Option<String>,
+}
+
+impl Stats {
+    // The fraction of the states created that were thrown away
+    pub fn discard_ratio(&self) -> f64 {
+        self.children_discarded as f64 / self.states_created as f64
+    }
+
+    // The fraction of the discarded states that were thrown away for the given reason
+    pub fn discarded_for(&self, count: u64) -> f64 {
+        if self.children_discarded == 0 {
+            return 0.0;
+        }
+        count as f64 / self.children_discarded as f64
+    }
+
+    pub fn print(&self) {
+        println!("---STATS AT {}---", self.states_visited);
+        println!("States visited: {}\nMax Frontier Size: {}\nChildren Discarded: {}\nPercent Discarded: {}\nCurrent Frontier: {}\nStates created: {}",
+                 self.states_visited, self.max_frontier, self.children_discarded, self.discard_ratio(), self.frontier, self.states_created);
+        println!("Max Flows Complete: {}", self.max_flows_completed);
+        println!("Discard Stats:\n\tNum children: {}\n\tDead end: {}\n\tPools: {}\n\tBlocked Flow: {}\n\tCC Failed: {}\n",
+                 self.discarded_for(self.discarded_no_children),
+                 self.discarded_for(self.discarded_dead_end),
+                 self.discarded_for(self.discarded_pools),
+                 self.discarded_for(self.discarded_blocked),
+                 self.discarded_for(self.discarded_cc));
+        if self.states_spilled > 0 || self.spill_error.is_some() {
+            println!(
+                "States spilled to disk: {}\nStill on disk: {}",
+                self.states_spilled, self.frontier_on_disk
+            );
+            if let Some(error) = &self.spill_error {
+                println!("Couldn't spill to disk: {}", error);
+            }
+            println!();
+        }
+        if !self.thresholds.is_empty() {
+            println!("Iterations: {}", self.thresholds.len());
+            for (threshold, states) in self.thresholds.iter() {
+                println!("\tThreshold {}: {} states", threshold, states);
+            }
+            println!();
+        }
+    }
+}
+
+// How the search ended
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Solved(Puzzle),
+    // Every possibility was tried
+    Unsolvable,
+    // One of the limits was hit before an answer was found
+    TimedOut,
+    // The search was cancelled (or an observer asked to stop). Comes with the state with the most cells filled in so far
+    Cancelled(Puzzle),
+    // The search gave up without trying everything (the beam search threw states away, or there were too many routes
+    // for the exact cover), so there might still be a solution. Comes with the state with the most cells filled in
+    Abandoned(Puzzle),
+}
+
+#[derive(Debug, Clone)]
+pub struct SolveResult {
+    pub outcome: Outcome,
+    pub stats: Stats,
+}
+
+impl SolveResult {
+    pub fn solution(&self) -> Option<&Puzzle> {
+        match &self.outcome {
+            Outcome::Solved(solution) => Some(solution),
+            _ => None,
+        }
+    }
+}
+
+// A state on the frontier, along with its score
+struct Scored {
+    score: u64,
+    puzzle: Puzzle,
+}
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Scored) -> bool {
+        self.score == other.score
+    }
+}
+
+impl Eq for Scored {}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Scored) -> Ordering {
+        self.score.cmp(&other.score)
+    }
+}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Scored) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Not really sure if this is greedy best first any more, but I'm not changing the name now
+// Solve the given PuzzleState, if possible. If not, return None
+pub fn greedy_best_first(puzzle: Puzzle) -> Option<Puzzle> {
+    let mut observer = ConsoleObserver::new(Verbosity::Normal);
+    match solve(puzzle, &SolverOptions::default(), &mut observer).outcome {
+        Outcome::Solved(solution) => Some(solution),
+        _ => None,
+    }
+}
+
+// Solve the puzzle with the given options, letting the observer know how it's going along the way
+pub fn solve(puzzle: Puzzle, options: &SolverOptions, observer: &mut dyn Observer) -> SolveResult {
+    let start = Instant::now();
+    let mut stats = Stats {
+        states_created: 1,
+        ..Stats::default()
+    };
+    let (outcome, last_state) = match options.backend {
+        Backend::Greedy | Backend::AStar => {
+            best_first(puzzle, options, observer, &mut stats, start)
+        }
+        Backend::Beam => beam(puzzle, options, observer, &mut stats, start),
+        Backend::IdaStar => ida_star(puzzle, options, observer, &mut stats, start),
+        Backend::ExactCover => exact_cover(puzzle, options, observer, &mut stats, start),
+    };
+
+    stats.elapsed = start.elapsed();
+    if let Outcome::Solved(solution) = &outcome {
+        observer.solution_found(solution, &stats);
+    }
+    observer.search_ended(&outcome, last_state.as_ref(), &stats);
+    SolveResult { outcome, stats }
+}
+
+// Search the states in order of the backend's score, best first
+// Returns how the search ended, along with the last state expanded
+fn best_first(
+    puzzle: Puzzle,
+    options: &SolverOptions,
+    observer: &mut dyn Observer,
+    stats: &mut Stats,
+    start: Instant,
+) -> (Outcome, Option<Puzzle>) {
+    // Nothing to do if the puzzle came already solved
+    if puzzle.is_complete() {
+        stats.max_flows_completed = puzzle.num_complete();
+        return (Outcome::Solved(puzzle), None);
+    }
+
+    // The state with the most cells filled in, to hand back if the search is cancelled
+    let mut best = puzzle.clone();
+    let mut best_depth = 0;
+
+    // Where the worse states go once there are too many to keep in memory
+    let mut disk = None;
+    if options.spill_after.is_some() {
+        let directory = options
+            .spill_directory
+            .clone()
+            .unwrap_or_else(env::temp_dir);
+        match DiskFrontier::new(&directory, &puzzle) {
+            Ok(created) => disk = Some(created),
+            Err(error) => stats.spill_error = Some(error.to_string()),
+        }
+    }
+
+    let start_open = puzzle.num_open_cells();
+    let mut frontier: BinaryHeap<Scored> = BinaryHeap::new(); // Puzzles to consider
+    frontier.push(Scored {
+        score: options.score(&puzzle, start_open),
+        puzzle,
+    });
+
+    let mut latest: Option<Puzzle> = None;
+
+    while !frontier.is_empty() || disk.as_ref().is_some_and(|disk| !disk.is_empty()) {
+        stats.frontier_on_disk = disk.as_ref().map_or(0, |disk| disk.len());
+        stats.frontier = frontier.len() + stats.frontier_on_disk;
+        if options.out_of_time_or_states(start, stats.states_visited) {
+            return (Outcome::TimedOut, latest);
+        }
+        if options.is_cancelled() {
+            return (Outcome::Cancelled(best), latest);
+        }
+
+        stats.states_visited += 1;
+        stats.max_frontier = max(stats.max_frontier, stats.frontier);
+
+        let curr_state = match pop_best(&mut frontier, &mut disk) {
+            Ok(state) => state,
+            Err(error) => {
+                stats.spill_error = Some(error.to_string());
+                return (Outcome::Abandoned(best), latest);
+            }
+        };
+        stats.max_flows_completed = max(stats.max_flows_completed, curr_state.num_complete());
+        if observer.state_expanded(&curr_state, stats) == Control::Stop {
+            return (Outcome::Cancelled(best), Some(curr_state));
+        }
+        let depth = curr_state.num_cells() - curr_state.num_open_cells() as usize;
+        if depth > best_depth {
+            best_depth = depth;
+            best = curr_state.clone();
+            observer.new_best_depth(&curr_state, depth, stats);
+        }
+
+        let mut children = options.moves.children(&curr_state);
+        stats.states_created += children.len() as u64;
+
+        // Evaluate each child
+        while let Some(child) = children.pop() {
+            // Yay! We're done!
+            if child.is_complete() {
+                stats.frontier_on_disk = disk.as_ref().map_or(0, |disk| disk.len());
+                stats.frontier = frontier.len() + stats.frontier_on_disk;
+                return (Outcome::Solved(child), Some(curr_state));
+            }
+            // Determine if child is solvable. If solvable, add it to the list to consider
+            match child.find_unsolvable() {
+                None => frontier.push(Scored {
+                    score: options.score(&child, start_open),
+                    puzzle: child,
+                }),
+                Some(reason) => {
+                    // Otherwise, update some stats and then forget about the child
+                    count_discarded(stats, reason.code());
+                    observer.child_pruned(&child, &reason, stats);
+                }
+            }
+        }
+
+        if let (Some(limit), Some(on_disk)) = (options.spill_after, disk.as_mut()) {
+            // After a failed write, don't keep trying (and failing) every time, but do still read back what's there
+            if frontier.len() > limit && stats.spill_error.is_none() {
+                spill_worse_half(&mut frontier, on_disk, stats);
+            }
+        }
+
+        latest = Some(curr_state);
+    }
+
+    // Never want to get here - if we did, the solver failed
+    stats.frontier = 0;
+    (Outcome::Unsolvable, latest)
+}
+
+// Take the best state off the frontier, whether it's in memory or on disk
+fn pop_best(
+    frontier: &mut BinaryHeap<Scored>,
+    disk: &mut Option<DiskFrontier>,
+) -> io::Result<Puzzle> {
+    if let Some(disk) = disk {
+        let best_on_disk = disk.peek_score();
+        if best_on_disk.is_some_and(|score| frontier.peek().is_none_or(|best| score > best.score)) {
+            return Ok(disk.pop()?.unwrap().1);
+        }
+    }
+    Ok(frontier.pop().unwrap().puzzle)
+}
+
+// Move the worse half of the frontier to disk. If that doesn't work, it all stays in memory
+fn spill_worse_half(frontier: &mut BinaryHeap<Scored>, disk: &mut DiskFrontier, stats: &mut Stats) {
+    // Sorted worst first
+    let mut worse = std::mem::take(frontier).into_sorted_vec();
+    let better = worse.split_off(worse.len() / 2);
+    *frontier = BinaryHeap::from(better);
+
+    let worse: Vec<(u64, Puzzle)> = worse
+        .into_iter()
+        .rev()
+        .map(|scored| (scored.score, scored.puzzle))
+        .collect();
+    match disk.spill(&worse) {
+        Ok(()) => stats.states_spilled += worse.len() as u64,
+        Err(error) => {
+            stats.spill_error = Some(error.to_string());
+            frontier.extend(
+                worse
+                    .into_iter()
+                    .map(|(score, puzzle)| Scored { score, puzzle }),
+            );
+        }
+    }
+}
+
+// Search one depth at a time, keeping only the best states at each depth
+// Returns how the search ended, along with the last state expanded
+fn beam(
+    puzzle: Puzzle,
+    options: &SolverOptions,
+    observer: &mut dyn Observer,
+    stats: &mut Stats,
+    start: Instant,
+) -> (Outcome, Option<Puzzle>) {
+    if puzzle.is_complete() {
+        stats.max_flows_completed = puzzle.num_complete();
+        return (Outcome::Solved(puzzle), None);
+    }
+
+    let start_open = puzzle.num_open_cells();
+    // The best state from the latest round, to hand back if the search gives up
+    let mut best = puzzle.clone();
+    let mut beam = vec![puzzle];
+    let mut latest: Option<Puzzle> = None;
+    // Whether any states have been thrown away, in which case running out doesn't mean there's no solution
+    let mut narrowed = false;
+
+    while !beam.is_empty() {
+        stats.frontier = beam.len();
+        stats.max_frontier = max(stats.max_frontier, beam.len());
+        let mut next: Vec<Scored> = vec![];
+
+        for curr_state in beam {
+            if options.out_of_time_or_states(start, stats.states_visited) {
+                return (Outcome::TimedOut, latest);
+            }
+            if options.is_cancelled() {
+                return (Outcome::Cancelled(best), latest);
+            }
+
+            stats.states_visited += 1;
+            stats.max_flows_completed = max(stats.max_flows_completed, curr_state.num_complete());
+            if observer.state_expanded(&curr_state, stats) == Control::Stop {
+                return (Outcome::Cancelled(best), Some(curr_state));
+            }
+
+            let children = options.moves.children(&curr_state);
+            stats.states_created += children.len() as u64;
+            for child in children {
+                if child.is_complete() {
+                    stats.frontier = next.len();
+                    return (Outcome::Solved(child), Some(curr_state));
+                }
+                match child.find_unsolvable() {
+                    None => next.push(Scored {
+                        score: options.score(&child, start_open),
+                        puzzle: child,
+                    }),
+                    Some(reason) => {
+                        count_discarded(stats, reason.code());
+                        observer.child_pruned(&child, &reason, stats);
+                    }
+                }
+            }
+            latest = Some(curr_state);
+        }
+
+        // Best first, keeping the order the children were made in for ties
+        next.sort_by(|a, b| b.cmp(a));
+        if next.len() > options.beam_width {
+            next.truncate(options.beam_width);
+            narrowed = true;
+        }
+        beam = next.into_iter().map(|scored| scored.puzzle).collect();
+        if let Some(first) = beam.first() {
+            best = first.clone();
+            let depth = best.num_cells() - best.num_open_cells() as usize;
+            observer.new_best_depth(&best, depth, stats);
+        }
+    }
+
+    stats.frontier = 0;
+    if narrowed {
+        (Outcome::Abandoned(best), latest)
+    } else {
+        (Outcome::Unsolvable, latest)
+    }
+}
+
+// Search depth first, but only as far as states whose cost (moves made plus the fewest moves that could be left)
+// is within the threshold. If that doesn't find a solution, go again with the threshold raised to the lowest cost
+// that was over it
+// Returns how the search ended, along with the last state expanded
+fn ida_star(
+    puzzle: Puzzle,
+    options: &SolverOptions,
+    observer: &mut dyn Observer,
+    stats: &mut Stats,
+    start: Instant,
+) -> (Outcome, Option<Puzzle>) {
+    if puzzle.is_complete() {
+        stats.max_flows_completed = puzzle.num_complete();
+        return (Outcome::Solved(puzzle), None);
+    }
+
+    let start_open = puzzle.num_open_cells();
+    let cost = |state: &Puzzle| {
+        state
+            .min_moves_left()
+            .map(|left| start_open - state.num_open_cells() + left)
+    };
+    let mut threshold = match cost(&puzzle) {
+        Some(threshold) => threshold,
+        None => return (Outcome::Unsolvable, None),
+    };
+
+    let mut best = puzzle.clone();
+    let mut best_depth = 0;
+    let mut latest: Option<Puzzle> = None;
+
+    loop {
+        let visited_before = stats.states_visited;
+        // The lowest cost over the threshold, which is where the next pass goes up to
+        let mut next_threshold: Option<u64> = None;
+        let mut stack = vec![puzzle.clone()];
+
+        while let Some(curr_state) = stack.pop() {
+            stats.frontier = stack.len() + 1;
+            if options.out_of_time_or_states(start, stats.states_visited) {
+                stats
+                    .thresholds
+                    .push((threshold, stats.states_visited - visited_before));
+                return (Outcome::TimedOut, latest);
+            }
+            if options.is_cancelled() {
+                stats
+                    .thresholds
+                    .push((threshold, stats.states_visited - visited_before));
+                return (Outcome::Cancelled(best), latest);
+            }
+
+            stats.states_visited += 1;
+            stats.max_frontier = max(stats.max_frontier, stats.frontier);
+            stats.max_flows_completed = max(stats.max_flows_completed, curr_state.num_complete());
+            if observer.state_expanded(&curr_state, stats) == Control::Stop {
+                stats
+                    .thresholds
+                    .push((threshold, stats.states_visited - visited_before));
+                return (Outcome::Cancelled(best), Some(curr_state));
+            }
+            let depth = curr_state.num_cells() - curr_state.num_open_cells() as usize;
+            if depth > best_depth {
+                best_depth = depth;
+                best = curr_state.clone();
+                observer.new_best_depth(&curr_state, depth, stats);
+            }
+
+            let children = options.moves.children(&curr_state);
+            stats.states_created += children.len() as u64;
+            let mut within: Vec<Scored> = vec![];
+            for child in children {
+                if child.is_complete() {
+                    stats.frontier = stack.len();
+                    stats
+                        .thresholds
+                        .push((threshold, stats.states_visited - visited_before));
+                    return (Outcome::Solved(child), Some(curr_state));
+                }
+                if let Some(reason) = child.find_unsolvable() {
+                    count_discarded(stats, reason.code());
+                    observer.child_pruned(&child, &reason, stats);
+                    continue;
+                }
+                match cost(&child) {
+                    // A flow that can't be connected any more (which is_solvable doesn't always spot)
+                    None => {}
+                    Some(child_cost) if child_cost > threshold => {
+                        next_threshold =
+                            Some(next_threshold.map_or(child_cost, |next| next.min(child_cost)));
+                    }
+                    Some(_) => within.push(Scored {
+                        score: options.score(&child, start_open),
+                        puzzle: child,
+                    }),
+                }
+            }
+            // The best looking child goes on the stack last, so it's looked at first
+            within.sort();
+            stack.extend(within.into_iter().map(|scored| scored.puzzle));
+            latest = Some(curr_state);
+        }
+
+        stats
+            .thresholds
+            .push((threshold, stats.states_visited - visited_before));
+        match next_threshold {
+            // Nothing was cut off, so every possibility has been tried
+            None => {
+                stats.frontier = 0;
+                return (Outcome::Unsolvable, latest);
+            }
+            Some(next) => threshold = next,
+        }
+    }
+}
+
+// Note down why a child was thrown away
+fn count_discarded(stats: &mut Stats, solvable_status: i16) {
+    stats.children_discarded += 1;
+    if solvable_status == UNSOLVABLE_NO_CHILDREN {
+        stats.discarded_no_children += 1;
+    } else if solvable_status == UNSOLVABLE_DEAD_ENDS {
+        stats.discarded_dead_end += 1;
+    } else if solvable_status == UNSOLVABLE_POOLS {
+        stats.discarded_pools += 1;
+    } else if solvable_status == UNSOLVABLE_PATH_BLOCKED {
+        stats.discarded_blocked += 1;
+    } else if solvable_status == UNSOLVABLE_REGION {
+        stats.discarded_cc += 1;
+    }
+}
+
+// How many solutions a puzzle has
+#[derive(Debug, Clone)]
+pub enum SolutionCount {
+    None,
+    // Exactly one, which is given
+    One(Puzzle),
+    // Two or more (the search stops at the second)
+    Several,
+    // One of the limits was hit (or the search was cancelled) before it could tell
+    Unknown,
+}
+
+// Find out whether the puzzle has no solutions, one or several, by searching every possibility
+// The search only finds what the solver can, so a flow is finished as soon as its ends are next to each other
+// (a solution where a flow runs alongside its own end isn't counted)
+// Only the limits in the options are used, since the order the states are looked at in doesn't matter here
+pub fn count_solutions(puzzle: Puzzle, options: &SolverOptions) -> SolutionCount {
+    if puzzle.is_complete() {
+        return SolutionCount::One(puzzle);
+    }
+
+    let start = Instant::now();
+    let mut states_visited = 0;
+    let mut found: Option<Puzzle> = None;
+    // Depth first, so only one branch of the search is kept around at a time
+    let mut stack = vec![puzzle];
+    while let Some(state) = stack.pop() {
+        if options.out_of_time_or_states(start, states_visited) || options.is_cancelled() {
+            return SolutionCount::Unknown;
+        }
+        states_visited += 1;
+
+        for child in state.create_children() {
+            if child.is_complete() {
+                if found.is_some() {
+                    return SolutionCount::Several;
+                }
+                found = Some(child);
+            } else if child.is_solvable() == SOLVABLE {
+                stack.push(child);
+            }
+        }
+    }
+
+    match found {
+        Some(solution) => SolutionCount::One(solution),
+        None => SolutionCount::None,
+    }
+}
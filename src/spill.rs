@@ -0,0 +1,257 @@
+use crate::puzzle::Puzzle;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Once there are this many runs on disk, the smallest are merged into one before the next is written, so there
+// aren't too many files open at once
+pub const MAX_RUNS: usize = 16;
+
+// How many of the smallest runs are merged at a time. Merging only some of them means each state is only rewritten a
+// few times as the runs pile up, rather than every time
+const MERGED_RUNS: usize = MAX_RUNS / 2;
+
+// Tells apart the directories of searches running at the same time in this process (e.g. a batch with --jobs)
+static NEXT_DIRECTORY: AtomicUsize = AtomicUsize::new(0);
+
+// The part of a search's frontier that didn't fit in memory, kept on disk in runs of states sorted best first
+// Only the best state of each run is in memory at once, so taking the best state overall is a merge of the runs
+// Each state is stored as its score and Puzzle::write_state, and read back into a copy of the puzzle being solved
+// The directory (and everything left in it) is deleted when this is dropped
+pub struct DiskFrontier {
+    directory: PathBuf,
+    puzzle: Puzzle,
+    runs: Vec<Run>,
+    // How many runs have been written so far, to name the next one
+    written: usize,
+    len: usize,
+}
+
+// One sorted file of states, along with the best state in it that hasn't been taken yet
+struct Run {
+    path: PathBuf,
+    reader: BufReader<File>,
+    head: Option<(u64, Vec<u8>)>,
+    // Where head starts in the file, and how many states are left in the run (counting head)
+    offset: u64,
+    remaining: usize,
+}
+
+impl Run {
+    // Open the run at the given path, from the state at the given offset, which has that many states left
+    fn open(path: PathBuf, offset: u64, remaining: usize, state_size: usize) -> io::Result<Run> {
+        let mut file = File::open(&path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut run = Run {
+            reader: BufReader::new(file),
+            path,
+            head: None,
+            offset,
+            remaining,
+        };
+        run.read_head(state_size)?;
+        Ok(run)
+    }
+
+    // Another reader of the states left in the run, so they can be read without moving this one along
+    fn reopen(&self, state_size: usize) -> io::Result<Run> {
+        Run::open(self.path.clone(), self.offset, self.remaining, state_size)
+    }
+
+    // Read the state at the current offset into head (or None if there are none left)
+    fn read_head(&mut self, state_size: usize) -> io::Result<()> {
+        if self.remaining == 0 {
+            self.head = None;
+            return Ok(());
+        }
+        let mut score = [0; 8];
+        self.reader.read_exact(&mut score)?;
+        let mut state = vec![0; state_size];
+        self.reader.read_exact(&mut state)?;
+        self.head = Some((u64::from_le_bytes(score), state));
+        Ok(())
+    }
+
+    // Take the head, and read the next state in its place
+    fn advance(&mut self, state_size: usize) -> io::Result<Option<(u64, Vec<u8>)>> {
+        let head = self.head.take();
+        if head.is_some() {
+            self.offset += (8 + state_size) as u64;
+            self.remaining -= 1;
+            self.read_head(state_size)?;
+        }
+        Ok(head)
+    }
+
+    fn score(&self) -> Option<u64> {
+        self.head.as_ref().map(|(score, _)| *score)
+    }
+}
+
+impl DiskFrontier {
+    // Make a new directory for the states inside the given one (which must already exist). The puzzle is the one
+    // being solved
+    pub fn new(parent: &Path, puzzle: &Puzzle) -> io::Result<DiskFrontier> {
+        // States are written with 16 bit ids, with the biggest one meaning None (see Puzzle::write_state)
+        if puzzle.num_cells() >= u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "a puzzle with {} cells is too big to write to disk",
+                    puzzle.num_cells()
+                ),
+            ));
+        }
+        let directory = parent.join(format!(
+            "flow-solver-{}-{}",
+            process::id(),
+            NEXT_DIRECTORY.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir(&directory)?;
+        Ok(DiskFrontier {
+            directory,
+            puzzle: puzzle.clone(),
+            runs: vec![],
+            written: 0,
+            len: 0,
+        })
+    }
+
+    // How many states are on disk
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // The score of the best state on disk
+    pub fn peek_score(&self) -> Option<u64> {
+        self.runs.iter().filter_map(|run| run.score()).max()
+    }
+
+    // Write the states (and their scores), which must be sorted best first, to disk as a new run
+    // If this fails, none of the states are on disk, and the ones that were there already still are
+    pub fn spill(&mut self, states: &[(u64, Puzzle)]) -> io::Result<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+        // Make room first, so there's nothing to undo if the merge fails
+        if self.runs.len() >= MAX_RUNS {
+            self.merge_smallest_runs()?;
+        }
+
+        let path = self.next_run_path();
+        let state_size = self.puzzle.state_size();
+        let run = write_run(path, states.len(), state_size, |writer| {
+            let mut bytes = Vec::with_capacity(state_size);
+            for (score, state) in states.iter() {
+                bytes.clear();
+                state.write_state(&mut bytes);
+                write_record(writer, *score, &bytes)?;
+            }
+            Ok(())
+        })?;
+        self.runs.push(run);
+        self.len += states.len();
+        Ok(())
+    }
+
+    // Take the best state off the disk, along with its score
+    pub fn pop(&mut self) -> io::Result<Option<(u64, Puzzle)>> {
+        let best = match best_run(&self.runs) {
+            Some(best) => best,
+            None => return Ok(None),
+        };
+        let run = &mut self.runs[best];
+        let (score, bytes) = run.advance(self.puzzle.state_size())?.unwrap();
+        // The state has been taken either way, so a run that can't be removed is left for the directory to be
+        // cleaned up with
+        if run.head.is_none() {
+            let _ = fs::remove_file(self.runs.remove(best).path);
+        }
+        self.len -= 1;
+        Ok(Some((score, self.puzzle.read_state(&bytes))))
+    }
+
+    fn next_run_path(&mut self) -> PathBuf {
+        self.written += 1;
+        self.directory.join(format!("run-{}.bin", self.written))
+    }
+
+    // Merge the runs with the fewest states left into one, a state at a time, best first
+    // They're read through new readers, so if anything goes wrong, the runs are left just as they were
+    fn merge_smallest_runs(&mut self) -> io::Result<()> {
+        let state_size = self.puzzle.state_size();
+        let mut merging: Vec<usize> = (0..self.runs.len()).collect();
+        merging.sort_by_key(|i| self.runs[*i].remaining);
+        merging.truncate(MERGED_RUNS);
+
+        let mut sources = merging
+            .iter()
+            .map(|i| self.runs[*i].reopen(state_size))
+            .collect::<io::Result<Vec<Run>>>()?;
+        let count = sources.iter().map(|run| run.remaining).sum();
+        let path = self.next_run_path();
+        let merged = write_run(path, count, state_size, |writer| {
+            while let Some(best) = best_run(&sources) {
+                let (score, bytes) = sources[best].advance(state_size)?.unwrap();
+                write_record(writer, score, &bytes)?;
+            }
+            Ok(())
+        })?;
+        drop(sources);
+
+        // Everything is in the merged run now, so the old ones can go. Any files that can't be removed are left for
+        // the directory to be cleaned up with
+        merging.sort_unstable_by(|a, b| b.cmp(a));
+        for i in merging {
+            let _ = fs::remove_file(self.runs.remove(i).path);
+        }
+        self.runs.push(merged);
+        Ok(())
+    }
+}
+
+// The run with the best state at its head, if any of them have one
+fn best_run(runs: &[Run]) -> Option<usize> {
+    (0..runs.len())
+        .filter(|i| runs[*i].head.is_some())
+        .max_by_key(|i| runs[*i].score())
+}
+
+// Write a run of the given number of states to the file at the path, and open it to read back
+// If anything goes wrong, the file is removed again
+fn write_run<F>(path: PathBuf, count: usize, state_size: usize, write: F) -> io::Result<Run>
+where
+    F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+{
+    let written = File::create(&path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush()
+    });
+    match written.and_then(|_| Run::open(path.clone(), 0, count, state_size)) {
+        Ok(run) => Ok(run),
+        Err(error) => {
+            // Nothing was made if it failed creating the file
+            let _ = fs::remove_file(&path);
+            Err(error)
+        }
+    }
+}
+
+fn write_record(writer: &mut impl Write, score: u64, state: &[u8]) -> io::Result<()> {
+    writer.write_all(&score.to_le_bytes())?;
+    writer.write_all(state)
+}
+
+impl Drop for DiskFrontier {
+    fn drop(&mut self) {
+        // Nothing else can be done about it if this fails, and the files are in a temporary directory anyway
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::layout::{
+    flow_color, text_color, Color, Layout, Point, BACKGROUND, BRIDGE_GAP, BRIDGE_LANE_OPACITY,
+    BRIDGE_LANE_WIDTH, CELL_BACKGROUND, CELL_SIZE, DOT_RADIUS, GRID_COLOR, PIPE_WIDTH,
+    SHADE_OPACITY, WALL_COLOR, WALL_WIDTH, WARP_COLOR,
+};
+use crate::puzzle::{CellId, Puzzle, VERTICAL};
+
+// Draw the puzzle (solved or not) as an SVG image
+// Each flow is drawn as a pipe along the path it takes between its dots. Cells filled in without a known path
+// (e.g. from a partial solution read from a file) are shaded in their flow's color instead
+pub fn render_svg(puzzle: &Puzzle) -> String {
+    let layout = Layout::new(puzzle);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.1} {:.1}\">\n",
+        layout.width.ceil(),
+        layout.height.ceil(),
+        layout.width,
+        layout.height
+    );
+    svg.push_str(&format!(
+        "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+        hex(BACKGROUND)
+    ));
+
+    // Cells, shaded with their flow's color (if they have one). The two halves of a bridge are drawn once
+    for (index, cell) in puzzle.get_cells().iter().enumerate() {
+        if cell.bridge_direction == Some(VERTICAL) {
+            continue;
+        }
+        let (fill, opacity) = match cell.flow_id {
+            Some(flow_id) => (flow_color(flow_id.index), SHADE_OPACITY),
+            None => (CELL_BACKGROUND, 1.0),
+        };
+        svg.push_str(&format!(
+            "<polygon points=\"{}\" fill=\"{}\" fill-opacity=\"{}\" stroke=\"{}\" stroke-width=\"1\"/>\n",
+            points(&layout.shapes[index]),
+            hex(fill),
+            opacity,
+            hex(GRID_COLOR)
+        ));
+
+        // Bridges get the outline of a crossing, so they can be told apart from regular cells
+        if cell.bridge_direction.is_some() {
+            for lane in layout.bridge_lanes(CellId { index }) {
+                svg.push_str(&line(
+                    lane,
+                    GRID_COLOR,
+                    BRIDGE_LANE_WIDTH,
+                    BRIDGE_LANE_OPACITY,
+                ));
+            }
+        }
+    }
+
+    for wall in layout.walls.iter() {
+        svg.push_str(&line(*wall, WALL_COLOR, WALL_WIDTH, 1.0));
+    }
+    for warp in layout.warps.iter() {
+        let ((x1, y1), (x2, y2)) = *warp;
+        svg.push_str(&format!(
+            "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"{:.1}\" stroke-dasharray=\"4 4\"/>\n",
+            x1, y1, x2, y2, hex(WARP_COLOR), WALL_WIDTH
+        ));
+    }
+
+    // The pipes, with the gaps around the pipes going over bridges
+    let pipes = layout.pipe_segments(puzzle, &puzzle.get_connections());
+    for outline in pipes.iter().filter_map(|piece| piece.outline) {
+        svg.push_str(&line(
+            outline,
+            BACKGROUND,
+            PIPE_WIDTH + 2.0 * BRIDGE_GAP,
+            1.0,
+        ));
+    }
+    for piece in pipes.iter() {
+        svg.push_str(&line(
+            piece.segment,
+            flow_color(piece.flow_id.index),
+            PIPE_WIDTH,
+            1.0,
+        ));
+    }
+
+    // The dots, with their labels on top
+    for flow in puzzle.flows.iter() {
+        let color = flow_color(flow.id);
+        for dot in flow.get_dots().iter() {
+            let (x, y) = layout.centers[dot.index];
+            svg.push_str(&format!(
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\"/>\n",
+                x,
+                y,
+                DOT_RADIUS,
+                hex(color)
+            ));
+            svg.push_str(&format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"{:.1}\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"{}\">{}</text>\n",
+                x,
+                y,
+                CELL_SIZE * 0.3,
+                hex(text_color(color)),
+                escape(flow.get_label())
+            ));
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// A color, as written in SVG
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2)
+}
+
+// The list of points for a polygon
+fn points(corners: &[Point]) -> String {
+    corners
+        .iter()
+        .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// A line with rounded ends
+fn line(segment: (Point, Point), color: Color, width: f64, opacity: f64) -> String {
+    let ((x1, y1), (x2, y2)) = segment;
+    format!(
+        "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"{:.1}\" stroke-opacity=\"{}\" stroke-linecap=\"round\"/>\n",
+        x1, y1, x2, y2, hex(color), width, opacity
+    )
+}
+
+// Labels can contain anything, so escape the characters that mean something in XML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,146 @@
+use crate::layout::{flow_color, Color};
+use crate::parser::{is_cell_token, split_columns};
+use crate::puzzle::{CellId, FlowId, Puzzle};
+use std::collections::HashMap;
+
+// The box drawing character for a cell with arms going in the given directions
+// Indexed by the arms added together: north = 1, east = 2, south = 4, west = 8
+const BOX_CHARACTERS: [char; 16] = [
+    ' ', '╵', '╶', '└', '╷', '│', '┌', '├', '╴', '┘', '─', '┴', '┐', '┤', '┬', '┼',
+];
+const NORTH: u8 = 1;
+const EAST: u8 = 2;
+const SOUTH: u8 = 4;
+const WEST: u8 = 8;
+
+// Draw the puzzle for a terminal, in the same layout as the puzzle file, with each flow in its own color
+// Paths are drawn with box drawing characters, and only the connectors the paths go through are kept,
+// so it's clear which way every path runs (including out through warps and across bridges)
+// Hex puzzles can't be drawn with box characters, so their cells show their flow's label instead
+pub fn render_terminal(puzzle: &Puzzle) -> String {
+    let mut cells_at: HashMap<(usize, usize), Vec<CellId>> = HashMap::new();
+    for (index, cell) in puzzle.get_cells().iter().enumerate() {
+        cells_at
+            .entry((cell.row, cell.col))
+            .or_default()
+            .push(CellId { index });
+    }
+
+    // Work out which way each path leaves each cell, and which connectors it goes through
+    let mut arms: HashMap<usize, u8> = HashMap::new();
+    let mut connectors: HashMap<(usize, usize), FlowId> = HashMap::new();
+    for (a, b) in puzzle.get_connections().iter() {
+        let flow_id = puzzle.get_cell(*a).unwrap().flow_id.unwrap();
+        let connector = puzzle.connector_between(*a, *b);
+        connectors.insert(connector, flow_id);
+        for id in [a, b] {
+            let cell = puzzle.get_cell(*id).unwrap();
+            let (row, col) = (cell.row, cell.col);
+            // The path leaves towards the connector. Except at the far end of a warp, where it leaves off the
+            // edge of the board, away from the connector on the other side
+            let arm = if row.abs_diff(connector.0) <= 1 && col.abs_diff(connector.1) <= 1 {
+                direction(row, col, connector.0, connector.1)
+            } else {
+                direction(connector.0, connector.1, row, col)
+            };
+            *arms.entry(id.index).or_default() |= arm;
+        }
+    }
+
+    let mut output = String::new();
+    let mut row = 0;
+    for line in puzzle.print_string.split('\n').skip(1) {
+        if line.starts_with("//") {
+            output.push_str(&paint(line, None, false));
+            output.push('\n');
+            continue;
+        }
+        for (col, token) in split_columns(line.trim_end()).iter().enumerate() {
+            if is_cell_token(token) {
+                output.push_str(&draw_cell(puzzle, &cells_at[&(row, col)], &arms));
+            } else if let Some(flow_id) = connectors.get(&(row, col)) {
+                let connector = match token.as_str() {
+                    "-" => "─",
+                    "|" => "│",
+                    "/" => "╱",
+                    _ => "╲",
+                };
+                output.push_str(&paint(connector, Some(flow_color(flow_id.index)), false));
+            } else if ["-", "|", "/", "\\"].contains(&token.as_str()) {
+                // Connectors the paths don't go through
+                output.push(' ');
+            } else {
+                output.push_str(token);
+            }
+        }
+        output.push('\n');
+        row += 1;
+    }
+    output
+}
+
+// The direction of the second cell from the first
+fn direction(row: usize, col: usize, other_row: usize, other_col: usize) -> u8 {
+    let mut direction = 0;
+    if other_row < row {
+        direction |= NORTH;
+    }
+    if other_row > row {
+        direction |= SOUTH;
+    }
+    if other_col < col {
+        direction |= WEST;
+    }
+    if other_col > col {
+        direction |= EAST;
+    }
+    direction
+}
+
+// Draw a single cell (or both halves of a bridge)
+fn draw_cell(puzzle: &Puzzle, ids: &[CellId], arms: &HashMap<usize, u8>) -> String {
+    let cell_arms = ids
+        .iter()
+        .map(|id| arms.get(&id.index).copied().unwrap_or(0))
+        .fold(0, |all, arms| all | arms);
+    // The color of the cell comes from the half of a bridge on top, if it's filled in
+    let flow_id = ids
+        .iter()
+        .rev()
+        .find_map(|id| puzzle.get_cell(*id).unwrap().flow_id);
+
+    match flow_id {
+        None if ids.len() > 1 => paint("*", None, false),
+        None => paint(".", None, false),
+        Some(flow_id) => {
+            let flow = puzzle.get_flow(flow_id).unwrap();
+            let color = Some(flow_color(flow_id.index));
+            if puzzle.is_dot(ids[0]) {
+                paint(&flow.get_display_label(), color, true)
+            } else if cell_arms == 0 || puzzle.is_hex {
+                // No known path through the cell (or no way of drawing one), so just show which flow it is
+                paint(&flow.get_display_label(), color, false)
+            } else {
+                paint(
+                    &BOX_CHARACTERS[cell_arms as usize].to_string(),
+                    color,
+                    false,
+                )
+            }
+        }
+    }
+}
+
+// Wrap the text in the ANSI codes for the given color (or dim it, if there's no color), and optionally bold it
+fn paint(text: &str, color: Option<Color>, bold: bool) -> String {
+    let style = match color {
+        Some((r, g, b)) => format!("38;2;{};{};{}", r, g, b),
+        None => String::from("2"),
+    };
+    format!(
+        "\x1b[{}{}m{}\x1b[0m",
+        if bold { "1;" } else { "" },
+        style,
+        text
+    )
+}
@@ -0,0 +1,240 @@
+use crate::parser::{parse_label, split_columns, PATHS_SECTION, PUZZLE_TYPES};
+use crate::puzzle::{CellId, FlowId, Puzzle, BRIDGE_NOTE};
+use std::collections::HashMap;
+
+// Check a proposed solution against the puzzle it is meant to solve
+// The solution is a solved board in the same format as the puzzle file: every cell holds the label of the flow
+// that fills it, and bridges are left as '*' (which flows cross them is worked out from their neighbors).
+// The first line may be the puzzle type, just like in the puzzle file, but it can be left off.
+// Returns the solved puzzle if the solution is valid, otherwise a description of the first problem found
+pub fn verify_solution(puzzle: &Puzzle, solution: &str) -> Result<Puzzle, String> {
+    let mut lines: Vec<&str> = solution.split('\n').collect();
+    if let Some(i) = lines
+        .iter()
+        .position(|line| line.trim_end() == PATHS_SECTION)
+    {
+        lines.truncate(i);
+    }
+    if lines
+        .first()
+        .is_some_and(|line| PUZZLE_TYPES.contains(&line.trim()))
+    {
+        lines.remove(0);
+    }
+
+    // Map the location of each cell in the puzzle to its id(s). Bridges have two cells at the same location
+    let mut cell_map: HashMap<(usize, usize), Vec<CellId>> = HashMap::new();
+    for (index, cell) in puzzle.get_cells().iter().enumerate() {
+        cell_map
+            .entry((cell.row, cell.col))
+            .or_default()
+            .push(CellId { index });
+    }
+
+    let mut solved = puzzle.clone();
+    let mut seen: Vec<(usize, usize)> = vec![];
+
+    let mut row = 0;
+    for line in lines.iter() {
+        if line.starts_with("//") || line.starts_with(BRIDGE_NOTE) {
+            continue;
+        }
+        for (col, token) in split_columns(line).iter().enumerate() {
+            if token.trim().is_empty() || ["-", "|", "/", "\\"].contains(&token.as_str()) {
+                continue;
+            }
+
+            let location = format!("row {}, column {}", row + 1, col + 1);
+            let ids = cell_map
+                .get(&(row, col))
+                .ok_or(format!("{} is not a cell in the puzzle", location))?;
+            seen.push((row, col));
+
+            // Bridges are filled in later, once all of the regular cells are known
+            if ids.len() > 1 {
+                if token != "*" {
+                    return Err(format!("{} should be a bridge ('*')", location));
+                }
+                continue;
+            }
+
+            let label = match parse_label(token) {
+                Some(label) => label,
+                None if token == "." => return Err(format!("{} is empty", location)),
+                None => return Err(format!("{} is not a bridge in the puzzle", location)),
+            };
+            let flow_id = solved
+                .flows
+                .iter()
+                .position(|flow| flow.get_label() == label)
+                .map(|index| FlowId { index })
+                .ok_or(format!(
+                    "{} is filled with {}, which isn't a flow in the puzzle",
+                    location, label
+                ))?;
+
+            let cell = solved.get_cell_mut(ids[0]).unwrap();
+            if let Some(expected) = cell.flow_id.or(cell.restricted_to) {
+                if expected != flow_id {
+                    let expected_label = puzzle.get_flow(expected).unwrap().get_label();
+                    return Err(format!(
+                        "{} belongs to flow {} in the puzzle, but is filled with {}",
+                        location, expected_label, label
+                    ));
+                }
+            }
+            cell.flow_id = Some(flow_id);
+        }
+        row += 1;
+    }
+
+    if let Some(cell) = puzzle
+        .get_cells()
+        .iter()
+        .find(|cell| !seen.contains(&(cell.row, cell.col)))
+    {
+        return Err(format!("{} is missing from the solution", cell.location()));
+    }
+
+    // Each half of a bridge is filled by the flow passing straight through it, so copy the flow over from its
+    // neighbors (which are only the ones in that half's direction). They have to agree, or the flows turn on the bridge
+    // Keep going until nothing changes, as bridges can be next to each other
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 0..solved.num_cells() {
+            let cell = solved.get_cell(CellId { index: i }).unwrap();
+            if cell.is_occupied() {
+                continue;
+            }
+            let mut neighbor_flows: Vec<FlowId> = cell
+                .neighbors
+                .iter()
+                .filter_map(|n| solved.get_cell(*n).unwrap().flow_id)
+                .collect();
+            neighbor_flows.dedup();
+            match neighbor_flows[..] {
+                [] => {}
+                [flow_id] => {
+                    solved.get_cell_mut(CellId { index: i }).unwrap().flow_id = Some(flow_id);
+                    changed = true;
+                }
+                [a, b, ..] => {
+                    return Err(format!(
+                        "{} is crossed by both {} and {}, which would have to turn on the bridge",
+                        cell.location(),
+                        solved.get_flow(a).unwrap().get_label(),
+                        solved.get_flow(b).unwrap().get_label()
+                    ))
+                }
+            }
+        }
+    }
+
+    verify_puzzle(&solved)?;
+    Ok(solved)
+}
+
+// Check that a filled in puzzle is a valid solution
+// Every cell must be filled, and each flow must form a single path between its two dots, moving only between
+// neighboring cells (so warps and bridges are followed exactly like the solver follows them)
+// Returns a description of the first problem found
+pub fn verify_puzzle(puzzle: &Puzzle) -> Result<(), String> {
+    for cell in puzzle.get_cells().iter() {
+        match cell.flow_id {
+            None => return Err(format!("{} is empty", cell.location())),
+            Some(flow_id) => {
+                if cell.restricted_to.is_some_and(|id| id != flow_id) {
+                    return Err(format!(
+                        "{} is filled with {}, but was pre-drawn as {}",
+                        cell.location(),
+                        puzzle.get_flow(flow_id).unwrap().get_label(),
+                        puzzle
+                            .get_flow(cell.restricted_to.unwrap())
+                            .unwrap()
+                            .get_label()
+                    ));
+                }
+            }
+        }
+    }
+
+    for (f, flow) in puzzle.flows.iter().enumerate() {
+        let flow_id = FlowId { index: f };
+        let [dot_0, dot_1] = flow.get_dots();
+        for dot in [dot_0, dot_1] {
+            let cell = puzzle.get_cell(dot).unwrap();
+            if cell.flow_id != Some(flow_id) {
+                return Err(format!(
+                    "{} is a dot of flow {}, but is filled with {}",
+                    cell.location(),
+                    flow.get_label(),
+                    puzzle.get_flow(cell.flow_id.unwrap()).unwrap().get_label()
+                ));
+            }
+        }
+
+        // Find everything connected to the first dot
+        let mut reached = vec![false; puzzle.num_cells()];
+        let mut queue = vec![dot_0];
+        while let Some(curr) = queue.pop() {
+            if reached[curr.index] {
+                continue;
+            }
+            reached[curr.index] = true;
+            for n in puzzle.get_cell(curr).unwrap().neighbors.iter() {
+                if puzzle.get_cell(*n).unwrap().flow_id == Some(flow_id) {
+                    queue.push(*n);
+                }
+            }
+        }
+
+        if !reached[dot_1.index] {
+            return Err(format!(
+                "flow {} doesn't connect its dots at {} and {}",
+                flow.get_label(),
+                puzzle.get_cell(dot_0).unwrap().location(),
+                puzzle.get_cell(dot_1).unwrap().location()
+            ));
+        }
+
+        for (index, cell) in puzzle.get_cells().iter().enumerate() {
+            if cell.flow_id != Some(flow_id) {
+                continue;
+            }
+            if !reached[index] {
+                return Err(format!(
+                    "{} is filled with {}, but isn't connected to the rest of the flow",
+                    cell.location(),
+                    flow.get_label()
+                ));
+            }
+
+            // Anything in the middle of a path has to have somewhere to come from and somewhere to go
+            let id = CellId { index };
+            let same_flow_neighbors = cell
+                .neighbors
+                .iter()
+                .filter(|n| puzzle.get_cell(**n).unwrap().flow_id == Some(flow_id))
+                .count();
+            if id != dot_0 && id != dot_1 && same_flow_neighbors < 2 {
+                return Err(format!(
+                    "{} is a dead end in flow {}",
+                    cell.location(),
+                    flow.get_label()
+                ));
+            }
+        }
+
+        if puzzle.trace_path(flow_id).is_none() {
+            return Err(format!(
+                "flow {} can't be drawn as a single path from {} to {} (it branches, loops or runs alongside itself)",
+                flow.get_label(),
+                puzzle.get_cell(dot_0).unwrap().location(),
+                puzzle.get_cell(dot_1).unwrap().location()
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// Run the solver with the given arguments and standard input, returning its exit code and what it printed
+fn run(args: &[&str], stdin: &str) -> (i32, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_flow_free_solver_rust"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let mut printed = String::from_utf8_lossy(&output.stdout).to_string();
+    printed.push_str(&String::from_utf8_lossy(&output.stderr));
+    (output.status.code().unwrap(), printed)
+}
+
+#[test]
+fn puzzles_are_read_from_standard_input_in_any_format() {
+    let (code, printed) = run(
+        &["convert", "-q", "--level", "2", "-"],
+        "3,0,1,2;0,1,2;6,7,8\n3,0,2,2;0,3,6;2,5,8\n",
+    );
+    assert_eq!(code, 0, "{}", printed);
+    assert_eq!(printed, "STANDARD\nA-.-B\n| | |\n.-.-.\n| | |\nA-.-B\n");
+
+    let (code, printed) = run(
+        &["solve", "-q", "-"],
+        "{\"type\": \"standard\", \"board\": [\"A-A\"]}",
+    );
+    assert_eq!(code, 0, "{}", printed);
+
+    let (code, printed) = run(&["convert", "-"], "not a puzzle");
+    assert_eq!(code, 2);
+    assert!(
+        printed.contains("Couldn't parse standard input"),
+        "{}",
+        printed
+    );
+}
+
+const UNSOLVABLE: &str = "STANDARD\nA-.-B\n| | |\n.-.-.\n| | |\nB-.-A\n";
+
+#[test]
+fn exit_codes_say_how_it_went() {
+    let puzzle = "puzzles/standard/Regular5x5_1.txt";
+    assert_eq!(run(&["-q", puzzle], "").0, 0);
+    assert_eq!(run(&["solve", "-q", "-"], UNSOLVABLE).0, 1);
+    assert_eq!(run(&["explain", "-"], UNSOLVABLE).0, 1);
+    assert_eq!(run(&["-q", "puzzles/no-such-puzzle.txt"], "").0, 2);
+    assert_eq!(run(&["render", "-"], "STANDARD\nA-.-.").0, 2);
+    let hard = "puzzles/standard/ExtremePack12x12_30.txt";
+    assert_eq!(run(&["-q", "--max-states", "10", hard], "").0, 3);
+    assert_eq!(
+        run(&["hint", "--flow", "--max-states", "10", hard], "").0,
+        3
+    );
+    assert_eq!(run(&[], "").0, 64);
+    assert_eq!(run(&["--help"], "").0, 0);
+}
+
+#[test]
+fn output_that_cant_be_written_is_an_error() {
+    let missing = Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-such-directory");
+    let output = |name: &str| missing.join(name).to_string_lossy().to_string();
+    let puzzle = "puzzles/standard/Regular5x5_1.txt";
+    for args in [
+        vec!["-q", "-o", &output("solution.txt"), puzzle],
+        vec!["-f", "gif", "-o", &output("search.gif"), puzzle],
+        vec!["render", "-f", "svg", "-o", &output("puzzle.svg"), puzzle],
+        vec!["convert", "-o", &output("puzzle.txt"), puzzle],
+        vec!["generate", "--seed", "1", "-o", &output("generated.txt")],
+        vec!["batch", "--csv", &output("results.csv"), puzzle],
+        vec![
+            "bench",
+            "--runs",
+            "1",
+            "--save-baseline",
+            &output("baseline.csv"),
+            puzzle,
+        ],
+    ] {
+        let (code, printed) = run(&args, "");
+        assert_eq!(code, 2, "{:?}: {}", args, printed);
+        assert!(
+            printed.contains("Couldn't write"),
+            "{:?}: {}",
+            args,
+            printed
+        );
+    }
+    assert!(!missing.exists());
+}
+
+#[test]
+fn command_lines_that_dont_make_sense_are_usage_errors() {
+    let puzzle = "puzzles/standard/Regular5x5_1.txt";
+    for (args, expected) in [
+        (vec!["--solver", "nope", puzzle], "Unknown solver nope"),
+        (
+            vec!["--timeout", "0", puzzle],
+            "--timeout needs a number of seconds",
+        ),
+        (vec!["--timeout"], "--timeout needs a value"),
+        (vec!["--bogus", puzzle], "Unknown option --bogus"),
+        (
+            vec!["verify", puzzle],
+            "verify needs a puzzle and a solution",
+        ),
+        (vec!["batch"], "batch needs at least one puzzle"),
+        (vec!["generate", puzzle], "generate doesn't take a puzzle"),
+        (vec![puzzle, puzzle], "solve needs a single puzzle"),
+        (
+            vec!["--heuristic", "default,open-cells", puzzle],
+            "Only bench can compare",
+        ),
+        (vec!["-f", "gif", puzzle], "A gif needs an --output file"),
+        (
+            vec!["render", "-f", "gif", "-o", "x.gif", puzzle],
+            "render can't be shown as gif",
+        ),
+        (
+            vec!["solve", "--flow", puzzle],
+            "Only hint can give a whole --flow",
+        ),
+    ] {
+        let (code, printed) = run(&args, "");
+        assert_eq!(code, 64, "{:?}: {}", args, printed);
+        assert!(printed.contains(expected), "{:?}: {}", args, printed);
+    }
+
+    // Options can go before or after the puzzle
+    let (code, printed) = run(&["hint", "--flow", puzzle], "");
+    assert_eq!(code, 0, "{}", printed);
+    let (code, after) = run(&["hint", puzzle, "--flow"], "");
+    assert_eq!(code, 0);
+    assert_eq!(after, printed);
+    assert!(printed.starts_with("Flow "), "{}", printed);
+    assert!(printed.contains(" cells between "), "{}", printed);
+}
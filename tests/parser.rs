@@ -0,0 +1,258 @@
+extern crate flow_free_solver_rust;
+
+use flow_free_solver_rust::convert::{detect_format, InputFormat, PuzzleFile};
+use flow_free_solver_rust::parser::{parse_label, parse_puzzle, split_columns};
+use flow_free_solver_rust::puzzle::{FlowId, MAX_CELLS};
+
+// A standard puzzle from its rows of cells, with every neighbor joined up
+fn standard(rows: &[&str]) -> String {
+    let mut lines = vec![String::from("STANDARD")];
+    for (i, row) in rows.iter().enumerate() {
+        let cells = split_columns(row);
+        lines.push(cells.join("-"));
+        if i + 1 < rows.len() {
+            lines.push(
+                cells
+                    .iter()
+                    .map(|_| String::from("|"))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+        }
+    }
+    lines.join("\n")
+}
+
+fn parse_error(input: &str) -> String {
+    match parse_puzzle(input) {
+        Ok(_) => panic!("expected an error parsing:\n{}", input),
+        Err(why) => why,
+    }
+}
+
+#[test]
+fn columns_are_split_with_escaped_labels_kept_whole() {
+    assert_eq!(split_columns("A-{Red}-."), ["A", "-", "{Red}", "-", "."]);
+    assert_eq!(
+        split_columns("{10}|{Long name}"),
+        ["{10}", "|", "{Long name}"]
+    );
+    // Empty and unterminated braces are just characters
+    assert_eq!(split_columns("{}-."), ["{", "}", "-", "."]);
+    assert_eq!(split_columns("{Red-."), ["{", "R", "e", "d", "-", "."]);
+    assert_eq!(split_columns("a{b}}"), ["a", "{b}", "}"]);
+    assert!(split_columns("").is_empty());
+}
+
+#[test]
+fn labels_are_letters_digits_or_anything_in_braces() {
+    assert_eq!(parse_label("A").as_deref(), Some("A"));
+    assert_eq!(parse_label("q").as_deref(), Some("q"));
+    assert_eq!(parse_label("7").as_deref(), Some("7"));
+    assert_eq!(parse_label("{Red}").as_deref(), Some("Red"));
+    assert_eq!(parse_label("{12}").as_deref(), Some("12"));
+    for not_a_label in [".", "*", "-", "|", "{}", "{", "}", "AB", "é", ""] {
+        assert_eq!(parse_label(not_a_label), None, "{:?}", not_a_label);
+    }
+}
+
+#[test]
+fn escaped_labels_are_flows_of_their_own() {
+    let puzzle = parse_puzzle(&standard(&["{Red}..{10}", "....", "{Red}..{10}"])).unwrap();
+    let labels: Vec<&str> = puzzle.flows.iter().map(|flow| flow.get_label()).collect();
+    assert_eq!(labels, ["Red", "10"]);
+    assert_eq!(puzzle.num_cells(), 12);
+}
+
+#[test]
+fn every_label_needs_exactly_two_endpoints() {
+    let why = parse_error(&standard(&["A.A", "...", "..A"]));
+    assert!(
+        why.contains("flow A has more than two endpoints"),
+        "{}",
+        why
+    );
+    assert!(why.contains("row 5, column 5"), "{}", why);
+
+    let why = parse_error(&standard(&["A.B", "...", "..B"]));
+    assert!(why.contains("flow A only has one endpoint"), "{}", why);
+}
+
+#[test]
+fn boards_too_big_for_the_solver_are_rejected() {
+    // An empty board of the given size, with a single flow down its first column
+    let board = |width: usize, height: usize| {
+        let mut rows = vec![".".repeat(width); height];
+        rows[0].replace_range(0..1, "A");
+        rows[height - 1].replace_range(0..1, "A");
+        let rows: Vec<&str> = rows.iter().map(|row| row.as_str()).collect();
+        standard(&rows)
+    };
+    let width = (MAX_CELLS as f64).sqrt() as usize + 1;
+    assert!(width * width > MAX_CELLS && width * (width - 1) <= MAX_CELLS);
+
+    let why = parse_error(&board(width, width));
+    assert!(why.contains("the most the solver can handle"), "{}", why);
+    assert!(parse_puzzle(&board(width, width - 1)).is_ok());
+}
+
+// The example from the README: A is drawn down one cell, B across one, and two cells are set aside for A and C
+const PATHS_EXAMPLE: &str = "STANDARD
+A-.-B-.-C
+| | | | |
+.-.-D-.-E
+| | | | |
+.-.-.-.-.
+| | | | |
+.-B-.-C-.
+| | | | |
+.-A-D-E-.
+PATHS
+A B-B . C
+|
+A . . . .
+
+. . . C .
+
+. . . . .
+
+. . . . .";
+
+// A 3x3 board with A down the left and B down the right, and the given pre-drawn paths
+fn with_paths(paths: &str) -> String {
+    format!(
+        "STANDARD\nA-.-B\n| | |\n.-.-.\n| | |\nA-.-B\nPATHS\n{}",
+        paths
+    )
+}
+
+#[test]
+fn pre_drawn_paths_are_drawn_in_from_the_endpoints() {
+    let puzzle = parse_puzzle(PATHS_EXAMPLE).unwrap();
+    let cell_at = |row: usize, col: usize| {
+        puzzle
+            .get_cells()
+            .iter()
+            .position(|cell| cell.row == row && cell.col == col)
+            .unwrap()
+    };
+    let flow = |label: &str| {
+        puzzle
+            .flows
+            .iter()
+            .find(|flow| flow.get_label() == label)
+            .unwrap()
+    };
+    let cells = puzzle.get_cells();
+
+    // A's first endpoint has moved down a cell, and B's first across one, each coming from its dot
+    let a_head = cell_at(2, 0);
+    assert_eq!(flow("A").get_endpoint(0).index, a_head);
+    assert_eq!(cells[a_head].previous.unwrap().index, cell_at(0, 0));
+    assert!(cells[a_head].is_endpoint && !cells[cell_at(0, 0)].is_endpoint);
+    let b_head = cell_at(0, 2);
+    assert_eq!(flow("B").get_endpoint(0).index, b_head);
+    assert_eq!(cells[b_head].previous.unwrap().index, cell_at(0, 4));
+
+    // C's cell is set aside for it, but still empty
+    let c_cell = &cells[cell_at(4, 6)];
+    assert!(!c_cell.is_occupied());
+    let c = puzzle
+        .flows
+        .iter()
+        .position(|flow| flow.get_label() == "C")
+        .unwrap();
+    assert_eq!(c_cell.restricted_to, Some(FlowId { index: c }));
+    assert_eq!(puzzle.num_open_cells(), 25 - 10 - 2);
+}
+
+#[test]
+fn bad_pre_drawn_paths_are_explained() {
+    for (paths, expected) in [
+        (" A", "pre-drawn A at row 1, column 2 is not on a cell"),
+        ("C", "pre-drawn flow C has no endpoints"),
+        (
+            "B",
+            "pre-drawn B at row 1, column 1 is on another flow's endpoint",
+        ),
+        (
+            "-",
+            "pre-drawn '-' at row 1, column 1 doesn't connect two neighboring cells",
+        ),
+        (
+            "A-.\n|",
+            "pre-drawn path of flow A branches at row 2, column 1",
+        ),
+        // A and B both drawn into the middle of the top row
+        (
+            "A-.-B",
+            "pre-drawn path of flow A runs into another flow at row 1, column 4",
+        ),
+        // Not touching either of A's dots
+        (
+            "\n\n.-.",
+            "pre-drawn connection at row 3, column 2 isn't part of a path drawn from an endpoint",
+        ),
+    ] {
+        let why = parse_error(&with_paths(paths));
+        assert!(why.contains(expected), "{:?}: {}", paths, why);
+    }
+    assert!(parse_puzzle(&with_paths("A-.\n\n. . .")).is_ok());
+    assert_eq!(
+        parse_error("PATHS\nA-A"),
+        "the board is missing before PATHS"
+    );
+}
+
+// A 3x3 level pack with two levels: A across the top and B across the bottom, then the same turned on its side
+const LEVEL_PACK: &str = "3,0,1,2;0,1,2;6,7,8\n3,0,2,2;0,3,6;2,5,8\n";
+const LEVEL_ONE: &str = "STANDARD\nA-.-A\n| | |\n.-.-.\n| | |\nB-.-B";
+
+#[test]
+fn formats_are_detected_from_the_contents() {
+    assert_eq!(detect_format(LEVEL_ONE), Some(InputFormat::Text));
+    assert_eq!(
+        detect_format("\u{feff}// A comment\n\n  hex\n"),
+        Some(InputFormat::Text)
+    );
+    assert_eq!(
+        detect_format("{\"type\": \"STANDARD\"}"),
+        Some(InputFormat::Json)
+    );
+    assert_eq!(detect_format(LEVEL_PACK), Some(InputFormat::LevelPack));
+    assert_eq!(detect_format("5:6,0,1,0;\n"), Some(InputFormat::LevelPack));
+    for not_a_puzzle in ["", "A-.-A", "0,0,1,2;0,1", "3,0,1;0,1,2"] {
+        assert_eq!(detect_format(not_a_puzzle), None, "{:?}", not_a_puzzle);
+    }
+}
+
+#[test]
+fn every_format_reads_into_the_same_puzzle() {
+    let text = PuzzleFile::read(LEVEL_ONE).unwrap();
+    let level_pack = PuzzleFile::read(LEVEL_PACK).unwrap();
+    let json = PuzzleFile::read(&text.to_json()).unwrap();
+    assert_eq!(level_pack, text);
+    assert_eq!(json, text);
+    let puzzle = parse_puzzle(&level_pack.to_text()).unwrap();
+    assert_eq!(
+        puzzle.to_text(),
+        parse_puzzle(&text.to_text()).unwrap().to_text()
+    );
+
+    // Pre-drawn paths survive the trip through JSON
+    let with_paths = PuzzleFile::read(PATHS_EXAMPLE).unwrap();
+    assert_eq!(PuzzleFile::read(&with_paths.to_json()).unwrap(), with_paths);
+
+    let second = PuzzleFile::read_level(LEVEL_PACK, 2).unwrap();
+    assert_eq!(second.board, ["A-.-B", "| | |", ".-.-.", "| | |", "A-.-B"]);
+    let why = PuzzleFile::read_level(LEVEL_PACK, 3).unwrap_err();
+    assert!(
+        why.contains("there's no level 3 (the pack has 2 levels)"),
+        "{}",
+        why
+    );
+    let why = PuzzleFile::read("3,0,1,2;0,1,2\n").unwrap_err();
+    assert!(why.contains("should have 2 flows, but has 1"), "{}", why);
+    let why = PuzzleFile::read("3,0,1,1;0,9\n").unwrap_err();
+    assert!(why.contains("a cell off the board"), "{}", why);
+}
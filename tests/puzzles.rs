@@ -0,0 +1,1116 @@
+extern crate flow_free_solver_rust;
+
+use flow_free_solver_rust::batch::find_puzzles;
+use flow_free_solver_rust::bench::{read_baseline, run_bench, to_baseline, BenchConfig};
+use flow_free_solver_rust::convert::PuzzleFile;
+use flow_free_solver_rust::explain::explain;
+use flow_free_solver_rust::generate::{generate, GenerateOptions};
+use flow_free_solver_rust::hint::{find_flow, find_move, Hint};
+use flow_free_solver_rust::logic::{deduce, Technique};
+use flow_free_solver_rust::observer::{Control, Observer};
+use flow_free_solver_rust::parser::parse_puzzle;
+use flow_free_solver_rust::puzzle::{CellId, FlowId, Puzzle, Unsolvable};
+use flow_free_solver_rust::rate::rate;
+use flow_free_solver_rust::solver::{
+    count_solutions, solve, Backend, CancelToken, Heuristic, Moves, Outcome, SolutionCount,
+    SolverOptions, Stats,
+};
+use flow_free_solver_rust::spill::{DiskFrontier, MAX_RUNS};
+use flow_free_solver_rust::verify::verify_solution;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Every puzzle has to be solved within this long
+const TIME_BUDGET: Duration = Duration::from_secs(20);
+
+// Puzzles that take far longer than the budget, only solved when asked for (cargo test -- --ignored)
+const SLOW_PUZZLES: [&str; 7] = [
+    "hex/15x15Mania_150.txt",
+    "standard/ExtremePack12x12_30.txt",
+    "warps/14x14Mania_27.txt",
+    "warps/14x14Mania_27_guided.txt",
+    "warps/14x14Mania_8.txt",
+    "warps/14x14Mania_9.txt",
+    "warps/JumboRainbow_150.txt",
+];
+const SLOW_TIME_BUDGET: Duration = Duration::from_secs(600);
+
+// Files that look like puzzles, but are only there to copy new puzzles from
+const TEMPLATES: [&str; 1] = ["warps/14x14Template.txt"];
+
+// Set this to save the solver's solutions as the expected ones, e.g. for new puzzles
+const UPDATE_SOLUTIONS: &str = "UPDATE_SOLUTIONS";
+
+fn puzzles_directory() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("puzzles")
+}
+
+// The bundled puzzles in the given directory (under puzzles), as their paths relative to the puzzles directory
+fn bundled_puzzles(directory: &str) -> Vec<String> {
+    let root = puzzles_directory();
+    let search = root.join(directory).to_string_lossy().to_string();
+    find_puzzles(&[search])
+        .unwrap()
+        .iter()
+        .map(|path| {
+            path.strip_prefix(&root)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .filter(|name| !TEMPLATES.contains(&name.as_str()))
+        .collect()
+}
+
+// The expected solution is stored next to the puzzle, e.g. standard/Regular5x5_1.solution.txt
+fn solution_path(name: &str) -> PathBuf {
+    let path = puzzles_directory().join(name);
+    let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+    path.with_file_name(format!("{}.solution.txt", stem))
+}
+
+fn load(name: &str) -> Result<Puzzle, String> {
+    let input =
+        fs::read_to_string(puzzles_directory().join(name)).map_err(|why| why.to_string())?;
+    parse_puzzle(&PuzzleFile::read(&input)?.to_text())
+}
+
+// Solve the puzzle, and check the solution against the rules and against the stored solution
+fn check_solution(name: &str, time_limit: Duration) -> Result<(), String> {
+    let puzzle = load(name)?;
+    let options = SolverOptions {
+        time_limit: Some(time_limit),
+        ..SolverOptions::default()
+    };
+    let result = solve(puzzle.clone(), &options, &mut ());
+    let solution = match result.outcome {
+        Outcome::Solved(solution) => solution.to_text(),
+        Outcome::Unsolvable => return Err(String::from("no solution found")),
+        Outcome::TimedOut => {
+            return Err(format!(
+                "not solved within {} seconds",
+                time_limit.as_secs()
+            ))
+        }
+        Outcome::Cancelled(_) => return Err(String::from("the search was cancelled")),
+        Outcome::Abandoned(_) => return Err(String::from("the beam ran out of states")),
+    };
+
+    // Checked without the solver, so a bug in the solver's own checks can't hide a bad solution
+    verify_solution(&puzzle, &solution).map_err(|why| format!("invalid solution: {}", why))?;
+
+    let stored = solution_path(name);
+    if env::var_os(UPDATE_SOLUTIONS).is_some() {
+        fs::write(&stored, &solution).map_err(|why| why.to_string())?;
+    }
+    match fs::read_to_string(&stored) {
+        Err(_) => Err(format!(
+            "no stored solution at {} (run with {}=1 to save it)",
+            stored.display(),
+            UPDATE_SOLUTIONS
+        )),
+        Ok(expected) if expected.replace("\r\n", "\n") != solution => Err(format!(
+            "the solution is different to the stored one:\n{}",
+            solution
+        )),
+        Ok(_) => Ok(()),
+    }
+}
+
+// Solve all the puzzles in the directory that are quick enough, and report every one that goes wrong
+fn check_directory(directory: &str) {
+    let failures: Vec<String> = bundled_puzzles(directory)
+        .iter()
+        .filter(|name| !SLOW_PUZZLES.contains(&name.as_str()))
+        .filter_map(|name| {
+            check_solution(name, TIME_BUDGET)
+                .err()
+                .map(|why| format!("{}: {}", name, why))
+        })
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn every_puzzle_parses() {
+    let failures: Vec<String> = bundled_puzzles("")
+        .iter()
+        .filter_map(|name| load(name).err().map(|why| format!("{}: {}", name, why)))
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn stored_solutions_are_valid() {
+    let mut checked = 0;
+    for name in bundled_puzzles("") {
+        if let Ok(solution) = fs::read_to_string(solution_path(&name)) {
+            let puzzle = load(&name).unwrap();
+            if let Err(why) = verify_solution(&puzzle, &solution) {
+                panic!("the stored solution to {} is invalid: {}", name, why);
+            }
+            checked += 1;
+        }
+    }
+    assert!(checked > 0, "no stored solutions were found");
+}
+
+// A board in the puzzle file layout, from one string of labels per row
+fn board(rows: &[&str]) -> String {
+    let rows: Vec<String> = rows
+        .iter()
+        .map(|row| row.chars().map(String::from).collect::<Vec<_>>().join("-"))
+        .collect();
+    let between = format!("\n{}\n", vec!["|"; rows[0].len() / 2 + 1].join(" "));
+    format!("STANDARD\n{}", rows.join(&between))
+}
+
+#[test]
+fn solutions_are_checked_a_cell_at_a_time() {
+    let puzzle = parse_puzzle(&board(&[
+        "A........",
+        ".........",
+        ".........",
+        ".........",
+        ".........",
+        ".........",
+        "........A",
+        "B.......B",
+        "C.......C",
+    ]))
+    .unwrap();
+
+    // One flow snaking across most of the board runs alongside itself, which used to be slow to rule out
+    let snake = board(&[
+        "AAAAAAAAA",
+        "AAAAAAAAA",
+        "AAAAAAAAA",
+        "AAAAAAAAA",
+        "AAAAAAAAA",
+        "AAAAAAAAA",
+        "AAAAAAAAA",
+        "BBBBBBBBB",
+        "CCCCCCCCC",
+    ]);
+    let start = Instant::now();
+    let why = verify_solution(&puzzle, &snake).unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(
+        why.contains("flow A can't be drawn as a single path"),
+        "{}",
+        why
+    );
+
+    // Spread out over the board, each cell has just the one way in and out, so the path can be followed
+    let puzzle = parse_puzzle(&board(&[
+        "A.......A",
+        "B.......B",
+        "C.......C",
+        "D.......D",
+        "E.......E",
+        "F.......F",
+        "G.......G",
+        "H.......H",
+        "I.......I",
+    ]))
+    .unwrap();
+    let rows: Vec<String> = "ABCDEFGHI"
+        .chars()
+        .map(|label| label.to_string().repeat(9))
+        .collect();
+    let rows: Vec<&str> = rows.iter().map(|row| row.as_str()).collect();
+    let solution = verify_solution(&puzzle, &board(&rows)).unwrap();
+    let flow_id = FlowId { index: 0 };
+    let path = solution.trace_path(flow_id).unwrap();
+    assert_eq!(path.len(), 9);
+    assert!(path
+        .windows(2)
+        .all(|pair| pair[1].index == pair[0].index + 1));
+
+    // Flows have to go straight across a bridge, so one coming in from the left can't leave as another on the right
+    let name = "bridges/Starter5x5_1.txt";
+    let solution = fs::read_to_string(solution_path(name)).unwrap();
+    let turned = solution.replace("D-D-*-D-D", "D-D-*-B-D");
+    assert_ne!(turned, solution);
+    let why = verify_solution(&load(name).unwrap(), &turned).unwrap_err();
+    assert_eq!(
+        why,
+        "row 5, column 5 (horizontal crossing of the bridge) is crossed by both D and B, which would have to turn on the bridge"
+    );
+}
+
+#[test]
+fn standard_puzzles_are_solved() {
+    check_directory("standard");
+}
+
+#[test]
+fn warps_puzzles_are_solved() {
+    check_directory("warps");
+}
+
+#[test]
+fn hex_puzzles_are_solved() {
+    check_directory("hex");
+}
+
+#[test]
+fn bridges_puzzles_are_solved() {
+    check_directory("bridges");
+}
+
+#[test]
+#[ignore]
+fn slow_puzzles_are_solved() {
+    let failures: Vec<String> = SLOW_PUZZLES
+        .iter()
+        .filter_map(|name| {
+            check_solution(name, SLOW_TIME_BUDGET)
+                .err()
+                .map(|why| format!("{}: {}", name, why))
+        })
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn cancelled_searches_stop_with_the_best_board_so_far() {
+    let name = "standard/ExtremePack12x12_30.txt";
+    let puzzle = load(name).unwrap();
+    let cancel = CancelToken::new();
+    let options = SolverOptions {
+        cancel: Some(cancel.clone()),
+        ..SolverOptions::default()
+    };
+
+    let start = Instant::now();
+    let result = thread::scope(|scope| {
+        let search = scope.spawn(|| solve(puzzle.clone(), &options, &mut ()));
+        thread::sleep(Duration::from_millis(200));
+        cancel.cancel();
+        search.join().unwrap()
+    });
+    // Far quicker than actually solving it
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    match result.outcome {
+        Outcome::Cancelled(best) => {
+            assert!(result.stats.states_visited > 0);
+            assert!(best.num_open_cells() < puzzle.num_open_cells());
+        }
+        outcome => panic!("{} wasn't cancelled: {:?}", name, outcome),
+    }
+}
+
+#[test]
+fn generated_puzzles_have_one_solution() {
+    let options = SolverOptions {
+        time_limit: Some(TIME_BUDGET),
+        ..SolverOptions::default()
+    };
+    for puzzle_type in ["standard", "warps", "hex", "bridges"] {
+        for seed in 0..3 {
+            let generate_options = GenerateOptions {
+                puzzle_type: puzzle_type.to_string(),
+                width: 6,
+                height: 6,
+                flows: 6,
+                seed,
+            };
+            let text = generate(&generate_options, &options).unwrap();
+            let puzzle = parse_puzzle(&text).unwrap();
+            let solution = match count_solutions(puzzle.clone(), &options) {
+                SolutionCount::One(solution) => solution,
+                count => panic!("{} puzzle from seed {}: {:?}", puzzle_type, seed, count),
+            };
+            verify_solution(&puzzle, &solution.to_text()).unwrap();
+            // Making it again from the same seed gives the same puzzle
+            assert_eq!(generate(&generate_options, &options).unwrap(), text);
+        }
+    }
+
+    // Boards with more cells than the solver can handle aren't made at all
+    let too_big = |puzzle_type: &str, width: usize, height: usize| GenerateOptions {
+        puzzle_type: puzzle_type.to_string(),
+        width,
+        height,
+        flows: 10,
+        seed: 0,
+    };
+    assert!(too_big("standard", 31, 32).check().is_ok());
+    assert!(too_big("standard", 32, 32).check().is_err());
+    // 31x32 fits, but not once the bridges are split in two
+    assert!(too_big("bridges", 31, 32).check().is_err());
+    assert!(too_big("warps", usize::MAX, 2).check().is_err());
+}
+
+#[test]
+fn harder_puzzles_are_rated_higher() {
+    let options = SolverOptions {
+        time_limit: Some(TIME_BUDGET),
+        ..SolverOptions::default()
+    };
+    let easy = rate(&load("standard/Regular5x5_1.txt").unwrap(), &options).unwrap();
+    let hard = rate(&load("standard/ExtremePack11x11_7.txt").unwrap(), &options).unwrap();
+
+    assert_eq!(easy.grade(), "easy");
+    assert!(hard.score() > easy.score());
+    assert!(hard.searched_cells() > easy.searched_cells());
+    assert!(matches!(hard.outcome, Outcome::Solved(_)));
+}
+
+#[test]
+fn logic_solves_puzzles_step_by_step() {
+    let options = SolverOptions {
+        time_limit: Some(TIME_BUDGET),
+        ..SolverOptions::default()
+    };
+    // The easy ones don't need any guessing, but the harder ones do
+    for (name, guessing) in [
+        ("standard/Regular5x5_1.txt", false),
+        ("bridges/Starter5x5_1.txt", false),
+        ("hex/Classic5x5_1.txt", false),
+        ("standard/Regular9x9_30.txt", true),
+        ("warps/Daily20190115_1.txt", true),
+    ] {
+        let puzzle = load(name).unwrap();
+        let deduction = deduce(&puzzle, &options);
+
+        assert!(deduction.is_solved(), "{} wasn't solved", name);
+        assert_eq!(deduction.steps.len() as u64, puzzle.num_open_cells());
+        assert_eq!(deduction.guesses().is_empty(), !guessing, "{}", name);
+        assert!(deduction.count(Technique::OnlyMove) > 0);
+        verify_solution(&puzzle, &deduction.board.to_text()).unwrap();
+    }
+}
+
+#[test]
+fn a_star_solves_puzzles() {
+    for weight in [1.0, 2.0] {
+        let options = SolverOptions {
+            backend: Backend::AStar,
+            weight,
+            time_limit: Some(TIME_BUDGET),
+            ..SolverOptions::default()
+        };
+        for name in [
+            "standard/Regular9x9_30.txt",
+            "bridges/Starter5x5_1.txt",
+            "hex/Classic5x5_8.txt",
+            "warps/Daily20190126_6.txt",
+        ] {
+            let puzzle = load(name).unwrap();
+            // Every open cell takes a move to fill, so the estimate can't be more than that
+            assert!(puzzle.min_moves_left().unwrap() <= puzzle.num_open_cells());
+
+            let mut costs = Costs::new(&puzzle);
+            match solve(puzzle.clone(), &options, &mut costs).outcome {
+                Outcome::Solved(solution) => {
+                    assert_eq!(solution.min_moves_left(), Some(0));
+                    verify_solution(&puzzle, &solution.to_text()).unwrap();
+                }
+                outcome => panic!("{} (weight {}): {:?}", name, weight, outcome),
+            }
+
+            // A move fills at least one cell, and can only bring its own flow's ends one cell closer, so the
+            // estimate never drops by more than the moves made. Plain A* must then look at states cheapest first
+            if weight == 1.0 {
+                assert!(
+                    costs.expanded.windows(2).all(|pair| pair[0] <= pair[1]),
+                    "{}: {:?}",
+                    name,
+                    costs.expanded
+                );
+            }
+        }
+    }
+
+    // The estimate is exact for a lone flow with a clear run, and there's no estimate for one that's cut off
+    let puzzle = parse_puzzle("STANDARD\nA-.-.-A").unwrap();
+    assert_eq!(puzzle.min_moves_left(), Some(2));
+    let puzzle = parse_puzzle("STANDARD\nA-B-A\n| | |\n.-B-.").unwrap();
+    assert_eq!(puzzle.min_moves_left(), None);
+}
+
+// Keeps the cost (moves made plus the fewest moves left) of every state expanded, in order
+struct Costs {
+    start_open: u64,
+    expanded: Vec<u64>,
+    // The open cells of every state expanded, in the same order
+    open: Vec<u64>,
+}
+
+impl Costs {
+    fn new(puzzle: &Puzzle) -> Costs {
+        Costs {
+            start_open: puzzle.num_open_cells(),
+            expanded: vec![],
+            open: vec![],
+        }
+    }
+}
+
+impl Observer for Costs {
+    fn state_expanded(&mut self, state: &Puzzle, _stats: &Stats) -> Control {
+        let left = state.min_moves_left().unwrap_or(u64::MAX / 2);
+        self.expanded
+            .push(self.start_open - state.num_open_cells() + left);
+        self.open.push(state.num_open_cells());
+        Control::Continue
+    }
+}
+
+#[test]
+fn beam_search_solves_easy_puzzles_and_owns_up_to_giving_up() {
+    let options = SolverOptions {
+        backend: Backend::Beam,
+        beam_width: 10,
+        time_limit: Some(TIME_BUDGET),
+        ..SolverOptions::default()
+    };
+    for name in [
+        "standard/Regular9x9_30.txt",
+        "standard/11x11Mania_150.txt",
+        "hex/Classic5x5_8.txt",
+        "warps/Daily20190126_6.txt",
+    ] {
+        let puzzle = load(name).unwrap();
+        let mut costs = Costs::new(&puzzle);
+        let result = solve(puzzle.clone(), &options, &mut costs);
+        match result.outcome {
+            Outcome::Solved(solution) => {
+                verify_solution(&puzzle, &solution.to_text()).unwrap();
+            }
+            outcome => panic!("{}: {:?}", name, outcome),
+        }
+        assert!(result.stats.max_frontier <= 10);
+
+        // Each move fills a cell, so the states at each depth have the same number of open cells
+        // No more than the width of them are looked at, and the warps puzzle needs the whole width somewhere
+        let mut at_depth: HashMap<u64, usize> = HashMap::new();
+        for open in costs.open.iter() {
+            *at_depth.entry(*open).or_default() += 1;
+        }
+        assert!(at_depth.values().all(|states| *states <= 10), "{}", name);
+        if name == "warps/Daily20190126_6.txt" {
+            assert!(at_depth.values().any(|states| *states == 10));
+        }
+    }
+
+    // Too narrow to find the way through, which doesn't mean there isn't one
+    let narrow = SolverOptions {
+        beam_width: 1,
+        ..options
+    };
+    let puzzle = load("standard/Regular9x9_30.txt").unwrap();
+    match solve(puzzle.clone(), &narrow, &mut ()).outcome {
+        Outcome::Abandoned(best) => assert!(best.num_open_cells() < puzzle.num_open_cells()),
+        outcome => panic!("Regular9x9_30 wasn't abandoned: {:?}", outcome),
+    }
+}
+
+#[test]
+fn ida_star_solves_puzzles_with_a_small_frontier() {
+    let options = SolverOptions {
+        backend: Backend::IdaStar,
+        time_limit: Some(TIME_BUDGET),
+        ..SolverOptions::default()
+    };
+    let mut passes = 0;
+    for name in [
+        "standard/Regular9x9_30.txt",
+        "bridges/Starter5x5_1.txt",
+        "hex/Classic5x5_8.txt",
+        "warps/Daily20190126_6.txt",
+    ] {
+        let puzzle = load(name).unwrap();
+        let mut costs = Costs::new(&puzzle);
+        let result = solve(puzzle.clone(), &options, &mut costs);
+        match result.outcome {
+            Outcome::Solved(solution) => {
+                verify_solution(&puzzle, &solution.to_text()).unwrap();
+            }
+            outcome => panic!("{}: {:?}", name, outcome),
+        }
+
+        // Each pass goes a little further than the last, and between them they cover every state visited
+        let thresholds = &result.stats.thresholds;
+        assert_eq!(thresholds[0].0, puzzle.min_moves_left().unwrap());
+        assert!(thresholds.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        passes = passes.max(thresholds.len());
+
+        // Every pass only looks at states within its threshold
+        let mut expanded = costs.expanded.iter();
+        for (threshold, states) in thresholds.iter() {
+            for cost in expanded.by_ref().take(*states as usize) {
+                assert!(cost <= threshold, "{}: {} > {}", name, cost, threshold);
+            }
+        }
+        assert!(thresholds.last().unwrap().0 <= puzzle.num_open_cells());
+        assert_eq!(
+            thresholds.iter().map(|(_, states)| states).sum::<u64>(),
+            result.stats.states_visited
+        );
+        assert!((result.stats.max_frontier as u64) < puzzle.num_open_cells());
+    }
+    assert!(passes > 1);
+
+    let puzzle = load("warps/14x14Template.txt").unwrap();
+    assert!(matches!(
+        solve(puzzle, &options, &mut ()).outcome,
+        Outcome::Unsolvable
+    ));
+}
+
+#[test]
+fn every_way_of_moving_solves_puzzles() {
+    for moves in [Moves::Endpoint, Moves::FlowEnds, Moves::WholePaths] {
+        let options = SolverOptions {
+            moves,
+            time_limit: Some(TIME_BUDGET),
+            ..SolverOptions::default()
+        };
+        for name in [
+            "standard/Regular9x9_30.txt",
+            "bridges/Starter5x5_1.txt",
+            "hex/Classic5x5_8.txt",
+            "warps/Daily20190126_6.txt",
+        ] {
+            let puzzle = load(name).unwrap();
+            match solve(puzzle.clone(), &options, &mut ()).outcome {
+                Outcome::Solved(solution) => {
+                    verify_solution(&puzzle, &solution.to_text()).unwrap();
+                }
+                outcome => panic!("{} ({}): {:?}", name, moves.name(), outcome),
+            }
+        }
+    }
+
+    // Every child of whole paths has one more flow finished
+    let puzzle = load("standard/Regular5x5_1.txt").unwrap();
+    let children = Moves::WholePaths.children(&puzzle);
+    assert!(!children.is_empty());
+    for child in children.iter() {
+        assert_eq!(child.num_complete(), puzzle.num_complete() + 1);
+    }
+
+    // A's corner only has one way to go, so that's the endpoint moved. But B has fewer moves between its two
+    // ends (2 and 2, to A's 1 and 4), so B is the flow the others move, from its first end
+    let puzzle =
+        parse_puzzle("STANDARD\nA-B-.-.\n| | | |\n.-.-.-.\n| | | |\n.-A-.-.\n| | | |\nB-.-.-.")
+            .unwrap();
+    let b_end = CellId { index: 1 };
+    assert_eq!(Moves::Endpoint.children(&puzzle).len(), 1);
+    let flow_ends = Moves::FlowEnds.children(&puzzle);
+    assert_eq!(flow_ends.len(), 2);
+    assert!(flow_ends
+        .iter()
+        .all(|child| child.get_cell(b_end).unwrap().previous.is_none()
+            && !child.get_cell(b_end).unwrap().is_endpoint));
+
+    // Whole paths are every way of drawing B in, one cell at a time
+    let whole_paths: HashSet<String> = Moves::WholePaths
+        .children(&puzzle)
+        .iter()
+        .map(|child| child.to_text())
+        .collect();
+    let mut drawn_in = HashSet::new();
+    let mut partial = vec![puzzle.clone()];
+    while let Some(state) = partial.pop() {
+        let end = state.get_flow(FlowId { index: 1 }).unwrap().get_endpoint(0);
+        for child in state.create_children_from(end) {
+            if child.num_complete() > 0 {
+                drawn_in.insert(child.to_text());
+            } else {
+                partial.push(child);
+            }
+        }
+    }
+    assert_eq!(whole_paths.len(), 19);
+    assert_eq!(whole_paths, drawn_in);
+}
+
+#[test]
+fn exact_cover_solves_small_puzzles_and_gives_up_on_big_ones() {
+    let options = SolverOptions {
+        backend: Backend::ExactCover,
+        time_limit: Some(TIME_BUDGET),
+        ..SolverOptions::default()
+    };
+    for name in [
+        "standard/Regular9x9_30.txt",
+        "bridges/Starter5x5_1.txt",
+        "hex/Classic5x5_8.txt",
+        "warps/WormPack_150.txt",
+    ] {
+        let puzzle = load(name).unwrap();
+        match solve(puzzle.clone(), &options, &mut ()).outcome {
+            Outcome::Solved(solution) => {
+                verify_solution(&puzzle, &solution.to_text()).unwrap();
+                // These only have the one solution, so it had better be the same one the other solvers find
+                let stored = fs::read_to_string(solution_path(name)).unwrap();
+                assert_eq!(stored.replace("\r\n", "\n"), solution.to_text(), "{}", name);
+            }
+            outcome => panic!("{}: {:?}", name, outcome),
+        }
+    }
+
+    // Far too many ways for the flows to go on a big board, so it owns up to not trying them all
+    let puzzle = load("standard/ExtremePack12x12_30.txt").unwrap();
+    assert!(matches!(
+        solve(puzzle, &options, &mut ()).outcome,
+        Outcome::Abandoned(_)
+    ));
+
+    let puzzle = load("warps/14x14Template.txt").unwrap();
+    assert!(matches!(
+        solve(puzzle, &options, &mut ()).outcome,
+        Outcome::Unsolvable
+    ));
+
+    // The only ways to fill this board run alongside A's own path, so they aren't routes. That leaves just three:
+    // straight across the top, through the middle row, and around the bottom, none of which fill the board
+    let puzzle = parse_puzzle("STANDARD\nA-.-A\n| | |\n.-.-.\n| | |\n.-.-.").unwrap();
+    let result = solve(puzzle, &options, &mut ());
+    assert!(matches!(result.outcome, Outcome::Unsolvable));
+    assert_eq!(result.stats.states_created, 3);
+}
+
+#[test]
+fn spilled_states_are_read_back_from_disk() {
+    let directory = env::temp_dir().join(format!("flow-solver-spill-test-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    for backend in [Backend::Greedy, Backend::AStar] {
+        let options = SolverOptions {
+            backend,
+            time_limit: Some(TIME_BUDGET),
+            spill_after: Some(4),
+            spill_directory: Some(directory.clone()),
+            ..SolverOptions::default()
+        };
+        for name in ["warps/Daily20190126_6.txt", "hex/Classic5x5_8.txt"] {
+            let puzzle = load(name).unwrap();
+            let result = solve(puzzle.clone(), &options, &mut ());
+            assert!(result.stats.spill_error.is_none(), "{}", name);
+            match result.outcome {
+                Outcome::Solved(solution) => {
+                    verify_solution(&puzzle, &solution.to_text()).unwrap();
+                }
+                outcome => panic!("{} ({}): {:?}", name, backend.name(), outcome),
+            }
+        }
+    }
+
+    // Something was spilled, and a state read back is the same as the one written
+    let puzzle = load("warps/Daily20190126_6.txt").unwrap();
+    let options = SolverOptions {
+        spill_after: Some(4),
+        spill_directory: Some(directory.clone()),
+        ..SolverOptions::default()
+    };
+    let stats = solve(puzzle.clone(), &options, &mut ()).stats;
+    assert!(stats.states_spilled > 0);
+    // What was left on disk when it was solved still counts towards the frontier
+    assert!(stats.frontier_on_disk > 0);
+    assert!(stats.frontier > stats.frontier_on_disk);
+    let state = &puzzle.create_children()[0];
+    let mut bytes = vec![];
+    state.write_state(&mut bytes);
+    assert_eq!(bytes.len(), puzzle.state_size());
+    assert_eq!(puzzle.read_state(&bytes).to_text(), state.to_text());
+
+    // Everything is cleaned up afterwards
+    assert_eq!(fs::read_dir(&directory).unwrap().count(), 0);
+    fs::remove_dir(&directory).unwrap();
+}
+
+#[test]
+fn hints_follow_the_solution_until_the_puzzle_is_done() {
+    let options = SolverOptions {
+        time_limit: Some(TIME_BUDGET),
+        ..SolverOptions::default()
+    };
+    for name in ["standard/Regular9x9_30.txt", "warps/Daily20190126_6.txt"] {
+        let mut puzzle = load(name).unwrap();
+        let solution = match solve(puzzle.clone(), &options, &mut ()).outcome {
+            Outcome::Solved(solution) => solution,
+            outcome => panic!("{}: {:?}", name, outcome),
+        };
+        let solution_flow = |index: usize| solution.get_cells()[index].flow_id;
+
+        // Every hinted cell is filled the same way as in the solution, and following them finishes the puzzle
+        let mut hints = 0;
+        while let Some(hint) = find_move(&puzzle, &options).unwrap() {
+            match &hint {
+                Hint::Move { from, to, .. } => {
+                    assert_eq!(
+                        solution_flow(to.index),
+                        puzzle.get_cell(*from).unwrap().flow_id
+                    );
+                }
+                Hint::Flow { flow_id, path } => {
+                    for cell in path.iter() {
+                        assert_eq!(solution_flow(cell.index), Some(*flow_id));
+                    }
+                }
+            }
+            hint.apply(&mut puzzle);
+            hints += 1;
+            assert!(
+                hints <= puzzle.num_cells(),
+                "{}: the hints never finish",
+                name
+            );
+        }
+        assert!(puzzle.is_complete(), "{}: no hint before it was done", name);
+        verify_solution(&load(name).unwrap(), &puzzle.to_text()).unwrap();
+
+        // A whole flow is one from the solution, from one of its dots to the other
+        let puzzle = load(name).unwrap();
+        match find_flow(&puzzle, &options).unwrap() {
+            Some(Hint::Flow { flow_id, path }) => {
+                assert_eq!(Some(path), solution.trace_path(flow_id));
+            }
+            hint => panic!("{}: {:?}", name, hint),
+        }
+    }
+
+    // A search that gives up is reported, not taken to mean there's no solution
+    let puzzle = load("standard/ExtremePack12x12_30.txt").unwrap();
+    let options = SolverOptions {
+        state_limit: Some(1),
+        ..SolverOptions::default()
+    };
+    assert!(matches!(
+        find_flow(&puzzle, &options),
+        Err(Outcome::TimedOut)
+    ));
+}
+
+#[test]
+fn explanations_point_at_the_smallest_impossible_part() {
+    let options = SolverOptions {
+        time_limit: Some(TIME_BUDGET),
+        ..SolverOptions::default()
+    };
+    let explain_text = |text: &str| {
+        let puzzle = parse_puzzle(text).unwrap();
+        assert!(matches!(
+            solve(puzzle.clone(), &options, &mut ()).outcome,
+            Outcome::Unsolvable
+        ));
+        let explanation = explain(&puzzle, &options).unwrap().unwrap();
+        assert!(explanation.minimal);
+        (puzzle, explanation)
+    };
+
+    // A and B have to cross, though either one could be connected on its own
+    let (_, explanation) = explain_text("STANDARD\nA-.-B\n| | |\n.-.-.\n| | |\nB-.-A");
+    assert_eq!(
+        explanation.flows,
+        [FlowId { index: 0 }, FlowId { index: 1 }]
+    );
+    assert!(explanation.cells.is_empty());
+
+    // A path between opposite corners of a 2x2 board can only fill one of the other two cells
+    let (puzzle, explanation) = explain_text("STANDARD\nA-.\n| |\n.-A");
+    let mut cells: Vec<(usize, usize)> = explanation
+        .cells
+        .iter()
+        .map(|id| {
+            let cell = puzzle.get_cell(*id).unwrap();
+            (cell.row, cell.col)
+        })
+        .collect();
+    cells.sort();
+    assert_eq!(cells, [(0, 2), (2, 0)]);
+
+    // Nothing to explain about a puzzle that can be solved, and giving up isn't the same as it being impossible
+    assert!(
+        explain(&load("standard/Regular5x5_1.txt").unwrap(), &options)
+            .unwrap()
+            .is_none()
+    );
+    let options = SolverOptions {
+        state_limit: Some(1),
+        ..SolverOptions::default()
+    };
+    let puzzle = load("standard/ExtremePack12x12_30.txt").unwrap();
+    assert!(matches!(explain(&puzzle, &options), Err(Outcome::TimedOut)));
+}
+
+#[test]
+fn bench_baselines_have_a_column_for_every_heading() {
+    let paths: Vec<PathBuf> = ["standard/Regular5x5_1.txt", "hex/Classic5x5_8.txt"]
+        .iter()
+        .map(|name| puzzles_directory().join(name))
+        .collect();
+    let configs = [
+        BenchConfig {
+            backend: Backend::Greedy,
+            heuristic: Heuristic::Default,
+            moves: Moves::Endpoint,
+        },
+        BenchConfig {
+            backend: Backend::AStar,
+            heuristic: Heuristic::Default,
+            moves: Moves::FlowEnds,
+        },
+    ];
+    let options = SolverOptions {
+        time_limit: Some(TIME_BUDGET),
+        ..SolverOptions::default()
+    };
+    let results = run_bench(&paths, &configs, 2, &options, |_| {});
+    assert_eq!(results.len(), paths.len() * configs.len());
+    assert!(results.iter().all(|result| result.times.len() == 2));
+
+    let baseline = to_baseline(&results);
+    let mut lines = baseline.lines();
+    let columns = lines.next().unwrap().split(',').count();
+    for line in lines {
+        assert_eq!(line.split(',').count(), columns, "{}", line);
+    }
+
+    // And they read back in as they were written
+    let entries = read_baseline(&baseline).unwrap();
+    assert_eq!(entries.len(), results.len());
+    for (entry, result) in entries.iter().zip(results.iter()) {
+        assert_eq!(entry.puzzle, result.path.display().to_string());
+        assert_eq!(entry.solver, result.config.backend.name());
+        assert_eq!(entry.moves, result.config.moves.name());
+        assert_eq!(entry.outcome, "solved");
+        assert_eq!(
+            entry.states_visited,
+            result.stats.as_ref().unwrap().states_visited
+        );
+    }
+}
+
+// Stops the search after it's looked at so many states, keeping every reason a child was pruned for
+struct StopAfter {
+    states: u64,
+    expanded: u64,
+    pruned: Vec<Unsolvable>,
+    ended_cancelled: bool,
+}
+
+impl Observer for StopAfter {
+    fn state_expanded(&mut self, _state: &Puzzle, _stats: &Stats) -> Control {
+        self.expanded += 1;
+        if self.expanded >= self.states {
+            Control::Stop
+        } else {
+            Control::Continue
+        }
+    }
+
+    fn child_pruned(&mut self, child: &Puzzle, reason: &Unsolvable, _stats: &Stats) {
+        assert_eq!(child.is_solvable(), reason.code());
+        self.pruned.push(reason.clone());
+    }
+
+    fn search_ended(&mut self, outcome: &Outcome, _last_state: Option<&Puzzle>, _stats: &Stats) {
+        self.ended_cancelled = matches!(outcome, Outcome::Cancelled(_));
+    }
+}
+
+#[test]
+fn observers_hear_why_children_are_pruned_and_can_stop_the_search() {
+    let puzzle = load("standard/ExtremePack12x12_30.txt").unwrap();
+    for backend in [Backend::Greedy, Backend::Beam, Backend::IdaStar] {
+        let mut observer = StopAfter {
+            states: 200,
+            expanded: 0,
+            pruned: vec![],
+            ended_cancelled: false,
+        };
+        let options = SolverOptions {
+            backend,
+            ..SolverOptions::default()
+        };
+        let result = solve(puzzle.clone(), &options, &mut observer);
+        assert!(
+            matches!(result.outcome, Outcome::Cancelled(_)),
+            "{}: {:?}",
+            backend.name(),
+            result.outcome
+        );
+        assert!(observer.ended_cancelled);
+        assert_eq!(observer.expanded, 200);
+
+        // Every discarded child was heard about, for the same reason the stats counted it under
+        let stats = &result.stats;
+        let count = |matches: fn(&Unsolvable) -> bool| {
+            observer
+                .pruned
+                .iter()
+                .filter(|reason| matches(reason))
+                .count() as u64
+        };
+        assert!(!observer.pruned.is_empty(), "{}", backend.name());
+        assert_eq!(observer.pruned.len() as u64, stats.children_discarded);
+        assert_eq!(
+            count(|reason| matches!(reason, Unsolvable::NoChildren(_))),
+            stats.discarded_no_children
+        );
+        assert_eq!(
+            count(|reason| matches!(reason, Unsolvable::DeadEnd(_))),
+            stats.discarded_dead_end
+        );
+        assert_eq!(
+            count(|reason| matches!(reason, Unsolvable::Pool(_))),
+            stats.discarded_pools
+        );
+        assert_eq!(
+            count(|reason| matches!(reason, Unsolvable::PathBlocked(_))),
+            stats.discarded_blocked
+        );
+        assert_eq!(
+            count(|reason| matches!(reason, Unsolvable::Region(_))),
+            stats.discarded_cc
+        );
+    }
+}
+
+// Some different states of the puzzle, each with a score, to spill
+fn scored_states(puzzle: &Puzzle, count: usize) -> Vec<(u64, Puzzle)> {
+    let mut states = vec![];
+    let mut next = vec![puzzle.clone()];
+    while states.len() < count {
+        let state = next.remove(0);
+        next.extend(state.create_children());
+        // Scores repeat now and then, so ties are spilled too
+        states.push(((states.len() * 37 % 101) as u64, state));
+    }
+    states
+}
+
+// The states with the best first, as the frontier wants them spilled
+fn best_first(states: &[(u64, Puzzle)]) -> Vec<(u64, Puzzle)> {
+    let mut states = states.to_vec();
+    states.sort_by_key(|(score, _)| Reverse(*score));
+    states
+}
+
+// Take everything off the disk, checking it comes off best first
+fn pop_all(frontier: &mut DiskFrontier) -> Vec<(u64, String)> {
+    let mut popped: Vec<(u64, String)> = vec![];
+    while let Some((score, state)) = frontier.pop().unwrap() {
+        if let Some((last, _)) = popped.last() {
+            assert!(score <= *last, "{} came off after {}", score, last);
+        }
+        popped.push((score, state.to_text()));
+    }
+    assert!(frontier.is_empty());
+    popped
+}
+
+fn sorted_texts(states: &[(u64, Puzzle)]) -> Vec<(u64, String)> {
+    let mut texts: Vec<(u64, String)> = states
+        .iter()
+        .map(|(score, state)| (*score, state.to_text()))
+        .collect();
+    texts.sort();
+    texts
+}
+
+// A directory of its own for each test, with the one the frontier makes inside it
+fn spill_directories(test: &str) -> (PathBuf, impl Fn() -> PathBuf) {
+    let parent = env::temp_dir().join(format!("flow-solver-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&parent).unwrap();
+    let inside = parent.clone();
+    (parent, move || {
+        fs::read_dir(&inside)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path()
+    })
+}
+
+fn file_names(directory: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn spilled_runs_come_back_best_first() {
+    let (parent, frontier_directory) = spill_directories("spill-runs");
+    let puzzle = load("standard/Regular9x9_30.txt").unwrap();
+    let states = scored_states(&puzzle, 400);
+    let mut frontier = DiskFrontier::new(&parent, &puzzle).unwrap();
+
+    // More runs than are kept, so some of them get merged along the way
+    for run in states.chunks(10) {
+        frontier.spill(&best_first(run)).unwrap();
+        assert!(file_names(&frontier_directory()).len() <= MAX_RUNS);
+    }
+    assert_eq!(frontier.len(), states.len());
+    assert_eq!(
+        frontier.peek_score(),
+        states.iter().map(|(score, _)| *score).max()
+    );
+    let mut popped = pop_all(&mut frontier);
+    popped.sort();
+    assert_eq!(popped, sorted_texts(&states));
+    assert!(file_names(&frontier_directory()).is_empty());
+
+    drop(frontier);
+    assert_eq!(fs::read_dir(&parent).unwrap().count(), 0);
+    fs::remove_dir(&parent).unwrap();
+}
+
+#[test]
+fn failed_spills_leave_the_disk_as_it_was() {
+    let (parent, frontier_directory) = spill_directories("spill-errors");
+    let puzzle = load("standard/Regular9x9_30.txt").unwrap();
+    let states = scored_states(&puzzle, 200);
+    let runs: Vec<Vec<(u64, Puzzle)>> = states.chunks(10).map(best_first).collect();
+    let mut frontier = DiskFrontier::new(&parent, &puzzle).unwrap();
+    let directory = frontier_directory();
+    // Something already at the path a run is going to be written to makes writing it fail
+    let block = |run: usize| fs::create_dir(directory.join(format!("run-{}.bin", run))).unwrap();
+
+    let mut spilled = vec![];
+    for run in &runs[..3] {
+        frontier.spill(run).unwrap();
+        spilled.extend(run.iter().cloned());
+    }
+    block(4);
+    let files = file_names(&directory);
+    assert!(frontier.spill(&runs[3]).is_err());
+    assert_eq!(frontier.len(), spilled.len());
+    assert_eq!(file_names(&directory), files);
+
+    // Filling up the runs, so the next spill has to merge some of them first, and the merged run can't be written
+    let mut written = 4;
+    for run in &runs[3..MAX_RUNS] {
+        frontier.spill(run).unwrap();
+        spilled.extend(run.iter().cloned());
+        written += 1;
+    }
+    block(written + 1);
+    let files = file_names(&directory);
+    assert!(frontier.spill(&runs[MAX_RUNS]).is_err());
+    assert_eq!(frontier.len(), spilled.len());
+    assert_eq!(file_names(&directory), files);
+
+    // Nothing was lost or written twice
+    let mut popped = pop_all(&mut frontier);
+    popped.sort();
+    assert_eq!(popped, sorted_texts(&spilled));
+
+    drop(frontier);
+    fs::remove_dir(&parent).unwrap();
+}
@@ -0,0 +1,183 @@
+extern crate flow_free_solver_rust;
+
+use flow_free_solver_rust::convert::PuzzleFile;
+use flow_free_solver_rust::gif::{render_gif, solution_frames, FINAL_DELAY, FRAME_DELAY};
+use flow_free_solver_rust::parser::parse_puzzle;
+use flow_free_solver_rust::puzzle::Puzzle;
+use flow_free_solver_rust::solver::{solve, Outcome, SolverOptions};
+use flow_free_solver_rust::svg::render_svg;
+use flow_free_solver_rust::terminal::render_terminal;
+use std::fs;
+use std::path::Path;
+
+fn load(name: &str) -> Puzzle {
+    let input = fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("puzzles")
+            .join(name),
+    )
+    .unwrap();
+    parse_puzzle(&PuzzleFile::read(&input).unwrap().to_text()).unwrap()
+}
+
+fn solved(name: &str) -> Puzzle {
+    match solve(load(name), &SolverOptions::default(), &mut ()).outcome {
+        Outcome::Solved(solution) => solution,
+        outcome => panic!("{}: {:?}", name, outcome),
+    }
+}
+
+#[test]
+fn svg_has_a_cell_for_every_cell_and_a_labelled_dot_for_every_dot() {
+    let solution = solved("standard/Regular5x5_1.txt");
+    let svg = render_svg(&solution);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.ends_with("</svg>\n"));
+    assert_eq!(svg.matches("<polygon").count(), solution.num_cells());
+    assert_eq!(svg.matches("<circle").count(), 2 * solution.flows.len());
+    assert_eq!(svg.matches("<text").count(), 2 * solution.flows.len());
+
+    // Labels are escaped, so they can't break the SVG
+    let puzzle = parse_puzzle("STANDARD\n{<&>}-.-{<&>}").unwrap();
+    let svg = render_svg(&puzzle);
+    assert!(svg.contains(">&lt;&amp;&gt;</text>"), "{}", svg);
+}
+
+// The delay of each frame in the GIF, checking the blocks it's made of are well formed along the way
+fn gif_frame_delays(gif: &[u8]) -> Vec<u16> {
+    assert_eq!(&gif[..6], b"GIF89a");
+    // The header, then the 256 color global color table
+    assert_eq!(gif[10] & 0x87, 0x87);
+    let mut i = 13 + 3 * 256;
+    let skip_sub_blocks = |mut i: usize| {
+        while gif[i] != 0 {
+            i += gif[i] as usize + 1;
+        }
+        i + 1
+    };
+
+    let mut delays = vec![];
+    let mut delay = None;
+    loop {
+        match gif[i] {
+            // A graphic control extension, for the frame after it
+            0x21 if gif[i + 1] == 0xF9 => {
+                delay = Some(u16::from_le_bytes([gif[i + 4], gif[i + 5]]));
+                i = skip_sub_blocks(i + 2);
+            }
+            0x21 => i = skip_sub_blocks(i + 2),
+            // An image, without a local color table, then its LZW code size and data
+            0x2C => {
+                assert_eq!(gif[i + 9] & 0x80, 0);
+                delays.push(delay.take().expect("a frame without a delay"));
+                i = skip_sub_blocks(i + 11);
+            }
+            0x3B => break,
+            block => panic!("unexpected block {:#x} at byte {}", block, i),
+        }
+    }
+    assert_eq!(i + 1, gif.len());
+    delays
+}
+
+#[test]
+fn gif_has_a_frame_for_every_change() {
+    let puzzle = load("standard/Regular5x5_1.txt");
+    let solution = solved("standard/Regular5x5_1.txt");
+
+    // The solution drawn in a cell at a time, starting from the dots
+    let drawn_in = solution_frames(&solution);
+    assert_eq!(
+        drawn_in.len(),
+        1 + solution.num_cells() - 2 * solution.flows.len()
+    );
+    let delays = gif_frame_delays(&render_gif(&puzzle, &[], Some(&solution)));
+    assert_eq!(delays.len(), drawn_in.len());
+    assert!(delays[..delays.len() - 1]
+        .iter()
+        .all(|delay| *delay == FRAME_DELAY));
+    assert_eq!(delays.last(), Some(&FINAL_DELAY));
+
+    // States that don't change anything show the frame before for longer, instead of adding a frame
+    let delays = gif_frame_delays(&render_gif(
+        &puzzle,
+        &[puzzle.clone(), puzzle.clone(), puzzle.clone()],
+        None,
+    ));
+    assert_eq!(delays, [FINAL_DELAY]);
+
+    // With nothing recorded and no solution, there's still the puzzle to show
+    let delays = gif_frame_delays(&render_gif(&puzzle, &[], None));
+    assert_eq!(delays, [FINAL_DELAY]);
+}
+
+// The text without the ANSI color codes
+fn strip_colors(text: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            chars.by_ref().find(|c| *c == 'm');
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+#[test]
+fn terminal_paths_join_up_in_the_puzzle_file_layout() {
+    let solution = solved("standard/Regular5x5_1.txt");
+    let output = render_terminal(&solution);
+    assert!(output.contains("\u{1b}["));
+    let lines: Vec<Vec<char>> = strip_colors(&output)
+        .trim_end_matches('\n')
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect();
+    let expected = fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("puzzles/standard/Regular5x5_1.txt"),
+    )
+    .unwrap();
+    let expected: Vec<&str> = expected.lines().skip(1).collect();
+    assert_eq!(lines.len(), expected.len());
+
+    // Cells are on the even rows and columns, with the connectors between them
+    let at = |row: usize, col: usize| lines.get(row).and_then(|line| line.get(col)).copied();
+    let mut dots = 0;
+    for (row, line) in lines.iter().enumerate() {
+        assert_eq!(
+            line.len(),
+            expected[row].chars().count(),
+            "line {}",
+            row + 1
+        );
+        for (col, c) in line.iter().enumerate() {
+            if row % 2 == 1 || col % 2 == 1 {
+                assert!([' ', '─', '│'].contains(c), "{:?} at {}, {}", c, row, col);
+                continue;
+            }
+            if c.is_ascii_alphabetic() {
+                dots += 1;
+                continue;
+            }
+            // Every arm of a path goes through a connector to the next cell
+            let arms = [
+                ("╵└│┘┴├┤┼", row.checked_sub(1).map(|r| (r, col)), '│'),
+                ("╶└┌─┴├┬┼", Some((row, col + 1)), '─'),
+                ("╷│┌┐├┤┬┼", Some((row + 1, col)), '│'),
+                ("╴┘─┐┴┤┬┼", col.checked_sub(1).map(|c| (row, c)), '─'),
+            ];
+            let mut count = 0;
+            for (characters, connector, drawn) in arms {
+                if characters.contains(*c) {
+                    let (r, c) = connector.unwrap();
+                    assert_eq!(at(r, c), Some(drawn), "at {}, {}", row, col);
+                    count += 1;
+                }
+            }
+            assert_eq!(count, 2, "{:?} at {}, {} isn't part of a path", c, row, col);
+        }
+    }
+    assert_eq!(dots, 2 * solution.flows.len());
+}